@@ -0,0 +1,456 @@
+/// =========================================================================
+/// Módulo: Archivos adjuntos de los registros
+///
+/// Un registro puede tener cualquier número de archivos adjuntos (facturas
+/// en PDF, cartas de garantía, varias fotos), sin necesidad de columnas BLOB:
+/// - Se guardan en la tabla interna `_unea_attachments`, dentro de la propia
+///   base de datos, de modo que viajan con el archivo `.db`
+/// - De cada archivo se guarda el nombre, tipo MIME, tamaño, huella SHA-256,
+///   fecha de carga y quién lo cargó
+/// - Comandos para agregar desde una ruta, listar, extraer (para abrirlo o
+///   guardarlo) y eliminar; agregar y eliminar se pueden deshacer
+/// - Un adjunto eliminado solo se marca (`deleted_at`): el diario de deshacer
+///   guarda la marca y no el contenido. Los marcados se borran cuando ya no
+///   se pueden recuperar con deshacer
+/// - Al borrar definitivamente un registro se eliminan sus adjuntos en la
+///   misma operación; al eliminar la tabla, también
+///
+/// `get_record_details` incluye la lista de adjuntos del registro.
+/// =========================================================================
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::Local;
+use rusqlite::types::Value as SqlValue;
+use rusqlite::{Connection, OptionalExtension, TransactionBehavior};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use tauri::State;
+
+use crate::auditoria::{current_user, log_operation, pk_text};
+use crate::busqueda_tabla::primary_key_column;
+use crate::codec_valores::json_to_sql;
+use crate::database_manager::AppState;
+use crate::deshacer::{read_row, RowChange, RowImage, UndoState};
+use crate::protocolo_imagenes::sniff_image_mime;
+
+/// Tabla interna con los archivos
+pub(crate) const ATTACHMENTS_TABLE: &str = "_unea_attachments";
+/// Fecha en que se eliminó el adjunto (NULL = vigente)
+const DELETED_AT_COLUMN: &str = "deleted_at";
+/// Tamaño máximo de un adjunto (50 MB)
+const MAX_ATTACHMENT_BYTES: u64 = 50 * 1024 * 1024;
+
+/// Adjunto tal como se envía al frontend (sin el contenido)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AttachmentInfo {
+    pub id: i64,
+    pub table_name: String,
+    /// Clave primaria del registro, como texto
+    pub record_pk: String,
+    pub file_name: String,
+    pub mime_type: String,
+    /// Tamaño en bytes
+    pub size: i64,
+    /// Huella SHA-256 en hexadecimal
+    pub sha256: String,
+    /// Fecha de carga (RFC 3339)
+    pub uploaded_at: String,
+    pub uploaded_by: String,
+}
+
+fn ensure_attachments_table(conn: &Connection) -> Result<(), String> {
+    conn.execute_batch(&format!(
+        "CREATE TABLE IF NOT EXISTS \"{table}\" (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            table_name TEXT NOT NULL,
+            record_pk TEXT NOT NULL,
+            file_name TEXT NOT NULL,
+            mime_type TEXT NOT NULL,
+            size INTEGER NOT NULL,
+            sha256 TEXT NOT NULL,
+            uploaded_at TEXT NOT NULL,
+            uploaded_by TEXT NOT NULL,
+            data BLOB NOT NULL,
+            deleted_at TEXT
+        );
+        CREATE INDEX IF NOT EXISTS \"{table}_record\" ON \"{table}\" (table_name, record_pk);",
+        table = ATTACHMENTS_TABLE
+    )).map_err(|e| format!("Error al crear la tabla de adjuntos: {}", e))?;
+
+    // Tablas creadas antes de que los adjuntos eliminados se conservaran para deshacer
+    let has_deleted_at: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM pragma_table_info(?) WHERE name = ?",
+        [ATTACHMENTS_TABLE, DELETED_AT_COLUMN],
+        |row| row.get(0),
+    ).map_err(|e| format!("Error al consultar la tabla de adjuntos: {}", e))?;
+    if has_deleted_at == 0 {
+        conn.execute(
+            &format!("ALTER TABLE \"{}\" ADD COLUMN {} TEXT", ATTACHMENTS_TABLE, DELETED_AT_COLUMN),
+            [],
+        ).map_err(|e| format!("Error al actualizar la tabla de adjuntos: {}", e))?;
+    }
+    Ok(())
+}
+
+fn has_attachments_table(conn: &Connection) -> Result<bool, String> {
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name=?",
+        [ATTACHMENTS_TABLE],
+        |row| row.get(0),
+    ).map_err(|e| format!("Error al consultar los adjuntos: {}", e))?;
+    Ok(count > 0)
+}
+
+const ATTACHMENT_COLUMNS: &str = "id, table_name, record_pk, file_name, mime_type, size, sha256, uploaded_at, uploaded_by";
+
+fn row_to_attachment(row: &rusqlite::Row) -> rusqlite::Result<AttachmentInfo> {
+    Ok(AttachmentInfo {
+        id: row.get(0)?,
+        table_name: row.get(1)?,
+        record_pk: row.get(2)?,
+        file_name: row.get(3)?,
+        mime_type: row.get(4)?,
+        size: row.get(5)?,
+        sha256: row.get(6)?,
+        uploaded_at: row.get(7)?,
+        uploaded_by: row.get(8)?,
+    })
+}
+
+fn load_attachment(conn: &Connection, attachment_id: i64) -> Result<AttachmentInfo, String> {
+    ensure_attachments_table(conn)?;
+    conn.query_row(
+        &format!("SELECT {} FROM \"{}\" WHERE id = ? AND deleted_at IS NULL", ATTACHMENT_COLUMNS, ATTACHMENTS_TABLE),
+        [attachment_id],
+        row_to_attachment,
+    )
+    .optional()
+    .map_err(|e| format!("Error al leer el adjunto: {}", e))?
+    .ok_or_else(|| format!("No se encontró el adjunto con id {}", attachment_id))
+}
+
+/// Clave primaria del registro como texto (la misma forma que usa la bitácora)
+fn record_key(pk_value: &Value) -> Result<String, String> {
+    pk_text(&json_to_sql(pk_value)?).ok_or_else(|| "Falta la clave primaria del registro".to_string())
+}
+
+/// Adjuntos de un registro, del más reciente al más antiguo
+pub(crate) fn record_attachments(conn: &Connection, table_name: &str, pk_value: &Value) -> Result<Vec<AttachmentInfo>, String> {
+    if !has_attachments_table(conn)? || pk_value.is_null() {
+        return Ok(Vec::new());
+    }
+    ensure_attachments_table(conn)?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM \"{}\" WHERE table_name = ? AND record_pk = ? AND deleted_at IS NULL
+         ORDER BY uploaded_at DESC, id DESC",
+        ATTACHMENT_COLUMNS, ATTACHMENTS_TABLE
+    )).map_err(|e| format!("Error al preparar la consulta de adjuntos: {}", e))?;
+    let attachments = stmt.query_map([table_name.to_string(), record_key(pk_value)?], row_to_attachment)
+        .map_err(|e| format!("Error al consultar los adjuntos: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Error al leer los adjuntos: {}", e))?;
+    Ok(attachments)
+}
+
+/// Cambio de la marca de eliminación de un adjunto, para el diario de deshacer.
+/// La imagen solo lleva el id y la marca: deshacer y rehacer la ponen o la quitan.
+fn tombstone_change(attachment_id: i64, before: Option<&str>, after: Option<&str>) -> RowChange {
+    let image = |deleted_at: Option<&str>| {
+        let mut image = RowImage::new();
+        image.insert("id".to_string(), SqlValue::Integer(attachment_id));
+        image.insert(
+            DELETED_AT_COLUMN.to_string(),
+            deleted_at.map_or(SqlValue::Null, |d| SqlValue::Text(d.to_string())),
+        );
+        Some(image)
+    };
+    RowChange {
+        table_name: ATTACHMENTS_TABLE.to_string(),
+        pk_column: "id".to_string(),
+        before: image(before),
+        after: image(after),
+    }
+}
+
+/// Borra los adjuntos eliminados que ya no se pueden recuperar con deshacer
+fn purge_discarded(conn: &Connection, undo_state: &UndoState, db_name: &str) -> Result<(), String> {
+    let reachable = undo_state.journal_row_keys(db_name, ATTACHMENTS_TABLE);
+    let mut stmt = conn.prepare(&format!("SELECT id FROM \"{}\" WHERE deleted_at IS NOT NULL", ATTACHMENTS_TABLE))
+        .map_err(|e| format!("Error al preparar la consulta de adjuntos: {}", e))?;
+    let discarded = stmt.query_map([], |row| row.get::<_, i64>(0))
+        .map_err(|e| format!("Error al consultar los adjuntos: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Error al leer los adjuntos: {}", e))?;
+
+    for id in discarded {
+        if reachable.contains(&SqlValue::Integer(id)) {
+            continue;
+        }
+        conn.execute(&format!("DELETE FROM \"{}\" WHERE id = ?", ATTACHMENTS_TABLE), [id])
+            .map_err(|e| format!("Error al eliminar el adjunto: {}", e))?;
+    }
+    Ok(())
+}
+
+/// Elimina los adjuntos de un registro que se borra definitivamente. Devuelve
+/// los cambios para incluirlos en la misma operación de deshacer.
+pub(crate) fn discard_record_attachments(
+    conn: &Connection,
+    table_name: &str,
+    pk_value: &SqlValue,
+) -> Result<Vec<RowChange>, String> {
+    if !has_attachments_table(conn)? {
+        return Ok(Vec::new());
+    }
+    let Some(record_pk) = pk_text(pk_value) else {
+        return Ok(Vec::new());
+    };
+    ensure_attachments_table(conn)?;
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT id FROM \"{}\" WHERE table_name = ? AND record_pk = ? AND deleted_at IS NULL",
+        ATTACHMENTS_TABLE
+    )).map_err(|e| format!("Error al preparar la consulta de adjuntos: {}", e))?;
+    let ids = stmt.query_map([table_name, record_pk.as_str()], |row| row.get::<_, i64>(0))
+        .map_err(|e| format!("Error al consultar los adjuntos: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Error al leer los adjuntos: {}", e))?;
+    if ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let deleted_at = Local::now().to_rfc3339();
+    conn.execute(
+        &format!(
+            "UPDATE \"{}\" SET deleted_at = ? WHERE table_name = ? AND record_pk = ? AND deleted_at IS NULL",
+            ATTACHMENTS_TABLE
+        ),
+        [deleted_at.as_str(), table_name, record_pk.as_str()],
+    ).map_err(|e| format!("Error al eliminar los adjuntos: {}", e))?;
+
+    Ok(ids.into_iter().map(|id| tombstone_change(id, None, Some(&deleted_at))).collect())
+}
+
+/// Tipo MIME por el contenido; si no se reconoce, por la extensión
+fn detect_mime(bytes: &[u8], file_name: &str) -> String {
+    if let Some(mime) = sniff_image_mime(bytes) {
+        return mime.to_string();
+    }
+    if bytes.starts_with(b"%PDF") {
+        return "application/pdf".to_string();
+    }
+    let extension = Path::new(file_name)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "pdf" => "application/pdf",
+        "txt" => "text/plain",
+        "csv" => "text/csv",
+        "doc" => "application/msword",
+        "docx" => "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+        "xls" => "application/vnd.ms-excel",
+        "xlsx" => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        "zip" => "application/zip",
+        "xml" => "application/xml",
+        _ => "application/octet-stream",
+    }
+    .to_string()
+}
+
+/// Huella SHA-256 en hexadecimal
+pub(crate) fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Nombre de archivo sin caracteres que no se permiten en Windows
+fn safe_file_name(file_name: &str) -> String {
+    let cleaned: String = file_name
+        .chars()
+        .map(|c| if matches!(c, '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*') || c.is_control() { '_' } else { c })
+        .collect();
+    if cleaned.trim().is_empty() { "adjunto".to_string() } else { cleaned }
+}
+
+fn open_database(state: &AppState, db_name: &str) -> Result<Connection, String> {
+    let db_path = state.db_dir.join(format!("{}.db", db_name));
+    let sqlite_path = state.db_dir.join(format!("{}.sqlite", db_name));
+
+    let db_file = if db_path.exists() {
+        db_path
+    } else if sqlite_path.exists() {
+        sqlite_path
+    } else {
+        return Err(format!("Base de datos no encontrada: {}", db_name));
+    };
+
+    Connection::open(&db_file)
+        .map_err(|e| format!("Error al abrir la base de datos: {}", e))
+}
+
+/// Adjunta al registro el archivo de `file_path`
+#[tauri::command]
+pub fn add_attachment(
+    state: State<AppState>,
+    undo_state: State<UndoState>,
+    db_name: String,
+    table_name: String,
+    pk_value: Value,
+    file_path: String,
+) -> Result<AttachmentInfo, String> {
+    let path = Path::new(&file_path);
+    let metadata = fs::metadata(path)
+        .map_err(|e| format!("No se pudo leer el archivo '{}': {}", file_path, e))?;
+    if !metadata.is_file() {
+        return Err(format!("'{}' no es un archivo", file_path));
+    }
+    if metadata.len() > MAX_ATTACHMENT_BYTES {
+        return Err(format!(
+            "El archivo pesa {:.1} MB; el máximo para un adjunto es {} MB",
+            metadata.len() as f64 / (1024.0 * 1024.0),
+            MAX_ATTACHMENT_BYTES / (1024 * 1024)
+        ));
+    }
+    let bytes = fs::read(path)
+        .map_err(|e| format!("No se pudo leer el archivo '{}': {}", file_path, e))?;
+    let file_name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| "adjunto".to_string());
+
+    let uploaded_at = Local::now().to_rfc3339();
+    let mut conn = open_database(&state, &db_name)?;
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)
+        .map_err(|e| format!("Error al iniciar la transacción: {}", e))?;
+    ensure_attachments_table(&tx)?;
+    purge_discarded(&tx, &undo_state, &db_name)?;
+
+    // El registro debe existir
+    let pk_column = primary_key_column(&tx, &table_name)?;
+    if read_row(&tx, &table_name, &pk_column, &json_to_sql(&pk_value)?)?.is_none() {
+        return Err(format!("No se encontró ninguna fila con {} = {}", pk_column, pk_value));
+    }
+
+    tx.execute(
+        &format!(
+            "INSERT INTO \"{}\" (table_name, record_pk, file_name, mime_type, size, sha256, uploaded_at, uploaded_by, data)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            ATTACHMENTS_TABLE
+        ),
+        rusqlite::params![
+            table_name,
+            record_key(&pk_value)?,
+            file_name,
+            detect_mime(&bytes, &file_name),
+            bytes.len() as i64,
+            sha256_hex(&bytes),
+            uploaded_at,
+            current_user(),
+            bytes,
+        ],
+    ).map_err(|e| format!("Error al guardar el adjunto: {}", e))?;
+
+    let attachment_id = tx.last_insert_rowid();
+    // Deshacer lo marca como eliminado; el contenido no pasa por el diario
+    let inserted = tombstone_change(attachment_id, Some(&uploaded_at), None);
+    log_operation(&tx, "attachment", Some(&table_name), None, Some(&format!("agregado '{}' a {}", file_name, pk_value)))?;
+    let attachment = load_attachment(&tx, attachment_id)?;
+
+    tx.commit()
+        .map_err(|e| format!("Error al guardar los cambios: {}", e))?;
+    undo_state.record(&db_name, format!("Adjuntar '{}' en {}", file_name, table_name), vec![inserted]);
+
+    Ok(attachment)
+}
+
+/// Lista los adjuntos de un registro
+#[tauri::command]
+pub fn list_attachments(
+    state: State<AppState>,
+    db_name: String,
+    table_name: String,
+    pk_value: Value,
+) -> Result<Vec<AttachmentInfo>, String> {
+    let conn = open_database(&state, &db_name)?;
+    record_attachments(&conn, &table_name, &pk_value)
+}
+
+/// Escribe el adjunto en `destination` (ruta de archivo o carpeta) y devuelve
+/// la ruta final. Sin destino se escribe en la carpeta temporal, para que el
+/// frontend lo abra con la aplicación predeterminada.
+#[tauri::command]
+pub fn extract_attachment(
+    state: State<AppState>,
+    db_name: String,
+    attachment_id: i64,
+    destination: Option<String>,
+) -> Result<String, String> {
+    let conn = open_database(&state, &db_name)?;
+    let attachment = load_attachment(&conn, attachment_id)?;
+    let data: Vec<u8> = conn.query_row(
+        &format!("SELECT data FROM \"{}\" WHERE id = ?", ATTACHMENTS_TABLE),
+        [attachment_id],
+        |row| row.get(0),
+    ).map_err(|e| format!("Error al leer el adjunto: {}", e))?;
+
+    let file_name = safe_file_name(&attachment.file_name);
+    let target: PathBuf = match destination {
+        Some(destination) => {
+            let destination = PathBuf::from(destination);
+            if destination.is_dir() { destination.join(&file_name) } else { destination }
+        }
+        None => {
+            let dir = std::env::temp_dir().join("unea_adjuntos").join(attachment_id.to_string());
+            fs::create_dir_all(&dir)
+                .map_err(|e| format!("Error al crear la carpeta temporal: {}", e))?;
+            dir.join(&file_name)
+        }
+    };
+
+    fs::write(&target, &data)
+        .map_err(|e| format!("Error al escribir el archivo '{}': {}", target.display(), e))?;
+    Ok(target.to_string_lossy().to_string())
+}
+
+/// Elimina un adjunto
+#[tauri::command]
+pub fn delete_attachment(
+    state: State<AppState>,
+    undo_state: State<UndoState>,
+    db_name: String,
+    attachment_id: i64,
+) -> Result<bool, String> {
+    let mut conn = open_database(&state, &db_name)?;
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)
+        .map_err(|e| format!("Error al iniciar la transacción: {}", e))?;
+    let attachment = load_attachment(&tx, attachment_id)?;
+    purge_discarded(&tx, &undo_state, &db_name)?;
+
+    // Solo se marca: deshacer quita la marca sin guardar el contenido en el diario
+    let deleted_at = Local::now().to_rfc3339();
+    tx.execute(
+        &format!("UPDATE \"{}\" SET deleted_at = ? WHERE id = ?", ATTACHMENTS_TABLE),
+        rusqlite::params![deleted_at, attachment_id],
+    ).map_err(|e| format!("Error al eliminar el adjunto: {}", e))?;
+    log_operation(
+        &tx,
+        "attachment",
+        Some(&attachment.table_name),
+        None,
+        Some(&format!("eliminado '{}' de {}", attachment.file_name, attachment.record_pk)),
+    )?;
+
+    tx.commit()
+        .map_err(|e| format!("Error al guardar los cambios: {}", e))?;
+    undo_state.record(
+        &db_name,
+        format!("Eliminar adjunto '{}'", attachment.file_name),
+        vec![tombstone_change(attachment_id, None, Some(&deleted_at))],
+    );
+
+    Ok(true)
+}
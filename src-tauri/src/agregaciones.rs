@@ -0,0 +1,307 @@
+/// =========================================================================
+/// Módulo: Agregaciones y tablas dinámicas
+///
+/// Funcionalidades:
+/// - Agrupar una tabla por una o varias columnas (ej. Estatus y Plantel)
+/// - Calcular medidas: conteo, conteo de distintos, suma, promedio, mínimo y máximo
+/// - Aplicar filtros opcionales con el mismo árbol de `filtros.rs`
+/// - Devolver subtotales por cada nivel de agrupación y el total general,
+///   listos para gráficas del dashboard o resúmenes impresos
+/// =========================================================================
+
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::State;
+
+use crate::codec_valores::{decode_value, table_codecs, ColumnCodec, LogicalType};
+use crate::database_manager::AppState;
+use crate::filtros::FilterNode;
+use crate::vistas_guardadas::{load_view, view_sql};
+
+fn quote_identifier(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace("\"", "\"\""))
+}
+
+/// Funciones de agregación disponibles
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum AggregateFn {
+    Count,
+    CountDistinct,
+    Sum,
+    Avg,
+    Min,
+    Max,
+}
+
+/// Medida a calcular
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Measure {
+    /// Función de agregación
+    pub function: AggregateFn,
+    /// Columna sobre la que se calcula (opcional para `count`, que cuenta filas)
+    #[serde(default)]
+    pub column: Option<String>,
+    /// Nombre con el que se devuelve la medida (por defecto "funcion(columna)")
+    #[serde(default)]
+    pub alias: Option<String>,
+}
+
+impl Measure {
+    fn label(&self) -> String {
+        if let Some(alias) = &self.alias {
+            return alias.clone();
+        }
+        let name = match self.function {
+            AggregateFn::Count => "count",
+            AggregateFn::CountDistinct => "count_distinct",
+            AggregateFn::Sum => "sum",
+            AggregateFn::Avg => "avg",
+            AggregateFn::Min => "min",
+            AggregateFn::Max => "max",
+        };
+        match &self.column {
+            Some(col) => format!("{}({})", name, col),
+            None => name.to_string(),
+        }
+    }
+
+    /// Expresión SQL de la medida. Suma y promedio convierten el texto a número,
+    /// porque la mayoría de las columnas del inventario se guardan como TEXT.
+    /// El texto vacío cuenta como sin valor: no suma 0, no baja el promedio
+    /// ni se cuenta; en columnas numéricas, mínimo y máximo comparan números.
+    fn to_sql(&self, codecs: &[ColumnCodec]) -> Result<String, String> {
+        let column = match &self.column {
+            Some(col) => {
+                let codec = codecs.iter().find(|c| &c.name == col)
+                    .ok_or_else(|| format!("La columna '{}' no existe en la tabla", col))?;
+                let value = format!("NULLIF(TRIM({}), '')", quote_identifier(col));
+                Some((value, matches!(codec.logical, LogicalType::Integer | LogicalType::Real)))
+            }
+            None => None,
+        };
+
+        match (self.function, column) {
+            (AggregateFn::Count, None) => Ok("COUNT(*)".to_string()),
+            (AggregateFn::Count, Some((c, _))) => Ok(format!("COUNT({})", c)),
+            (AggregateFn::CountDistinct, Some((c, _))) => Ok(format!("COUNT(DISTINCT {})", c)),
+            (AggregateFn::Sum, Some((c, _))) => Ok(format!("TOTAL(CAST({} AS REAL))", c)),
+            (AggregateFn::Avg, Some((c, _))) => Ok(format!("AVG(CAST({} AS REAL))", c)),
+            (AggregateFn::Min, Some((c, true))) => Ok(format!("MIN(CAST({} AS NUMERIC))", c)),
+            (AggregateFn::Min, Some((c, false))) => Ok(format!("MIN({})", c)),
+            (AggregateFn::Max, Some((c, true))) => Ok(format!("MAX(CAST({} AS NUMERIC))", c)),
+            (AggregateFn::Max, Some((c, false))) => Ok(format!("MAX({})", c)),
+            (_, None) => Err(format!("La medida '{}' requiere una columna", self.label())),
+        }
+    }
+}
+
+/// Parámetros de la agregación
+#[derive(Debug, Deserialize)]
+pub struct AggregateRequest {
+    pub table_name: String,
+    /// Columnas de agrupación en orden (ej. ["Plantel", "Estatus"])
+    #[serde(default)]
+    pub group_by: Vec<String>,
+    /// Medidas a calcular; si está vacío se cuenta el número de filas
+    #[serde(default)]
+    pub measures: Vec<Measure>,
+    /// Filtro opcional sobre las filas
+    #[serde(default)]
+    pub filter: Option<FilterNode>,
+    /// Vista guardada cuyo filtro se aplica además de `filter`
+    #[serde(default)]
+    pub view_id: Option<i64>,
+    /// Calcular subtotales por cada nivel de agrupación
+    #[serde(default = "default_true")]
+    pub subtotals: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Fila del resultado. En los subtotales, las columnas de agrupación que no
+/// forman parte del nivel quedan en `null` y `level` indica cuántas se usan.
+#[derive(Debug, Serialize, Clone)]
+pub struct AggregateRow {
+    /// Valores de las columnas de agrupación (mismo orden que `group_by`)
+    pub keys: Vec<Value>,
+    /// Valores de las medidas (mismo orden que `measures`)
+    pub values: Vec<Value>,
+    /// Número de columnas de agrupación de esta fila (0 = total general)
+    pub level: usize,
+    /// Indica si la fila es un subtotal o el total general
+    pub is_total: bool,
+}
+
+/// Resultado listo para construir una tabla dinámica
+#[derive(Debug, Serialize)]
+pub struct AggregateResult {
+    pub table_name: String,
+    pub group_by: Vec<String>,
+    /// Nombres de las medidas
+    pub measures: Vec<String>,
+    /// Filas de detalle (todas las columnas de agrupación)
+    pub rows: Vec<AggregateRow>,
+    /// Subtotales por nivel, del más detallado al menos detallado
+    pub subtotals: Vec<AggregateRow>,
+    /// Total general
+    pub grand_total: AggregateRow,
+}
+
+/// Valor de una clave o medida; las claves se convierten según el tipo de su columna
+fn value_from_ref(value: rusqlite::types::ValueRef, logical: LogicalType) -> Value {
+    match value {
+        rusqlite::types::ValueRef::Blob(b) => Value::String(format!("BLOB({} bytes)", b.len())),
+        other => decode_value(other, logical),
+    }
+}
+
+/// Ejecuta una consulta agrupada por las primeras `level` columnas de `group_by`
+fn run_level(
+    conn: &Connection,
+    table_name: &str,
+    group_by: &[String],
+    level: usize,
+    measure_sql: &[String],
+    where_sql: &str,
+    params: &[rusqlite::types::Value],
+) -> Result<Vec<AggregateRow>, String> {
+    let keys: Vec<String> = group_by[..level].iter().map(|c| quote_identifier(c)).collect();
+
+    let mut select_parts = keys.clone();
+    select_parts.extend(measure_sql.iter().cloned());
+
+    let group_sql = if keys.is_empty() {
+        String::new()
+    } else {
+        format!(" GROUP BY {} ORDER BY {}", keys.join(", "), keys.join(", "))
+    };
+
+    let sql = format!(
+        "SELECT {} FROM {}{}{}",
+        select_parts.join(", "),
+        quote_identifier(table_name),
+        where_sql,
+        group_sql
+    );
+
+    let mut stmt = conn.prepare(&sql)
+        .map_err(|e| format!("Error al preparar la agregación: {}", e))?;
+
+    // Las medidas no tienen tipo declarado y se devuelven tal cual
+    let logical_types: Vec<LogicalType> = stmt.columns()
+        .iter()
+        .map(|c| LogicalType::from_declared(c.decl_type().unwrap_or("")))
+        .collect();
+
+    let total_keys = group_by.len();
+    let rows = stmt.query_map(rusqlite::params_from_iter(params.iter()), |row| {
+        // Las columnas que no participan en este nivel se devuelven como null
+        let mut key_values: Vec<Value> = (0..level).map(|i| row.get_ref(i).map(|v| value_from_ref(v, logical_types[i]))).collect::<rusqlite::Result<_>>()?;
+        key_values.resize(total_keys, Value::Null);
+
+        let values = (0..measure_sql.len())
+            .map(|i| row.get_ref(level + i).map(|v| value_from_ref(v, logical_types[level + i])))
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(AggregateRow {
+            keys: key_values,
+            values,
+            level,
+            is_total: level < total_keys,
+        })
+    })
+    .map_err(|e| format!("Error al ejecutar la agregación: {}", e))?
+    .collect::<Result<Vec<_>, _>>()
+    .map_err(|e| format!("Error al leer la agregación: {}", e))?;
+
+    Ok(rows)
+}
+
+/// Calcula agregaciones agrupadas sobre una tabla.
+///
+/// Ejemplo: `group_by = ["Estatus", "Plantel"]` con la medida `count` devuelve
+/// cuántos equipos hay por Estatus y Plantel, los subtotales por Estatus y el total.
+#[tauri::command]
+pub fn aggregate_table(
+    state: State<AppState>,
+    db_name: String,
+    request: AggregateRequest,
+) -> Result<AggregateResult, String> {
+    let db_path = state.db_dir.join(format!("{}.db", db_name));
+    let sqlite_path = state.db_dir.join(format!("{}.sqlite", db_name));
+
+    let db_file = if db_path.exists() {
+        db_path
+    } else if sqlite_path.exists() {
+        sqlite_path
+    } else {
+        return Err(format!("No se encontró la base de datos: {}", db_name));
+    };
+
+    let conn = Connection::open(&db_file)
+        .map_err(|e| format!("Error al abrir la base de datos: {}", e))?;
+
+    // Filtro: el de la vista guardada (si hay) combinado con el explícito
+    let mut filters: Vec<FilterNode> = Vec::new();
+    if let Some(view_id) = request.view_id {
+        let view = load_view(&conn, view_id)?;
+        if view.table_name != request.table_name {
+            return Err(format!("La vista '{}' pertenece a la tabla '{}'", view.name, view.table_name));
+        }
+        if let Some(f) = view.filter {
+            filters.push(f);
+        }
+    }
+    if let Some(f) = request.filter.clone() {
+        filters.push(f);
+    }
+    let filter = match filters.len() {
+        0 => None,
+        1 => filters.pop(),
+        _ => Some(FilterNode::And { children: filters }),
+    };
+
+    // Misma traducción que las vistas guardadas: valida las columnas contra la
+    // tabla real y deja fuera los registros en la papelera
+    let view = view_sql(&conn, &request.table_name, filter.as_ref(), &[], &request.group_by)?;
+    let (where_sql, params) = (view.where_sql, view.params);
+
+    let measures = if request.measures.is_empty() {
+        vec![Measure { function: AggregateFn::Count, column: None, alias: None }]
+    } else {
+        request.measures.clone()
+    };
+    let codecs = table_codecs(&conn, &request.table_name)?;
+    let measure_sql = measures
+        .iter()
+        .map(|m| m.to_sql(&codecs))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let group_by = &request.group_by;
+    let rows = run_level(&conn, &request.table_name, group_by, group_by.len(), &measure_sql, &where_sql, &params)?;
+
+    // Subtotales: un nivel por cada prefijo de las columnas de agrupación
+    let mut subtotals = Vec::new();
+    if request.subtotals && group_by.len() > 1 {
+        for level in (1..group_by.len()).rev() {
+            subtotals.extend(run_level(&conn, &request.table_name, group_by, level, &measure_sql, &where_sql, &params)?);
+        }
+    }
+
+    let grand_total = run_level(&conn, &request.table_name, group_by, 0, &measure_sql, &where_sql, &params)?
+        .pop()
+        .ok_or("No se pudo calcular el total general")?;
+
+    Ok(AggregateResult {
+        table_name: request.table_name,
+        group_by: group_by.clone(),
+        measures: measures.iter().map(|m| m.label()).collect(),
+        rows,
+        subtotals,
+        grand_total: AggregateRow { is_total: true, ..grand_total },
+    })
+}
@@ -0,0 +1,513 @@
+/// =========================================================================
+/// Módulo: Almacén de imágenes por contenido
+///
+/// Modo opcional, por columna, para no guardar las imágenes como BLOB dentro
+/// de las filas del inventario (lo que vuelve lentos `SELECT *`, las
+/// exportaciones y VACUUM, y repite la misma foto en cientos de registros):
+/// - La columna guarda una referencia de texto `sha256:<huella>`
+/// - Los bytes se guardan una sola vez en la tabla interna `_unea_image_store`,
+///   con la huella SHA-256 como clave, dentro de la propia base de datos
+/// - Unos triggers sobre la columna llevan el conteo de referencias de cada
+///   imagen al insertar, modificar o eliminar filas (incluido deshacer)
+/// - `collect_image_garbage` recalcula los conteos y elimina las imágenes
+///   que ya no usa ningún registro
+/// - `migrate_column_to_image_store` convierte los BLOB existentes de una
+///   columna y `migrate_column_to_blob` hace la conversión inversa
+///
+/// El protocolo `unea-img` y las consultas resuelven tanto BLOB como
+/// referencias, de modo que una columna puede tener valores de ambos tipos
+/// mientras se migra. `store_record_image` guarda en el almacén si la
+/// columna está en este modo.
+/// =========================================================================
+
+use chrono::Local;
+use rusqlite::types::ValueRef;
+use rusqlite::{Connection, OptionalExtension, TransactionBehavior};
+use serde::Serialize;
+use tauri::State;
+
+use crate::adjuntos::sha256_hex;
+use crate::auditoria::log_operation;
+use crate::codec_valores::{find_codec, table_codecs, LogicalType};
+use crate::database_manager::AppState;
+use crate::protocolo_imagenes::sniff_image_mime;
+
+/// Tabla interna con los bytes de cada imagen
+const STORE_TABLE: &str = "_unea_image_store";
+/// Tabla interna con las columnas que usan el almacén
+const STORE_COLUMNS_TABLE: &str = "_unea_image_store_columns";
+/// Prefijo de las referencias guardadas en las columnas
+pub(crate) const IMAGE_REF_PREFIX: &str = "sha256:";
+
+fn quote_identifier(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace("\"", "\"\""))
+}
+
+/// Columna que usa el almacén
+#[derive(Debug, Serialize, Clone)]
+pub struct ImageStoreColumn {
+    pub table_name: String,
+    pub column_name: String,
+    /// Fecha en que se activó el modo (RFC 3339)
+    pub enabled_at: String,
+}
+
+/// Estado del almacén
+#[derive(Debug, Serialize)]
+pub struct ImageStoreStats {
+    pub columns: Vec<ImageStoreColumn>,
+    /// Imágenes distintas guardadas
+    pub images: i64,
+    /// Bytes ocupados por las imágenes
+    pub total_bytes: i64,
+    /// Referencias desde los registros
+    pub references: i64,
+    /// Imágenes sin referencias (se eliminan con `collect_image_garbage`)
+    pub orphaned: i64,
+    pub orphaned_bytes: i64,
+}
+
+/// Resultado de una migración de columna
+#[derive(Debug, Serialize)]
+pub struct ImageMigrationResult {
+    /// Filas convertidas
+    pub converted_rows: usize,
+    /// Imágenes distintas entre las filas convertidas
+    pub distinct_images: usize,
+}
+
+/// Resultado de `collect_image_garbage`
+#[derive(Debug, Serialize)]
+pub struct ImageGarbageResult {
+    pub removed_images: i64,
+    pub freed_bytes: i64,
+}
+
+fn ensure_store_tables(conn: &Connection) -> Result<(), String> {
+    conn.execute_batch(&format!(
+        "CREATE TABLE IF NOT EXISTS \"{store}\" (
+            sha256 TEXT PRIMARY KEY,
+            mime_type TEXT NOT NULL,
+            size INTEGER NOT NULL,
+            ref_count INTEGER NOT NULL DEFAULT 0,
+            created_at TEXT NOT NULL,
+            data BLOB NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS \"{columns}\" (
+            table_name TEXT NOT NULL,
+            column_name TEXT NOT NULL,
+            enabled_at TEXT NOT NULL,
+            PRIMARY KEY (table_name, column_name)
+        );",
+        store = STORE_TABLE,
+        columns = STORE_COLUMNS_TABLE
+    )).map_err(|e| format!("Error al crear el almacén de imágenes: {}", e))
+}
+
+fn has_store_tables(conn: &Connection) -> Result<bool, String> {
+    let exists: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name=?",
+        [STORE_COLUMNS_TABLE],
+        |row| row.get(0),
+    ).map_err(|e| format!("Error al consultar el almacén de imágenes: {}", e))?;
+    Ok(exists > 0)
+}
+
+/// Huella de una referencia `sha256:<huella>`, o None si el valor no es una referencia
+pub(crate) fn parse_image_ref(value: ValueRef) -> Option<String> {
+    let ValueRef::Text(text) = value else {
+        return None;
+    };
+    let text = std::str::from_utf8(text).ok()?;
+    let hash = text.strip_prefix(IMAGE_REF_PREFIX)?;
+    (hash.len() == 64 && hash.bytes().all(|b| b.is_ascii_hexdigit())).then(|| hash.to_string())
+}
+
+/// Indica si el valor de una celda es una referencia al almacén
+pub(crate) fn is_image_ref(value: ValueRef) -> bool {
+    parse_image_ref(value).is_some()
+}
+
+/// Indica si la columna guarda sus imágenes en el almacén
+pub(crate) fn is_store_column(conn: &Connection, table_name: &str, column_name: &str) -> Result<bool, String> {
+    if !has_store_tables(conn)? {
+        return Ok(false);
+    }
+    conn.query_row(
+        &format!("SELECT 1 FROM \"{}\" WHERE table_name = ? AND column_name = ?", STORE_COLUMNS_TABLE),
+        [table_name, column_name],
+        |_| Ok(()),
+    )
+    .optional()
+    .map(|found| found.is_some())
+    .map_err(|e| format!("Error al consultar el almacén de imágenes: {}", e))
+}
+
+/// Guarda los bytes en el almacén (si no estaban ya) y devuelve la referencia
+/// que se escribe en la columna. El conteo lo actualizan los triggers.
+pub(crate) fn put_image(conn: &Connection, bytes: &[u8]) -> Result<String, String> {
+    ensure_store_tables(conn)?;
+    let hash = sha256_hex(bytes);
+    conn.execute(
+        &format!(
+            "INSERT OR IGNORE INTO \"{}\" (sha256, mime_type, size, ref_count, created_at, data) VALUES (?, ?, ?, 0, ?, ?)",
+            STORE_TABLE
+        ),
+        rusqlite::params![
+            hash,
+            sniff_image_mime(bytes).unwrap_or("application/octet-stream"),
+            bytes.len() as i64,
+            Local::now().to_rfc3339(),
+            bytes,
+        ],
+    ).map_err(|e| format!("Error al guardar la imagen en el almacén: {}", e))?;
+    Ok(format!("{}{}", IMAGE_REF_PREFIX, hash))
+}
+
+/// Bytes de una imagen del almacén por su huella
+pub(crate) fn read_image(conn: &Connection, hash: &str) -> Result<Option<Vec<u8>>, String> {
+    if !has_store_tables(conn)? {
+        return Ok(None);
+    }
+    conn.query_row(
+        &format!("SELECT data FROM \"{}\" WHERE sha256 = ?", STORE_TABLE),
+        [hash],
+        |row| row.get(0),
+    )
+    .optional()
+    .map_err(|e| format!("Error al leer la imagen del almacén: {}", e))
+}
+
+fn trigger_name(table_name: &str, column_name: &str, suffix: &str) -> String {
+    format!("_unea_imgref_{}_{}_{}", table_name, column_name, suffix)
+}
+
+/// Crea los triggers que llevan el conteo de referencias de la columna
+fn create_ref_triggers(conn: &Connection, table_name: &str, column_name: &str) -> Result<(), String> {
+    let table = quote_identifier(table_name);
+    let column = quote_identifier(column_name);
+    let is_ref = |row: &str| format!("typeof({row}.{column}) = 'text' AND substr({row}.{column}, 1, 7) = '{IMAGE_REF_PREFIX}'");
+    let adjust = |row: &str, delta: &str| format!(
+        "UPDATE \"{STORE_TABLE}\" SET ref_count = ref_count {delta} WHERE {} AND sha256 = substr({row}.{column}, 8)",
+        is_ref(row)
+    );
+
+    let triggers = [
+        ("ai", format!("AFTER INSERT ON {}", table), adjust("new", "+ 1")),
+        ("ad", format!("AFTER DELETE ON {}", table), adjust("old", "- 1")),
+        (
+            "au",
+            format!("AFTER UPDATE OF {} ON {} WHEN old.{} IS NOT new.{}", column, table, column, column),
+            format!("{}; {}", adjust("old", "- 1"), adjust("new", "+ 1")),
+        ),
+    ];
+    for (suffix, event, body) in triggers.iter() {
+        conn.execute(
+            &format!(
+                "CREATE TRIGGER IF NOT EXISTS {} {} BEGIN {}; END",
+                quote_identifier(&trigger_name(table_name, column_name, suffix)),
+                event,
+                body
+            ),
+            [],
+        ).map_err(|e| format!("Error al crear trigger del almacén de imágenes: {}", e))?;
+    }
+    Ok(())
+}
+
+fn drop_ref_triggers(conn: &Connection, table_name: &str, column_name: &str) -> Result<(), String> {
+    for suffix in ["ai", "ad", "au"] {
+        conn.execute(
+            &format!("DROP TRIGGER IF EXISTS {}", quote_identifier(&trigger_name(table_name, column_name, suffix))),
+            [],
+        ).map_err(|e| format!("Error al eliminar trigger del almacén de imágenes: {}", e))?;
+    }
+    Ok(())
+}
+
+fn store_columns(conn: &Connection) -> Result<Vec<ImageStoreColumn>, String> {
+    if !has_store_tables(conn)? {
+        return Ok(Vec::new());
+    }
+    let mut stmt = conn.prepare(&format!(
+        "SELECT table_name, column_name, enabled_at FROM \"{}\" ORDER BY table_name, column_name",
+        STORE_COLUMNS_TABLE
+    )).map_err(|e| format!("Error al consultar el almacén de imágenes: {}", e))?;
+    let columns = stmt.query_map([], |row| {
+        Ok(ImageStoreColumn {
+            table_name: row.get(0)?,
+            column_name: row.get(1)?,
+            enabled_at: row.get(2)?,
+        })
+    })
+    .map_err(|e| format!("Error al consultar el almacén de imágenes: {}", e))?
+    .collect::<Result<Vec<_>, _>>()
+    .map_err(|e| format!("Error al leer el almacén de imágenes: {}", e))?;
+    Ok(columns)
+}
+
+/// Quita el modo almacén de las columnas de una tabla (o de una sola columna).
+/// Se usa antes de eliminar tablas o columnas: SQLite rechaza DROP COLUMN
+/// si un trigger usa la columna. Los conteos se corrigen en la siguiente
+/// recolección de imágenes huérfanas.
+pub(crate) fn forget_store_columns(conn: &Connection, table_name: &str, column_name: Option<&str>) -> Result<(), String> {
+    for column in store_columns(conn)? {
+        if column.table_name != table_name || column_name.map_or(false, |c| c != column.column_name) {
+            continue;
+        }
+        drop_ref_triggers(conn, &column.table_name, &column.column_name)?;
+        conn.execute(
+            &format!("DELETE FROM \"{}\" WHERE table_name = ? AND column_name = ?", STORE_COLUMNS_TABLE),
+            [&column.table_name, &column.column_name],
+        ).map_err(|e| format!("Error al actualizar el almacén de imágenes: {}", e))?;
+    }
+    Ok(())
+}
+
+/// Vuelve a contar las referencias desde las columnas del almacén.
+/// También recrea los triggers que falten (por ejemplo, tras reconstruir una tabla).
+fn recount_references(conn: &Connection) -> Result<(), String> {
+    conn.execute(&format!("UPDATE \"{}\" SET ref_count = 0", STORE_TABLE), [])
+        .map_err(|e| format!("Error al contar las referencias: {}", e))?;
+    for column in store_columns(conn)? {
+        // Columnas de tablas que ya no existen no aportan referencias
+        let Ok(codecs) = table_codecs(conn, &column.table_name) else {
+            continue;
+        };
+        if find_codec(&codecs, &column.column_name).is_err() {
+            continue;
+        }
+        create_ref_triggers(conn, &column.table_name, &column.column_name)?;
+        conn.execute(
+            &format!(
+                "UPDATE \"{store}\" SET ref_count = ref_count + (
+                    SELECT COUNT(*) FROM {table} WHERE {col} = '{prefix}' || \"{store}\".sha256
+                )",
+                store = STORE_TABLE,
+                table = quote_identifier(&column.table_name),
+                col = quote_identifier(&column.column_name),
+                prefix = IMAGE_REF_PREFIX
+            ),
+            [],
+        ).map_err(|e| format!("Error al contar las referencias de '{}': {}", column.table_name, e))?;
+    }
+    Ok(())
+}
+
+fn check_image_column(conn: &Connection, table_name: &str, column_name: &str) -> Result<(), String> {
+    let codecs = table_codecs(conn, table_name)?;
+    match find_codec(&codecs, column_name)?.logical {
+        LogicalType::Blob | LogicalType::Any => Ok(()),
+        _ => Err(format!("La columna '{}' no es de tipo imagen (BLOB)", column_name)),
+    }
+}
+
+fn open_database(state: &AppState, db_name: &str) -> Result<Connection, String> {
+    let db_path = state.db_dir.join(format!("{}.db", db_name));
+    let sqlite_path = state.db_dir.join(format!("{}.sqlite", db_name));
+
+    let db_file = if db_path.exists() {
+        db_path
+    } else if sqlite_path.exists() {
+        sqlite_path
+    } else {
+        return Err(format!("Base de datos no encontrada: {}", db_name));
+    };
+
+    Connection::open(&db_file)
+        .map_err(|e| format!("Error al abrir la base de datos: {}", e))
+}
+
+/// Activa el almacén para una columna y convierte sus BLOB en referencias.
+/// Se puede volver a ejecutar para convertir BLOB escritos después por otras vías.
+#[tauri::command]
+pub fn migrate_column_to_image_store(
+    state: State<AppState>,
+    db_name: String,
+    table_name: String,
+    column_name: String,
+) -> Result<ImageMigrationResult, String> {
+    let mut conn = open_database(&state, &db_name)?;
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)
+        .map_err(|e| format!("Error al iniciar la transacción: {}", e))?;
+
+    check_image_column(&tx, &table_name, &column_name)?;
+    ensure_store_tables(&tx)?;
+    tx.execute(
+        &format!(
+            "INSERT OR IGNORE INTO \"{}\" (table_name, column_name, enabled_at) VALUES (?, ?, ?)",
+            STORE_COLUMNS_TABLE
+        ),
+        [&table_name, &column_name, &Local::now().to_rfc3339()],
+    ).map_err(|e| format!("Error al activar el almacén de imágenes: {}", e))?;
+    create_ref_triggers(&tx, &table_name, &column_name)?;
+
+    let table = quote_identifier(&table_name);
+    let column = quote_identifier(&column_name);
+    let rowids: Vec<i64> = {
+        let mut stmt = tx.prepare(&format!("SELECT rowid FROM {} WHERE typeof({}) = 'blob'", table, column))
+            .map_err(|e| format!("Error al preparar la migración: {}", e))?;
+        let rowids = stmt.query_map([], |row| row.get(0))
+            .map_err(|e| format!("Error al leer las imágenes: {}", e))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Error al leer las imágenes: {}", e))?;
+        rowids
+    };
+
+    // Fila por fila para no cargar todas las imágenes en memoria a la vez
+    let mut hashes = std::collections::HashSet::new();
+    for rowid in &rowids {
+        let bytes: Vec<u8> = tx.query_row(
+            &format!("SELECT {} FROM {} WHERE rowid = ?", column, table),
+            [rowid],
+            |row| row.get(0),
+        ).map_err(|e| format!("Error al leer la imagen: {}", e))?;
+        let image_ref = put_image(&tx, &bytes)?;
+        tx.execute(&format!("UPDATE {} SET {} = ? WHERE rowid = ?", table, column), rusqlite::params![image_ref, rowid])
+            .map_err(|e| format!("Error al convertir la imagen: {}", e))?;
+        hashes.insert(image_ref);
+    }
+
+    log_operation(
+        &tx,
+        "image_store",
+        Some(&table_name),
+        Some(&column_name),
+        Some(&format!("{} imágenes convertidas ({} distintas)", rowids.len(), hashes.len())),
+    )?;
+
+    tx.commit()
+        .map_err(|e| format!("Error al guardar los cambios: {}", e))?;
+
+    Ok(ImageMigrationResult { converted_rows: rowids.len(), distinct_images: hashes.len() })
+}
+
+/// Vuelve a guardar las imágenes de la columna como BLOB y desactiva el almacén para ella
+#[tauri::command]
+pub fn migrate_column_to_blob(
+    state: State<AppState>,
+    db_name: String,
+    table_name: String,
+    column_name: String,
+) -> Result<ImageMigrationResult, String> {
+    let mut conn = open_database(&state, &db_name)?;
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)
+        .map_err(|e| format!("Error al iniciar la transacción: {}", e))?;
+
+    if !is_store_column(&tx, &table_name, &column_name)? {
+        return Err(format!("La columna '{}' no usa el almacén de imágenes", column_name));
+    }
+
+    let table = quote_identifier(&table_name);
+    let column = quote_identifier(&column_name);
+    // Las referencias a imágenes que ya no existen en el almacén se dejan en NULL
+    let sql = format!(
+        "UPDATE {table} SET {column} = (SELECT data FROM \"{store}\" WHERE sha256 = substr({table}.{column}, 8))
+         WHERE typeof({column}) = 'text' AND substr({column}, 1, 7) = '{prefix}'",
+        table = table,
+        column = column,
+        store = STORE_TABLE,
+        prefix = IMAGE_REF_PREFIX
+    );
+    let distinct_images: i64 = tx.query_row(
+        &format!(
+            "SELECT COUNT(DISTINCT {column}) FROM {table} WHERE typeof({column}) = 'text' AND substr({column}, 1, 7) = '{prefix}'",
+            table = table,
+            column = column,
+            prefix = IMAGE_REF_PREFIX
+        ),
+        [],
+        |row| row.get(0),
+    ).map_err(|e| format!("Error al leer las imágenes: {}", e))?;
+    let converted_rows = tx.execute(&sql, [])
+        .map_err(|e| format!("Error al convertir las imágenes: {}", e))?;
+
+    forget_store_columns(&tx, &table_name, Some(&column_name))?;
+    log_operation(
+        &tx,
+        "image_store",
+        Some(&table_name),
+        Some(&column_name),
+        Some(&format!("{} imágenes devueltas a BLOB", converted_rows)),
+    )?;
+
+    tx.commit()
+        .map_err(|e| format!("Error al guardar los cambios: {}", e))?;
+
+    Ok(ImageMigrationResult { converted_rows, distinct_images: distinct_images as usize })
+}
+
+/// Columnas en modo almacén, imágenes guardadas y espacio ocupado
+#[tauri::command]
+pub fn image_store_stats(
+    state: State<AppState>,
+    db_name: String,
+) -> Result<ImageStoreStats, String> {
+    let conn = open_database(&state, &db_name)?;
+    let columns = store_columns(&conn)?;
+    if columns.is_empty() && !has_store_tables(&conn)? {
+        return Ok(ImageStoreStats { columns, images: 0, total_bytes: 0, references: 0, orphaned: 0, orphaned_bytes: 0 });
+    }
+
+    conn.query_row(
+        &format!(
+            "SELECT COUNT(*), COALESCE(SUM(size), 0), COALESCE(SUM(MAX(ref_count, 0)), 0),
+                    COUNT(CASE WHEN ref_count <= 0 THEN 1 END),
+                    COALESCE(SUM(CASE WHEN ref_count <= 0 THEN size END), 0)
+             FROM \"{}\"",
+            STORE_TABLE
+        ),
+        [],
+        |row| {
+            Ok(ImageStoreStats {
+                columns: columns.clone(),
+                images: row.get(0)?,
+                total_bytes: row.get(1)?,
+                references: row.get(2)?,
+                orphaned: row.get(3)?,
+                orphaned_bytes: row.get(4)?,
+            })
+        },
+    ).map_err(|e| format!("Error al consultar el almacén de imágenes: {}", e))
+}
+
+/// Recalcula los conteos de referencias y elimina las imágenes que ningún
+/// registro usa. Después de esto, deshacer un cambio anterior que apuntaba a
+/// una imagen eliminada deja la celda sin imagen.
+#[tauri::command]
+pub fn collect_image_garbage(
+    state: State<AppState>,
+    db_name: String,
+) -> Result<ImageGarbageResult, String> {
+    let mut conn = open_database(&state, &db_name)?;
+    if !has_store_tables(&conn)? {
+        return Ok(ImageGarbageResult { removed_images: 0, freed_bytes: 0 });
+    }
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)
+        .map_err(|e| format!("Error al iniciar la transacción: {}", e))?;
+
+    recount_references(&tx)?;
+
+    let (removed_images, freed_bytes): (i64, i64) = tx.query_row(
+        &format!("SELECT COUNT(*), COALESCE(SUM(size), 0) FROM \"{}\" WHERE ref_count <= 0", STORE_TABLE),
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    ).map_err(|e| format!("Error al consultar el almacén de imágenes: {}", e))?;
+    tx.execute(&format!("DELETE FROM \"{}\" WHERE ref_count <= 0", STORE_TABLE), [])
+        .map_err(|e| format!("Error al eliminar las imágenes huérfanas: {}", e))?;
+
+    if removed_images > 0 {
+        log_operation(
+            &tx,
+            "image_store",
+            None,
+            None,
+            Some(&format!("{} imágenes huérfanas eliminadas ({} bytes)", removed_images, freed_bytes)),
+        )?;
+    }
+
+    tx.commit()
+        .map_err(|e| format!("Error al guardar los cambios: {}", e))?;
+
+    Ok(ImageGarbageResult { removed_images, freed_bytes })
+}
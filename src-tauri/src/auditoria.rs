@@ -0,0 +1,475 @@
+/// =========================================================================
+/// Módulo: Bitácora de auditoría
+///
+/// Registra quién cambió qué, cuándo, y cuáles eran los valores anteriores
+/// y nuevos, en la tabla interna `_unea_audit` de cada base de datos:
+/// - Cambios de datos: una entrada por columna modificada (altas y bajas
+///   registran todas las columnas de la fila)
+/// - Cambios de esquema: creación/eliminación de tablas y columnas,
+///   importaciones y sentencias SQL ejecutadas a mano
+/// - La tabla es de solo inserción: unos triggers impiden modificar o
+///   borrar entradas
+/// - Las entradas de una misma operación comparten `batch_id`
+/// - Los BLOB no se copian: se guarda un resumen con su tamaño y huella
+///   SHA-256 para que la bitácora no crezca con cada imagen
+///
+/// Las entradas se escriben desde la capa de comandos, dentro de la misma
+/// transacción que el cambio cuando la hay.
+/// =========================================================================
+
+use chrono::Local;
+use rusqlite::types::Value as SqlValue;
+use rusqlite::{Connection, OpenFlags};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::State;
+
+use crate::adjuntos::sha256_hex;
+use crate::database_manager::AppState;
+use crate::deshacer::RowChange;
+
+/// Nombre de la tabla interna de auditoría.
+pub(crate) const AUDIT_TABLE: &str = "_unea_audit";
+
+/// Límite de entradas por consulta si el frontend no indica otro.
+const DEFAULT_LIMIT: usize = 500;
+
+/// Crea la tabla de auditoría y los triggers que la hacen de solo inserción
+pub(crate) fn ensure_audit_table(conn: &Connection) -> Result<(), String> {
+    conn.execute_batch(&format!(
+        "CREATE TABLE IF NOT EXISTS {table} (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            batch_id INTEGER NOT NULL,
+            timestamp TEXT NOT NULL,
+            station TEXT,
+            user_name TEXT,
+            operation TEXT NOT NULL,
+            table_name TEXT,
+            pk_value TEXT,
+            column_name TEXT,
+            old_value,
+            new_value,
+            context TEXT
+        );
+        CREATE INDEX IF NOT EXISTS {table}_record ON {table} (table_name, pk_value);
+        CREATE TRIGGER IF NOT EXISTS {table}_no_update BEFORE UPDATE ON {table}
+        BEGIN SELECT RAISE(ABORT, 'La bitácora de auditoría no se puede modificar'); END;
+        CREATE TRIGGER IF NOT EXISTS {table}_no_delete BEFORE DELETE ON {table}
+        BEGIN SELECT RAISE(ABORT, 'La bitácora de auditoría no se puede modificar'); END;",
+        table = AUDIT_TABLE
+    ))
+    .map_err(|e| format!("Error al crear la bitácora de auditoría: {}", e))
+}
+
+/// Nombre del equipo desde el que se hace el cambio
+pub(crate) fn station_name() -> String {
+    std::env::var("COMPUTERNAME")
+        .or_else(|_| std::env::var("HOSTNAME"))
+        .ok()
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok().map(|s| s.trim().to_string()))
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| "desconocido".to_string())
+}
+
+/// Usuario del sistema operativo que hace el cambio
+pub(crate) fn current_user() -> String {
+    std::env::var("USERNAME")
+        .or_else(|_| std::env::var("USER"))
+        .unwrap_or_else(|_| "desconocido".to_string())
+}
+
+/// Clave primaria como texto, para poder buscar el historial de un registro
+pub(crate) fn pk_text(value: &SqlValue) -> Option<String> {
+    match value {
+        SqlValue::Null => None,
+        SqlValue::Integer(i) => Some(i.to_string()),
+        SqlValue::Real(f) => Some(f.to_string()),
+        SqlValue::Text(s) => Some(s.clone()),
+        SqlValue::Blob(b) => Some(format!("BLOB({} bytes)", b.len())),
+    }
+}
+
+/// Resumen de un BLOB tal como se guarda en la bitácora
+pub(crate) fn blob_summary(bytes: &[u8]) -> String {
+    format!("BLOB({} bytes, sha256:{})", bytes.len(), sha256_hex(bytes))
+}
+
+/// Indica si el valor es un resumen de BLOB escrito por `blob_summary`
+pub(crate) fn is_blob_summary(value: &SqlValue) -> bool {
+    match value {
+        SqlValue::Text(s) => s.starts_with("BLOB(") && s.contains(" bytes, sha256:") && s.ends_with(')'),
+        _ => false,
+    }
+}
+
+/// Valor tal como se guarda en la bitácora: los BLOB se sustituyen por su resumen
+pub(crate) fn audit_value(value: &SqlValue) -> SqlValue {
+    match value {
+        SqlValue::Blob(b) => SqlValue::Text(blob_summary(b)),
+        other => other.clone(),
+    }
+}
+
+/// Entrada a insertar en la bitácora
+struct NewEntry<'a> {
+    operation: &'a str,
+    table_name: Option<&'a str>,
+    pk_value: Option<String>,
+    column_name: Option<&'a str>,
+    old_value: SqlValue,
+    new_value: SqlValue,
+}
+
+fn insert_entries(conn: &Connection, context: &str, entries: &[NewEntry]) -> Result<(), String> {
+    if entries.is_empty() {
+        return Ok(());
+    }
+    ensure_audit_table(conn)?;
+
+    let batch_id: i64 = conn.query_row(
+        &format!("SELECT COALESCE(MAX(batch_id), 0) + 1 FROM {}", AUDIT_TABLE),
+        [],
+        |row| row.get(0),
+    ).map_err(|e| format!("Error al leer la bitácora de auditoría: {}", e))?;
+
+    let timestamp = Local::now().to_rfc3339();
+    let station = station_name();
+    let user = current_user();
+
+    let mut stmt = conn.prepare(&format!(
+        "INSERT INTO {} (batch_id, timestamp, station, user_name, operation, table_name, pk_value, column_name, old_value, new_value, context)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        AUDIT_TABLE
+    )).map_err(|e| format!("Error al preparar la bitácora de auditoría: {}", e))?;
+
+    for entry in entries {
+        stmt.execute(rusqlite::params![
+            batch_id,
+            timestamp,
+            station,
+            user,
+            entry.operation,
+            entry.table_name,
+            entry.pk_value,
+            entry.column_name,
+            audit_value(&entry.old_value),
+            audit_value(&entry.new_value),
+            context,
+        ]).map_err(|e| format!("Error al escribir la bitácora de auditoría: {}", e))?;
+    }
+    Ok(())
+}
+
+/// Registra cambios de filas. `context` indica el comando que los produjo.
+pub(crate) fn log_row_changes(conn: &Connection, context: &str, changes: &[RowChange]) -> Result<(), String> {
+    let mut entries = Vec::new();
+
+    for change in changes {
+        let image = change.after.as_ref().or(change.before.as_ref());
+        let pk_value = image
+            .and_then(|img| img.get(&change.pk_column))
+            .and_then(pk_text);
+
+        match (&change.before, &change.after) {
+            (None, Some(after)) => {
+                let mut columns: Vec<&String> = after.keys().collect();
+                columns.sort();
+                for column in columns {
+                    entries.push(NewEntry {
+                        operation: "insert",
+                        table_name: Some(&change.table_name),
+                        pk_value: pk_value.clone(),
+                        column_name: Some(column),
+                        old_value: SqlValue::Null,
+                        new_value: after[column].clone(),
+                    });
+                }
+            }
+            (Some(before), None) => {
+                let mut columns: Vec<&String> = before.keys().collect();
+                columns.sort();
+                for column in columns {
+                    entries.push(NewEntry {
+                        operation: "delete",
+                        table_name: Some(&change.table_name),
+                        pk_value: pk_value.clone(),
+                        column_name: Some(column),
+                        old_value: before[column].clone(),
+                        new_value: SqlValue::Null,
+                    });
+                }
+            }
+            (Some(before), Some(after)) => {
+                let mut columns: Vec<&String> = after.keys().collect();
+                columns.sort();
+                for column in columns {
+                    let old_value = before.get(column).cloned().unwrap_or(SqlValue::Null);
+                    if old_value != after[column] {
+                        entries.push(NewEntry {
+                            operation: "update",
+                            table_name: Some(&change.table_name),
+                            pk_value: pk_value.clone(),
+                            column_name: Some(column),
+                            old_value,
+                            new_value: after[column].clone(),
+                        });
+                    }
+                }
+            }
+            (None, None) => {}
+        }
+    }
+
+    insert_entries(conn, context, &entries)
+}
+
+/// Registra un cambio de esquema u otra operación sin valores de fila
+/// (ej. "create_table", "drop_table", "add_column", "drop_column", "import_table", "sql").
+pub(crate) fn log_operation(
+    conn: &Connection,
+    operation: &str,
+    table_name: Option<&str>,
+    column_name: Option<&str>,
+    detail: Option<&str>,
+) -> Result<(), String> {
+    insert_entries(conn, operation, &[NewEntry {
+        operation,
+        table_name,
+        pk_value: None,
+        column_name,
+        old_value: SqlValue::Null,
+        new_value: detail.map(|d| SqlValue::Text(d.to_string())).unwrap_or(SqlValue::Null),
+    }])
+}
+
+/// Entrada de la bitácora tal como se envía al frontend
+#[derive(Debug, Serialize)]
+pub struct AuditEntry {
+    pub id: i64,
+    /// Entradas con el mismo lote pertenecen a la misma operación
+    pub batch_id: i64,
+    /// Fecha y hora (RFC 3339)
+    pub timestamp: String,
+    pub station: Option<String>,
+    pub user_name: Option<String>,
+    /// "insert", "update", "delete", "create_table", "drop_table", "add_column", ...
+    pub operation: String,
+    pub table_name: Option<String>,
+    pub pk_value: Option<String>,
+    pub column_name: Option<String>,
+    pub old_value: Value,
+    pub new_value: Value,
+    /// Comando que produjo la entrada
+    pub context: Option<String>,
+}
+
+/// Filtros de consulta de la bitácora (todos opcionales)
+#[derive(Debug, Deserialize, Default)]
+pub struct AuditQuery {
+    #[serde(default)]
+    pub table_name: Option<String>,
+    #[serde(default)]
+    pub pk_value: Option<String>,
+    #[serde(default)]
+    pub column_name: Option<String>,
+    #[serde(default)]
+    pub operation: Option<String>,
+    #[serde(default)]
+    pub station: Option<String>,
+    #[serde(default)]
+    pub user_name: Option<String>,
+    /// Desde esta fecha (RFC 3339 o AAAA-MM-DD)
+    #[serde(default)]
+    pub from: Option<String>,
+    /// Hasta esta fecha (RFC 3339 o AAAA-MM-DD, inclusive)
+    #[serde(default)]
+    pub to: Option<String>,
+    #[serde(default)]
+    pub limit: Option<usize>,
+    #[serde(default)]
+    pub offset: Option<usize>,
+}
+
+/// Valor almacenado en la bitácora como JSON (las imágenes se resumen;
+/// entradas antiguas pueden tener el BLOB completo)
+pub(crate) fn audit_value_to_json(value: SqlValue) -> Value {
+    match value {
+        SqlValue::Blob(b) => Value::String(format!("BLOB({} bytes)", b.len())),
+        other => crate::codec_valores::sql_to_json(&other),
+    }
+}
+
+/// Indica si la base de datos ya tiene bitácora
+pub(crate) fn has_audit_table(conn: &Connection) -> Result<bool, String> {
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name=?",
+        [AUDIT_TABLE],
+        |row| row.get(0),
+    ).map_err(|e| format!("Error al consultar la bitácora de auditoría: {}", e))?;
+    Ok(count > 0)
+}
+
+/// Consulta la bitácora con filtros; las entradas más recientes primero
+pub(crate) fn query_entries(conn: &Connection, query: &AuditQuery) -> Result<Vec<AuditEntry>, String> {
+    if !has_audit_table(conn)? {
+        return Ok(Vec::new());
+    }
+
+    let mut conditions: Vec<String> = Vec::new();
+    let mut params: Vec<SqlValue> = Vec::new();
+
+    let exact = [
+        ("table_name", &query.table_name),
+        ("pk_value", &query.pk_value),
+        ("column_name", &query.column_name),
+        ("operation", &query.operation),
+        ("station", &query.station),
+        ("user_name", &query.user_name),
+    ];
+    for (column, value) in exact {
+        if let Some(v) = value {
+            conditions.push(format!("{} = ?", column));
+            params.push(SqlValue::Text(v.clone()));
+        }
+    }
+    if let Some(from) = &query.from {
+        conditions.push("timestamp >= ?".to_string());
+        params.push(SqlValue::Text(from.clone()));
+    }
+    if let Some(to) = &query.to {
+        // Una fecha sin hora incluye todo ese día
+        conditions.push("substr(timestamp, 1, length(?)) <= ?".to_string());
+        params.push(SqlValue::Text(to.clone()));
+        params.push(SqlValue::Text(to.clone()));
+    }
+
+    let where_sql = if conditions.is_empty() {
+        String::new()
+    } else {
+        format!(" WHERE {}", conditions.join(" AND "))
+    };
+
+    let sql = format!(
+        "SELECT id, batch_id, timestamp, station, user_name, operation, table_name, pk_value, column_name, old_value, new_value, context
+         FROM {}{} ORDER BY id DESC LIMIT {} OFFSET {}",
+        AUDIT_TABLE,
+        where_sql,
+        query.limit.unwrap_or(DEFAULT_LIMIT),
+        query.offset.unwrap_or(0)
+    );
+
+    let mut stmt = conn.prepare(&sql)
+        .map_err(|e| format!("Error al preparar la consulta de auditoría: {}", e))?;
+    let entries = stmt.query_map(rusqlite::params_from_iter(params.iter()), |row| {
+        Ok(AuditEntry {
+            id: row.get(0)?,
+            batch_id: row.get(1)?,
+            timestamp: row.get(2)?,
+            station: row.get(3)?,
+            user_name: row.get(4)?,
+            operation: row.get(5)?,
+            table_name: row.get(6)?,
+            pk_value: row.get(7)?,
+            column_name: row.get(8)?,
+            old_value: audit_value_to_json(row.get(9)?),
+            new_value: audit_value_to_json(row.get(10)?),
+            context: row.get(11)?,
+        })
+    })
+    .map_err(|e| format!("Error al consultar la bitácora de auditoría: {}", e))?
+    .collect::<Result<Vec<_>, _>>()
+    .map_err(|e| format!("Error al leer la bitácora de auditoría: {}", e))?;
+
+    Ok(entries)
+}
+
+fn open_read_only(state: &AppState, db_name: &str) -> Result<Connection, String> {
+    let db_path = state.db_dir.join(format!("{}.db", db_name));
+    let sqlite_path = state.db_dir.join(format!("{}.sqlite", db_name));
+
+    let db_file = if db_path.exists() {
+        db_path
+    } else if sqlite_path.exists() {
+        sqlite_path
+    } else {
+        return Err(format!("Base de datos no encontrada: {}", db_name));
+    };
+
+    Connection::open_with_flags(&db_file, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| format!("Error al abrir la base de datos: {}", e))
+}
+
+/// Consulta la bitácora de auditoría de una base de datos
+#[tauri::command]
+pub fn query_audit_log(
+    state: State<AppState>,
+    db_name: String,
+    query: Option<AuditQuery>,
+) -> Result<Vec<AuditEntry>, String> {
+    let conn = open_read_only(&state, &db_name)?;
+    query_entries(&conn, &query.unwrap_or_default())
+}
+
+/// Campo CSV con comillas cuando hace falta
+fn csv_field(value: &str) -> String {
+    if value.contains(',') || value.contains('"') || value.contains('\n') {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn json_to_csv_text(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Exporta la bitácora filtrada como texto JSON o CSV (`format` = "json" | "csv").
+/// Sin `limit` se exportan todas las entradas que cumplan los filtros.
+#[tauri::command]
+pub fn export_audit_log(
+    state: State<AppState>,
+    db_name: String,
+    query: Option<AuditQuery>,
+    format: String,
+) -> Result<String, String> {
+    let conn = open_read_only(&state, &db_name)?;
+    let mut query = query.unwrap_or_default();
+    if query.limit.is_none() {
+        query.limit = Some(usize::MAX >> 1);
+    }
+    let entries = query_entries(&conn, &query)?;
+
+    match format.to_lowercase().as_str() {
+        "json" => serde_json::to_string_pretty(&entries)
+            .map_err(|e| format!("Error al generar el JSON: {}", e)),
+        "csv" => {
+            let mut out = String::from(
+                "id,batch_id,timestamp,station,user_name,operation,table_name,pk_value,column_name,old_value,new_value,context\n",
+            );
+            for e in &entries {
+                let fields = [
+                    e.id.to_string(),
+                    e.batch_id.to_string(),
+                    e.timestamp.clone(),
+                    e.station.clone().unwrap_or_default(),
+                    e.user_name.clone().unwrap_or_default(),
+                    e.operation.clone(),
+                    e.table_name.clone().unwrap_or_default(),
+                    e.pk_value.clone().unwrap_or_default(),
+                    e.column_name.clone().unwrap_or_default(),
+                    json_to_csv_text(&e.old_value),
+                    json_to_csv_text(&e.new_value),
+                    e.context.clone().unwrap_or_default(),
+                ];
+                out.push_str(&fields.iter().map(|f| csv_field(f)).collect::<Vec<_>>().join(","));
+                out.push('\n');
+            }
+            Ok(out)
+        }
+        other => Err(format!("Formato de exportación no soportado: {}", other)),
+    }
+}
@@ -0,0 +1,404 @@
+/// =========================================================================
+/// Módulo: Búsqueda global en todas las bases de datos
+///
+/// Funcionalidades:
+/// - Recorrer todas las bases de datos de `list_databases` y todas las
+///   tablas de `list_tables` buscando un texto (ej. un número de serie)
+/// - Enviar los resultados al frontend de forma incremental mediante eventos
+/// - Cancelar una búsqueda en curso
+///
+/// Si la tabla tiene índice FTS5 (ver `busqueda_tabla.rs`) se usa; si no,
+/// se recorren sus columnas de texto comparando sin acentos ni mayúsculas.
+/// Las dos formas no encuentran lo mismo:
+/// - Con índice solo se buscan las columnas indexadas y cada palabra debe
+///   ser el inicio de una palabra del valor ("lap" encuentra "Laptop",
+///   pero "top" no)
+/// - Sin índice se buscan todas las columnas que no son BLOB y cada palabra
+///   puede aparecer en cualquier parte del valor
+/// Cada lote de resultados indica la forma usada y las columnas revisadas.
+///
+/// Eventos emitidos a la ventana que inició la búsqueda:
+/// - `global-search-results`: lote de coincidencias de una tabla
+/// - `global-search-finished`: resumen al terminar o cancelar
+/// =========================================================================
+
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tauri::State;
+
+use crate::busqueda_tabla::{has_search_index, indexed_columns, primary_key_column, search_in_connection};
+use crate::codec_valores::{decode_value, LogicalType};
+use crate::database_manager::{list_databases, AppState};
+use crate::hub_tablas::list_tables;
+use crate::papelera::active_rows_condition;
+
+/// Número máximo de coincidencias por tabla si el frontend no indica otro.
+const DEFAULT_LIMIT_PER_TABLE: usize = 100;
+
+/// Cantidad de caracteres de contexto a cada lado de la coincidencia.
+const SNIPPET_CONTEXT: usize = 30;
+
+fn quote_identifier(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace("\"", "\"\""))
+}
+
+/// Estado de las búsquedas globales en curso, indexadas por `search_id`
+#[derive(Default)]
+pub struct GlobalSearchState {
+    /// Bandera de cancelación de cada búsqueda activa
+    pub cancel_flags: Arc<Mutex<HashMap<String, Arc<AtomicBool>>>>,
+}
+
+/// Coincidencia de la búsqueda global
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GlobalSearchHit {
+    /// Base de datos donde se encontró
+    pub db_name: String,
+    /// Tabla donde se encontró
+    pub table_name: String,
+    /// Columna de clave primaria del registro
+    pub pk_column: String,
+    /// Valor de la clave primaria del registro
+    pub pk_value: Value,
+    /// Columna que contiene el texto buscado
+    pub column: String,
+    /// Fragmento con el texto resaltado con `<mark>`
+    pub snippet: String,
+}
+
+/// Forma en que se buscó en una tabla
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchMode {
+    /// Índice FTS5: columnas indexadas, cada palabra como inicio de palabra
+    Index,
+    /// Recorrido de la tabla: columnas que no son BLOB, cada palabra como subcadena
+    Scan,
+}
+
+/// Lote de resultados enviado en el evento `global-search-results`
+/// (todas las coincidencias de una tabla)
+#[derive(Debug, Serialize, Clone)]
+pub struct GlobalSearchBatch {
+    pub search_id: String,
+    pub db_name: String,
+    pub table_name: String,
+    /// Forma en que se buscó en la tabla
+    pub mode: SearchMode,
+    /// Columnas revisadas
+    pub columns: Vec<String>,
+    pub hits: Vec<GlobalSearchHit>,
+}
+
+/// Resultado de buscar en una tabla
+struct TableSearch {
+    mode: SearchMode,
+    columns: Vec<String>,
+    hits: Vec<GlobalSearchHit>,
+}
+
+/// Resumen enviado en el evento `global-search-finished`
+#[derive(Debug, Serialize, Clone)]
+pub struct GlobalSearchSummary {
+    pub search_id: String,
+    /// Total de coincidencias enviadas
+    pub total_hits: usize,
+    /// Tablas revisadas
+    pub tables_searched: usize,
+    /// Indica si la búsqueda terminó por cancelación
+    pub cancelled: bool,
+    /// Errores encontrados en bases o tablas concretas (la búsqueda continúa)
+    pub errors: Vec<String>,
+}
+
+/// Normaliza un texto para comparar sin acentos ni mayúsculas.
+/// Conserva un carácter por carácter para poder ubicar la coincidencia en el original.
+fn fold_text(text: &str) -> Vec<char> {
+    text.chars()
+        .map(|c| {
+            let lower = c.to_lowercase().next().unwrap_or(c);
+            match lower {
+                'á' | 'à' | 'ä' | 'â' => 'a',
+                'é' | 'è' | 'ë' | 'ê' => 'e',
+                'í' | 'ì' | 'ï' | 'î' => 'i',
+                'ó' | 'ò' | 'ö' | 'ô' => 'o',
+                'ú' | 'ù' | 'ü' | 'û' => 'u',
+                'ñ' => 'n',
+                other => other,
+            }
+        })
+        .collect()
+}
+
+/// Busca `needle` dentro de `haystack` (ambos ya normalizados) y devuelve la posición
+fn find_chars(haystack: &[char], needle: &[char]) -> Option<usize> {
+    if needle.is_empty() || needle.len() > haystack.len() {
+        return None;
+    }
+    (0..=haystack.len() - needle.len()).find(|&i| &haystack[i..i + needle.len()] == needle)
+}
+
+/// Construye un fragmento del valor original con la coincidencia resaltada
+fn make_snippet(original: &str, start: usize, len: usize) -> String {
+    let chars: Vec<char> = original.chars().collect();
+    let from = start.saturating_sub(SNIPPET_CONTEXT);
+    let to = (start + len + SNIPPET_CONTEXT).min(chars.len());
+
+    let mut snippet = String::new();
+    if from > 0 {
+        snippet.push('…');
+    }
+    snippet.extend(&chars[from..start]);
+    snippet.push_str("<mark>");
+    snippet.extend(&chars[start..start + len]);
+    snippet.push_str("</mark>");
+    snippet.extend(&chars[start + len..to]);
+    if to < chars.len() {
+        snippet.push('…');
+    }
+    snippet
+}
+
+/// Columnas que recorre la búsqueda sin índice (todas las que no son BLOB)
+fn scan_columns(conn: &Connection, table_name: &str) -> Result<Vec<String>, String> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", quote_identifier(table_name)))
+        .map_err(|e| format!("Error al preparar la consulta de columnas: {}", e))?;
+    let text_columns: Vec<String> = stmt.query_map([], |row| {
+        let name: String = row.get(1)?;
+        let type_: String = row.get(2)?;
+        Ok((name, type_))
+    })
+    .map_err(|e| format!("Error al ejecutar la consulta de columnas: {}", e))?
+    .filter_map(|r| r.ok())
+    .filter(|(_, type_)| !type_.eq_ignore_ascii_case("BLOB"))
+    .map(|(name, _)| name)
+    .collect();
+    Ok(text_columns)
+}
+
+/// Búsqueda sin índice: recorre las columnas de texto de la tabla.
+/// Un registro coincide si cada palabra aparece en alguna de sus columnas.
+fn scan_table(
+    conn: &Connection,
+    db_name: &str,
+    table_name: &str,
+    text_columns: &[String],
+    terms: &[Vec<char>],
+    limit: usize,
+    cancel: &AtomicBool,
+) -> Result<Vec<GlobalSearchHit>, String> {
+    let pk_column = primary_key_column(conn, table_name)?;
+
+    if text_columns.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    // Los registros en la papelera no se buscan
+    let where_sql = match active_rows_condition(conn, table_name)? {
+        Some(active) => format!(" WHERE {}", active),
+        None => String::new(),
+    };
+
+    let select_list: Vec<String> = text_columns.iter().map(|c| quote_identifier(c)).collect();
+    let sql = format!(
+        "SELECT {}, {} FROM {}{}",
+        quote_identifier(&pk_column),
+        select_list.join(", "),
+        quote_identifier(table_name),
+        where_sql
+    );
+    let mut stmt = conn.prepare(&sql)
+        .map_err(|e| format!("Error al preparar la consulta de datos: {}", e))?;
+    let mut rows = stmt.query([])
+        .map_err(|e| format!("Error al ejecutar la consulta de datos: {}", e))?;
+
+    let mut hits = Vec::new();
+    while let Some(row) = rows.next().map_err(|e| e.to_string())? {
+        if cancel.load(Ordering::Relaxed) || hits.len() >= limit {
+            break;
+        }
+
+        // Valores de texto de la fila (los números se comparan como texto)
+        let values: Vec<Option<String>> = (0..text_columns.len())
+            .map(|i| match row.get_ref(i + 1) {
+                Ok(rusqlite::types::ValueRef::Text(s)) => Some(String::from_utf8_lossy(s).to_string()),
+                Ok(rusqlite::types::ValueRef::Integer(n)) => Some(n.to_string()),
+                Ok(rusqlite::types::ValueRef::Real(f)) => Some(f.to_string()),
+                _ => None,
+            })
+            .collect();
+        let folded: Vec<Option<Vec<char>>> = values.iter().map(|v| v.as_deref().map(fold_text)).collect();
+
+        // Todas las palabras deben aparecer en al menos una columna
+        let all_terms_found = terms.iter().all(|term| {
+            folded.iter().flatten().any(|f| find_chars(f, term).is_some())
+        });
+        if !all_terms_found {
+            continue;
+        }
+
+        let pk_value = decode_value(row.get_ref(0).map_err(|e| e.to_string())?, LogicalType::Any);
+
+        for (i, column) in text_columns.iter().enumerate() {
+            if let (Some(original), Some(f)) = (&values[i], &folded[i]) {
+                if let Some((start, len)) = terms.iter().find_map(|t| find_chars(f, t).map(|s| (s, t.len()))) {
+                    hits.push(GlobalSearchHit {
+                        db_name: db_name.to_string(),
+                        table_name: table_name.to_string(),
+                        pk_column: pk_column.clone(),
+                        pk_value: pk_value.clone(),
+                        column: column.clone(),
+                        snippet: make_snippet(original, start, len),
+                    });
+                }
+            }
+        }
+    }
+
+    Ok(hits)
+}
+
+/// Busca en una tabla usando su índice FTS5 si existe, o recorriéndola si no
+fn search_one_table(
+    conn: &Connection,
+    db_name: &str,
+    table_name: &str,
+    query: &str,
+    limit: usize,
+    cancel: &AtomicBool,
+) -> Result<TableSearch, String> {
+    if has_search_index(conn, table_name)? {
+        let columns = indexed_columns(conn, table_name)?;
+        let hits = search_in_connection(conn, table_name, query, limit)?;
+        let hits = hits
+            .into_iter()
+            .flat_map(|hit| {
+                let pk_column = hit.pk_column;
+                let pk_value = hit.pk_value;
+                hit.matches.into_iter().map(move |m| GlobalSearchHit {
+                    db_name: db_name.to_string(),
+                    table_name: table_name.to_string(),
+                    pk_column: pk_column.clone(),
+                    pk_value: pk_value.clone(),
+                    column: m.column,
+                    snippet: m.snippet,
+                })
+            })
+            .collect();
+        return Ok(TableSearch { mode: SearchMode::Index, columns, hits });
+    }
+
+    let columns = scan_columns(conn, table_name)?;
+    let terms: Vec<Vec<char>> = query.split_whitespace().map(fold_text).collect();
+    let hits = scan_table(conn, db_name, table_name, &columns, &terms, limit, cancel)?;
+    Ok(TableSearch { mode: SearchMode::Scan, columns, hits })
+}
+
+/// Inicia una búsqueda global en segundo plano.
+///
+/// Devuelve inmediatamente; los resultados llegan en eventos
+/// `global-search-results` y al final se emite `global-search-finished`.
+/// `search_id` lo genera el frontend y sirve para filtrar eventos y cancelar.
+#[tauri::command]
+pub fn global_search(
+    window: tauri::Window,
+    state: State<AppState>,
+    search_state: State<GlobalSearchState>,
+    search_id: String,
+    query: String,
+    limit_per_table: Option<usize>,
+) -> Result<(), String> {
+    if query.trim().is_empty() {
+        return Err("El texto de búsqueda está vacío.".to_string());
+    }
+
+    // Lista de trabajo: cada base de datos con sus tablas
+    let mut targets: Vec<(String, String, Vec<String>)> = Vec::new();
+    let mut errors: Vec<String> = Vec::new();
+    for db in list_databases(state.clone())? {
+        match list_tables(state.clone(), db.name.clone()) {
+            Ok(tables) => targets.push((db.name, db.path, tables.into_iter().map(|t| t.name).collect())),
+            Err(e) => errors.push(format!("{}: {}", db.name, e)),
+        }
+    }
+
+    let cancel = Arc::new(AtomicBool::new(false));
+    search_state.cancel_flags.lock().map_err(|e| e.to_string())?
+        .insert(search_id.clone(), cancel.clone());
+
+    let limit = limit_per_table.unwrap_or(DEFAULT_LIMIT_PER_TABLE);
+    let registry = search_state.cancel_flags.clone();
+
+    std::thread::spawn(move || {
+        let mut total_hits = 0;
+        let mut tables_searched = 0;
+
+        'databases: for (db_name, db_path, tables) in targets {
+            let conn = match Connection::open_with_flags(&db_path, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY) {
+                Ok(conn) => conn,
+                Err(e) => {
+                    errors.push(format!("{}: {}", db_name, e));
+                    continue;
+                }
+            };
+
+            for table_name in tables {
+                if cancel.load(Ordering::Relaxed) {
+                    break 'databases;
+                }
+
+                tables_searched += 1;
+                match search_one_table(&conn, &db_name, &table_name, &query, limit, &cancel) {
+                    Ok(result) if !result.hits.is_empty() => {
+                        total_hits += result.hits.len();
+                        let _ = window.emit("global-search-results", GlobalSearchBatch {
+                            search_id: search_id.clone(),
+                            db_name: db_name.clone(),
+                            table_name: table_name.clone(),
+                            mode: result.mode,
+                            columns: result.columns,
+                            hits: result.hits,
+                        });
+                    }
+                    Ok(_) => {}
+                    Err(e) => errors.push(format!("{}/{}: {}", db_name, table_name, e)),
+                }
+            }
+        }
+
+        // La búsqueda ya no se puede cancelar: se quita del registro
+        if let Ok(mut flags) = registry.lock() {
+            flags.remove(&search_id);
+        }
+
+        let _ = window.emit("global-search-finished", GlobalSearchSummary {
+            search_id,
+            total_hits,
+            tables_searched,
+            cancelled: cancel.load(Ordering::Relaxed),
+            errors,
+        });
+    });
+
+    Ok(())
+}
+
+/// Cancela una búsqueda global en curso
+#[tauri::command]
+pub fn cancel_global_search(
+    search_state: State<GlobalSearchState>,
+    search_id: String,
+) -> Result<bool, String> {
+    let flags = search_state.cancel_flags.lock().map_err(|e| e.to_string())?;
+    match flags.get(&search_id) {
+        Some(flag) => {
+            flag.store(true, Ordering::Relaxed);
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
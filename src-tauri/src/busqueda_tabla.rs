@@ -0,0 +1,524 @@
+/// =========================================================================
+/// Módulo: Búsqueda de texto completo (SQLite FTS5)
+///
+/// Funcionalidades:
+/// - Construir o reconstruir el índice de búsqueda de una tabla
+/// - Mantener el índice sincronizado mediante triggers
+/// - Buscar coincidencias ordenadas por relevancia con fragmentos resaltados
+/// - Eliminar el índice de búsqueda de una tabla
+///
+/// El índice se guarda en una tabla virtual `_unea_fts_<tabla>` que usa el
+/// tokenizador `unicode61 remove_diacritics 2`, de modo que "ubicacion"
+/// encuentra "Ubicación". Cada fila del índice usa el mismo rowid que el
+/// registro de la tabla, así los triggers la localizan sin recorrer el índice.
+/// =========================================================================
+
+use rusqlite::{Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::State;
+
+use crate::codec_valores::{decode_value, LogicalType};
+use crate::database_manager::AppState;
+use crate::papelera::active_rows_condition;
+
+/// Columnas que se indexan por defecto cuando existen en la tabla.
+const DEFAULT_SEARCH_COLUMNS: [&str; 5] = ["Equipo", "Marca", "Modelo", "Serie", "Ubicación"];
+
+/// Marcadores con los que se resaltan las coincidencias en los fragmentos.
+const MARK_OPEN: &str = "<mark>";
+const MARK_CLOSE: &str = "</mark>";
+
+/// Columna con la clave primaria del registro en los índices de versiones
+/// anteriores; se reemplazan por el rowid al reconstruir el índice.
+const LEGACY_FTS_PK_COLUMN: &str = "_pk";
+
+/// Helper function to properly quote SQL identifiers (table names, column names)
+/// Handles identifiers with spaces or special characters by wrapping in double quotes
+/// and escaping any existing double quotes by doubling them
+fn quote_identifier(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace("\"", "\"\""))
+}
+
+/// Coincidencia dentro de una columna concreta del registro
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SearchMatch {
+    /// Columna donde se encontró el término
+    pub column: String,
+    /// Fragmento del valor con los términos resaltados
+    pub snippet: String,
+}
+
+/// Registro encontrado por la búsqueda
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SearchHit {
+    /// Columna usada como clave primaria ("rowid" si la tabla no define una)
+    pub pk_column: String,
+    /// Valor de la clave primaria del registro
+    pub pk_value: Value,
+    /// Relevancia del resultado (mayor es mejor)
+    pub score: f64,
+    /// Columnas que contienen los términos buscados
+    pub matches: Vec<SearchMatch>,
+}
+
+/// Información del índice de búsqueda de una tabla
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SearchIndexInfo {
+    /// Nombre de la tabla indexada
+    pub table_name: String,
+    /// Columnas incluidas en el índice
+    pub columns: Vec<String>,
+    /// Número de registros indexados
+    pub indexed_rows: i64,
+}
+
+/// Nombre de la tabla virtual FTS5 asociada a una tabla
+pub(crate) fn fts_table_name(table_name: &str) -> String {
+    format!("_unea_fts_{}", table_name)
+}
+
+/// Prefijo común de los triggers que mantienen el índice sincronizado
+fn trigger_name(table_name: &str, suffix: &str) -> String {
+    format!("_unea_fts_{}_{}", table_name, suffix)
+}
+
+/// Devuelve la columna de clave primaria de la tabla, o "rowid" si no tiene.
+/// Con una clave primaria compuesta ninguna columna identifica por sí sola
+/// a la fila, así que también se usa "rowid".
+pub(crate) fn primary_key_column(conn: &Connection, table_name: &str) -> Result<String, String> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", quote_identifier(table_name)))
+        .map_err(|e| format!("Error al preparar la consulta de columnas: {}", e))?;
+
+    let pk_columns: Vec<String> = stmt.query_map([], |row| {
+        let name: String = row.get(1)?;
+        let pk: i64 = row.get(5)?;
+        Ok((name, pk))
+    })
+    .map_err(|e| format!("Error al ejecutar la consulta de columnas: {}", e))?
+    .filter_map(|r| r.ok())
+    .filter(|(_, pk)| *pk > 0)
+    .map(|(name, _)| name)
+    .collect();
+
+    match pk_columns.as_slice() {
+        [single] => Ok(single.clone()),
+        _ => Ok("rowid".to_string()),
+    }
+}
+
+/// Indica si la tabla tiene un índice de búsqueda
+pub(crate) fn has_search_index(conn: &Connection, table_name: &str) -> Result<bool, String> {
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name=?",
+        [fts_table_name(table_name)],
+        |row| row.get(0),
+    ).map_err(|e| format!("Error al consultar el índice de búsqueda: {}", e))?;
+    Ok(count > 0)
+}
+
+/// Columnas indexadas por el índice de búsqueda de la tabla (sin la clave primaria)
+pub(crate) fn indexed_columns(conn: &Connection, table_name: &str) -> Result<Vec<String>, String> {
+    let columns = index_columns(conn, table_name)?;
+    Ok(columns.into_iter().filter(|c| c != LEGACY_FTS_PK_COLUMN).collect())
+}
+
+/// Indica si el índice es de una versión anterior (clave primaria en `_pk`)
+fn is_legacy_index(conn: &Connection, table_name: &str) -> Result<bool, String> {
+    Ok(index_columns(conn, table_name)?.iter().any(|c| c == LEGACY_FTS_PK_COLUMN))
+}
+
+/// Reconstruye con el formato actual un índice de una versión anterior
+fn upgrade_search_index(conn: &Connection, table_name: &str) -> Result<(), String> {
+    if !is_legacy_index(conn, table_name)? {
+        return Ok(());
+    }
+    let columns = indexed_columns(conn, table_name)?;
+    let tx = conn.unchecked_transaction()
+        .map_err(|e| format!("Error al iniciar la transacción: {}", e))?;
+    drop_search_index(&tx, table_name)?;
+    create_search_index(&tx, table_name, &columns)?;
+    tx.commit().map_err(|e| format!("Error al confirmar transacción: {}", e))
+}
+
+/// Todas las columnas de la tabla virtual FTS5
+fn index_columns(conn: &Connection, table_name: &str) -> Result<Vec<String>, String> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", quote_identifier(&fts_table_name(table_name))))
+        .map_err(|e| format!("Error al leer el índice de búsqueda: {}", e))?;
+
+    let columns = stmt.query_map([], |row| row.get::<_, String>(1))
+        .map_err(|e| format!("Error al leer el índice de búsqueda: {}", e))?
+        .collect::<Result<Vec<String>, _>>()
+        .map_err(|e| format!("Error al leer el índice de búsqueda: {}", e))?;
+
+    Ok(columns)
+}
+
+/// Elimina el índice de búsqueda y sus triggers, si existen.
+/// Se usa también antes de borrar tablas o columnas indexadas.
+pub(crate) fn drop_search_index(conn: &Connection, table_name: &str) -> Result<(), String> {
+    for suffix in ["ai", "ad", "au"] {
+        conn.execute(&format!("DROP TRIGGER IF EXISTS {}", quote_identifier(&trigger_name(table_name, suffix))), [])
+            .map_err(|e| format!("Error al eliminar trigger de búsqueda: {}", e))?;
+    }
+    conn.execute(&format!("DROP TABLE IF EXISTS {}", quote_identifier(&fts_table_name(table_name))), [])
+        .map_err(|e| format!("Error al eliminar el índice de búsqueda: {}", e))?;
+    Ok(())
+}
+
+/// Crea la tabla FTS5, los triggers de sincronización y carga los datos actuales
+pub(crate) fn create_search_index(conn: &Connection, table_name: &str, columns: &[String]) -> Result<(), String> {
+    let fts = quote_identifier(&fts_table_name(table_name));
+    let table = quote_identifier(table_name);
+
+    let quoted: Vec<String> = columns.iter().map(|c| quote_identifier(c)).collect();
+    let new_values: Vec<String> = quoted.iter().map(|c| format!("new.{}", c)).collect();
+
+    conn.execute(
+        &format!(
+            "CREATE VIRTUAL TABLE {} USING fts5({}, tokenize = 'unicode61 remove_diacritics 2')",
+            fts,
+            quoted.join(", ")
+        ),
+        [],
+    ).map_err(|e| format!("Error al crear el índice de búsqueda: {}", e))?;
+
+    // Triggers: insertar, borrar y actualizar mantienen el índice al día.
+    // Las filas se localizan por rowid, sin recorrer el índice.
+    let insert_sql = format!(
+        "INSERT INTO {} (rowid, {}) VALUES (new.rowid, {})",
+        fts, quoted.join(", "), new_values.join(", ")
+    );
+    let delete_sql = format!("DELETE FROM {} WHERE rowid = old.rowid", fts);
+
+    let triggers = [
+        ("ai", "AFTER INSERT", insert_sql.clone()),
+        ("ad", "AFTER DELETE", delete_sql.clone()),
+        ("au", "AFTER UPDATE", format!("{}; {}", delete_sql, insert_sql)),
+    ];
+    for (suffix, event, body) in triggers.iter() {
+        conn.execute(
+            &format!(
+                "CREATE TRIGGER {} {} ON {} BEGIN {}; END",
+                quote_identifier(&trigger_name(table_name, suffix)),
+                event,
+                table,
+                body
+            ),
+            [],
+        ).map_err(|e| format!("Error al crear trigger de búsqueda: {}", e))?;
+    }
+
+    // Carga inicial con los registros existentes
+    conn.execute(
+        &format!(
+            "INSERT INTO {} (rowid, {}) SELECT rowid, {} FROM {}",
+            fts, quoted.join(", "), quoted.join(", "), table
+        ),
+        [],
+    ).map_err(|e| format!("Error al poblar el índice de búsqueda: {}", e))?;
+
+    Ok(())
+}
+
+/// Convierte el texto del usuario en una consulta FTS5 segura.
+/// Cada palabra se busca como prefijo y todas deben aparecer: "lenovo aula 3"
+/// se convierte en `"lenovo"* "aula"* "3"*`.
+pub(crate) fn build_match_query(query: &str) -> Option<String> {
+    let terms: Vec<String> = query
+        .split_whitespace()
+        .map(|t| format!("\"{}\"*", t.replace("\"", "\"\"")))
+        .collect();
+
+    if terms.is_empty() {
+        None
+    } else {
+        Some(terms.join(" "))
+    }
+}
+
+/// Ejecuta la búsqueda sobre una conexión abierta. Requiere que el índice exista.
+/// Acepta también índices de versiones anteriores, ya que la búsqueda global
+/// abre las bases de datos en solo lectura y no puede reconstruirlos.
+pub(crate) fn search_in_connection(
+    conn: &Connection,
+    table_name: &str,
+    query: &str,
+    limit: usize,
+) -> Result<Vec<SearchHit>, String> {
+    let match_query = match build_match_query(query) {
+        Some(q) => q,
+        None => return Ok(Vec::new()),
+    };
+
+    let pk_column = primary_key_column(conn, table_name)?;
+    let columns = indexed_columns(conn, table_name)?;
+    let fts = quote_identifier(&fts_table_name(table_name));
+    let table = quote_identifier(table_name);
+    let pk = quote_identifier(&pk_column);
+
+    // Las filas del índice comparten el rowid del registro; los índices
+    // anteriores guardan la clave primaria en `_pk` (primera columna)
+    let legacy = is_legacy_index(conn, table_name)?;
+    let (join_key, first_column) = if legacy {
+        (format!("t.{} = {}.{}", pk, fts, LEGACY_FTS_PK_COLUMN), 1)
+    } else {
+        (format!("t.rowid = {}.rowid", fts), 0)
+    };
+
+    // highlight() indica si la columna contiene el término y snippet() da el fragmento
+    let mut select_parts: Vec<String> = vec![format!("t.{}", pk), format!("bm25({})", fts)];
+    for i in 0..columns.len() {
+        let column = first_column + i;
+        select_parts.push(format!("highlight({}, {}, '{}', '{}')", fts, column, MARK_OPEN, MARK_CLOSE));
+        select_parts.push(format!("snippet({}, {}, '{}', '{}', '…', 12)", fts, column, MARK_OPEN, MARK_CLOSE));
+    }
+
+    // Los registros en la papelera siguen indexados pero no se devuelven
+    let active_filter = match active_rows_condition(conn, table_name)? {
+        Some(active) => format!(
+            " AND t.rowid IN (SELECT rowid FROM {} WHERE {})",
+            table,
+            active
+        ),
+        None => String::new(),
+    };
+
+    let sql = format!(
+        "SELECT {} FROM {} JOIN {} AS t ON {} WHERE {} MATCH ?{} ORDER BY bm25({}) LIMIT ?",
+        select_parts.join(", "),
+        fts,
+        table,
+        join_key,
+        fts,
+        active_filter,
+        fts
+    );
+
+    let mut stmt = conn.prepare(&sql)
+        .map_err(|e| format!("Error al preparar la búsqueda: {}", e))?;
+
+    let hits = stmt.query_map(rusqlite::params![match_query, limit as i64], |row| {
+        let pk_value = decode_value(row.get_ref(0)?, LogicalType::Any);
+        let bm25: f64 = row.get(1)?;
+
+        let mut matches = Vec::new();
+        for (i, column) in columns.iter().enumerate() {
+            let highlighted: Option<String> = row.get(2 + i * 2)?;
+            if highlighted.map_or(false, |h| h.contains(MARK_OPEN)) {
+                let snippet: Option<String> = row.get(3 + i * 2)?;
+                matches.push(SearchMatch {
+                    column: column.clone(),
+                    snippet: snippet.unwrap_or_default(),
+                });
+            }
+        }
+
+        Ok(SearchHit {
+            pk_column: pk_column.clone(),
+            pk_value,
+            // bm25() devuelve valores negativos: cuanto menor, más relevante
+            score: -bm25,
+            matches,
+        })
+    })
+    .map_err(|e| format!("Error al ejecutar la búsqueda: {}", e))?
+    .collect::<Result<Vec<SearchHit>, _>>()
+    .map_err(|e| format!("Error al leer los resultados de la búsqueda: {}", e))?;
+
+    Ok(hits)
+}
+
+/// Construye (o reconstruye) el índice de búsqueda de una tabla.
+///
+/// # Argumentos
+/// - `columns`: columnas a indexar. Si se omite se usan Equipo, Marca, Modelo,
+///   Serie y Ubicación; si la tabla no tiene ninguna, todas las columnas que no son BLOB.
+#[tauri::command]
+pub fn build_search_index(
+    state: State<AppState>,
+    db_name: String,
+    table_name: String,
+    columns: Option<Vec<String>>,
+) -> Result<SearchIndexInfo, String> {
+    let db_path = state.db_dir.join(format!("{}.db", db_name));
+    let sqlite_path = state.db_dir.join(format!("{}.sqlite", db_name));
+
+    let db_file = if db_path.exists() {
+        db_path
+    } else if sqlite_path.exists() {
+        sqlite_path
+    } else {
+        return Err(format!("No se encontró la base de datos: {}", db_name));
+    };
+
+    let mut conn = Connection::open(&db_file)
+        .map_err(|e| format!("Error al abrir la base de datos: {}", e))?;
+
+    // Columnas reales de la tabla con sus tipos
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", quote_identifier(&table_name)))
+        .map_err(|e| format!("Error al preparar la consulta de columnas: {}", e))?;
+    let columns_info: Vec<(String, String)> = stmt.query_map([], |row| {
+        let name: String = row.get(1)?;
+        let type_: String = row.get(2)?;
+        Ok((name, type_))
+    })
+    .map_err(|e| format!("Error al ejecutar la consulta de columnas: {}", e))?
+    .collect::<Result<Vec<_>, _>>()
+    .map_err(|e| format!("Error al obtener columnas: {}", e))?;
+    drop(stmt);
+
+    if columns_info.is_empty() {
+        return Err(format!("No se encontró la tabla: {}", table_name));
+    }
+
+    let selected: Vec<String> = match columns {
+        Some(requested) => {
+            for col in &requested {
+                if !columns_info.iter().any(|(name, _)| name == col) {
+                    return Err(format!("La columna '{}' no existe en la tabla '{}'", col, table_name));
+                }
+            }
+            requested
+        }
+        None => {
+            let defaults: Vec<String> = DEFAULT_SEARCH_COLUMNS
+                .iter()
+                .filter(|c| columns_info.iter().any(|(name, _)| name == *c))
+                .map(|c| c.to_string())
+                .collect();
+            if defaults.is_empty() {
+                columns_info
+                    .iter()
+                    .filter(|(_, type_)| !type_.eq_ignore_ascii_case("BLOB"))
+                    .map(|(name, _)| name.clone())
+                    .collect()
+            } else {
+                defaults
+            }
+        }
+    };
+
+    if selected.is_empty() {
+        return Err("No hay columnas de texto para indexar.".to_string());
+    }
+
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    drop_search_index(&tx, &table_name)?;
+    create_search_index(&tx, &table_name, &selected)?;
+
+    let indexed_rows: i64 = tx.query_row(
+        &format!("SELECT COUNT(*) FROM {}", quote_identifier(&fts_table_name(&table_name))),
+        [],
+        |row| row.get(0),
+    ).map_err(|e| format!("Error al contar registros indexados: {}", e))?;
+
+    tx.commit().map_err(|e| format!("Error al confirmar transacción: {}", e))?;
+
+    Ok(SearchIndexInfo {
+        table_name,
+        columns: selected,
+        indexed_rows,
+    })
+}
+
+/// Elimina el índice de búsqueda de una tabla
+#[tauri::command]
+pub fn delete_search_index(
+    state: State<AppState>,
+    db_name: String,
+    table_name: String,
+) -> Result<(), String> {
+    let db_path = state.db_dir.join(format!("{}.db", db_name));
+    let sqlite_path = state.db_dir.join(format!("{}.sqlite", db_name));
+
+    let db_file = if db_path.exists() {
+        db_path
+    } else if sqlite_path.exists() {
+        sqlite_path
+    } else {
+        return Err(format!("No se encontró la base de datos: {}", db_name));
+    };
+
+    let conn = Connection::open(&db_file)
+        .map_err(|e| format!("Error al abrir la base de datos: {}", e))?;
+
+    drop_search_index(&conn, &table_name)
+}
+
+/// Busca registros de una tabla que contengan todas las palabras de `query`.
+/// Los resultados vienen ordenados por relevancia e incluyen fragmentos con
+/// los términos resaltados con `<mark>`.
+#[tauri::command]
+pub fn search_table(
+    state: State<AppState>,
+    db_name: String,
+    table_name: String,
+    query: String,
+    limit: Option<usize>,
+) -> Result<Vec<SearchHit>, String> {
+    let db_path = state.db_dir.join(format!("{}.db", db_name));
+    let sqlite_path = state.db_dir.join(format!("{}.sqlite", db_name));
+
+    let db_file = if db_path.exists() {
+        db_path
+    } else if sqlite_path.exists() {
+        sqlite_path
+    } else {
+        return Err(format!("No se encontró la base de datos: {}", db_name));
+    };
+
+    let conn = Connection::open(&db_file)
+        .map_err(|e| format!("Error al abrir la base de datos: {}", e))?;
+
+    if !has_search_index(&conn, &table_name)? {
+        return Err(format!(
+            "La tabla '{}' no tiene índice de búsqueda. Constrúyalo con build_search_index.",
+            table_name
+        ));
+    }
+
+    // Los índices de versiones anteriores se reconstruyen al buscar
+    upgrade_search_index(&conn, &table_name)?;
+
+    search_in_connection(&conn, &table_name, &query, limit.unwrap_or(50))
+}
+
+/// Devuelve las columnas indexadas de una tabla, o None si no tiene índice
+#[tauri::command]
+pub fn get_search_index_info(
+    state: State<AppState>,
+    db_name: String,
+    table_name: String,
+) -> Result<Option<SearchIndexInfo>, String> {
+    let db_path = state.db_dir.join(format!("{}.db", db_name));
+    let sqlite_path = state.db_dir.join(format!("{}.sqlite", db_name));
+
+    let db_file = if db_path.exists() {
+        db_path
+    } else if sqlite_path.exists() {
+        sqlite_path
+    } else {
+        return Err(format!("No se encontró la base de datos: {}", db_name));
+    };
+
+    let conn = Connection::open(&db_file)
+        .map_err(|e| format!("Error al abrir la base de datos: {}", e))?;
+
+    if !has_search_index(&conn, &table_name)? {
+        return Ok(None);
+    }
+
+    let columns = indexed_columns(&conn, &table_name)?;
+    let indexed_rows: Option<i64> = conn.query_row(
+        &format!("SELECT COUNT(*) FROM {}", quote_identifier(&fts_table_name(&table_name))),
+        [],
+        |row| row.get(0),
+    ).optional().map_err(|e| format!("Error al contar registros indexados: {}", e))?;
+
+    Ok(Some(SearchIndexInfo {
+        table_name,
+        columns,
+        indexed_rows: indexed_rows.unwrap_or(0),
+    }))
+}
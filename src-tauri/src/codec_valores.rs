@@ -0,0 +1,307 @@
+/// =========================================================================
+/// Módulo: Conversión de valores JSON ↔ SQLite
+///
+/// Único punto de conversión entre los valores que envía/recibe el
+/// frontend (JSON) y los que se guardan en SQLite. La conversión depende
+/// del tipo lógico de la columna, que se deduce de su tipo declarado:
+/// - INTEGER / REAL: acepta números o texto numérico ("3,5" = 3.5);
+///   el texto vacío se guarda como NULL
+/// - BOOLEAN: true/false, 1/0, "sí"/"no"; se guarda como 1/0 y se lee como true/false
+/// - DATE: se guarda como AAAA-MM-DD (acepta también DD/MM/AAAA)
+/// - DATETIME / TIMESTAMP: RFC 3339 se conserva; las demás formas se
+///   guardan como AAAA-MM-DD HH:MM:SS
+/// - BLOB: texto en base64 (imágenes); se lee también en base64
+/// - TEXT: cualquier valor como texto
+/// - Sin tipo declarado: el valor JSON tal cual
+///
+/// Un NULL en una columna de texto NOT NULL se guarda como texto vacío.
+/// Los valores que no se pueden convertir devuelven un error que indica
+/// la columna y el valor.
+/// =========================================================================
+
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use rusqlite::types::{Value as SqlValue, ValueRef};
+use rusqlite::Connection;
+use serde_json::Value;
+
+/// Tipo lógico de una columna
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum LogicalType {
+    Integer,
+    Real,
+    Boolean,
+    Date,
+    DateTime,
+    Text,
+    Blob,
+    /// Sin tipo declarado (o desconocido): se guarda el valor JSON tal cual
+    Any,
+}
+
+impl LogicalType {
+    /// Tipo lógico a partir del tipo declarado (siguiendo las reglas de afinidad de SQLite)
+    pub(crate) fn from_declared(declared: &str) -> Self {
+        let declared = declared.trim().to_uppercase();
+        let has = |part: &str| declared.contains(part);
+        if declared.is_empty() {
+            LogicalType::Any
+        } else if has("BOOL") {
+            LogicalType::Boolean
+        } else if has("DATETIME") || has("TIMESTAMP") {
+            LogicalType::DateTime
+        } else if has("DATE") {
+            LogicalType::Date
+        } else if has("INT") {
+            LogicalType::Integer
+        } else if has("CHAR") || has("CLOB") || has("TEXT") {
+            LogicalType::Text
+        } else if has("BLOB") {
+            LogicalType::Blob
+        } else if has("REAL") || has("FLOA") || has("DOUB") || has("NUMERIC") || has("DECIMAL") {
+            LogicalType::Real
+        } else {
+            LogicalType::Any
+        }
+    }
+}
+
+/// Columna con lo necesario para convertir sus valores
+#[derive(Debug, Clone)]
+pub(crate) struct ColumnCodec {
+    pub name: String,
+    pub logical: LogicalType,
+    pub notnull: bool,
+}
+
+impl ColumnCodec {
+    pub(crate) fn new(name: &str, declared_type: &str, notnull: bool) -> Self {
+        ColumnCodec {
+            name: name.to_string(),
+            logical: LogicalType::from_declared(declared_type),
+            notnull,
+        }
+    }
+
+    /// Convierte un valor JSON para guardarlo en esta columna
+    pub(crate) fn encode(&self, value: &Value) -> Result<SqlValue, String> {
+        let encoded = encode_value(value, self.logical)
+            .map_err(|e| format!("Columna '{}': {}", self.name, e))?;
+        if encoded == SqlValue::Null && self.notnull && self.logical == LogicalType::Text {
+            return Ok(SqlValue::Text(String::new()));
+        }
+        Ok(encoded)
+    }
+
+    /// Convierte un valor leído de esta columna a JSON
+    pub(crate) fn decode(&self, value: ValueRef) -> Value {
+        decode_value(value, self.logical)
+    }
+}
+
+/// Columnas de una tabla en su orden
+pub(crate) fn table_codecs(conn: &Connection, table_name: &str) -> Result<Vec<ColumnCodec>, String> {
+    let mut stmt = conn.prepare(&format!(
+        "PRAGMA table_info(\"{}\")",
+        table_name.replace("\"", "\"\"")
+    )).map_err(|e| format!("Error al preparar la consulta de columnas: {}", e))?;
+    let codecs = stmt.query_map([], |row| {
+        let name: String = row.get(1)?;
+        let declared: String = row.get(2)?;
+        let notnull: i64 = row.get(3)?;
+        Ok(ColumnCodec::new(&name, &declared, notnull != 0))
+    })
+    .map_err(|e| format!("Error al ejecutar la consulta de columnas: {}", e))?
+    .collect::<Result<Vec<_>, _>>()
+    .map_err(|e| format!("Error al obtener columnas: {}", e))?;
+
+    if codecs.is_empty() {
+        return Err(format!("No se encontró la tabla: {}", table_name));
+    }
+    Ok(codecs)
+}
+
+/// Busca la columna por nombre
+pub(crate) fn find_codec<'a>(codecs: &'a [ColumnCodec], column: &str) -> Result<&'a ColumnCodec, String> {
+    codecs
+        .iter()
+        .find(|c| c.name == column)
+        .ok_or_else(|| format!("La columna '{}' no existe en la tabla", column))
+}
+
+/// Número escrito como texto; acepta coma decimal
+fn parse_number(text: &str) -> Option<f64> {
+    let text = text.trim();
+    text.parse::<f64>()
+        .ok()
+        .or_else(|| if text.contains('.') { None } else { text.replace(',', ".").parse().ok() })
+        .filter(|f| f.is_finite())
+}
+
+fn parse_integer(text: &str) -> Option<i64> {
+    let text = text.trim();
+    text.parse::<i64>().ok().or_else(|| {
+        parse_number(text).filter(|f| f.fract() == 0.0 && f.abs() < 9.0e15).map(|f| f as i64)
+    })
+}
+
+fn parse_bool(text: &str) -> Option<bool> {
+    match text.trim().to_lowercase().as_str() {
+        "1" | "true" | "verdadero" | "sí" | "si" | "s" | "x" => Some(true),
+        "0" | "false" | "falso" | "no" | "n" => Some(false),
+        _ => None,
+    }
+}
+
+fn parse_date(text: &str) -> Option<NaiveDate> {
+    ["%Y-%m-%d", "%d/%m/%Y", "%d-%m-%Y", "%Y/%m/%d"]
+        .iter()
+        .find_map(|fmt| NaiveDate::parse_from_str(text, fmt).ok())
+}
+
+/// Fecha y hora normalizada; RFC 3339 se conserva tal cual
+fn normalize_datetime(text: &str) -> Option<String> {
+    if DateTime::parse_from_rfc3339(text).is_ok() {
+        return Some(text.to_string());
+    }
+    let naive = [
+        "%Y-%m-%d %H:%M:%S%.f",
+        "%Y-%m-%dT%H:%M:%S%.f",
+        "%Y-%m-%d %H:%M",
+        "%Y-%m-%dT%H:%M",
+        "%d/%m/%Y %H:%M:%S",
+        "%d/%m/%Y %H:%M",
+    ]
+    .iter()
+    .find_map(|fmt| NaiveDateTime::parse_from_str(text, fmt).ok());
+    match naive {
+        Some(dt) => Some(dt.format("%Y-%m-%d %H:%M:%S").to_string()),
+        None => parse_date(text).map(|d| d.format("%Y-%m-%d").to_string()),
+    }
+}
+
+/// Convierte un valor JSON al valor SQL del tipo lógico indicado
+pub(crate) fn encode_value(value: &Value, logical: LogicalType) -> Result<SqlValue, String> {
+    let invalid = |what: &str| Err(format!("el valor {} no es {}", value, what));
+
+    // Texto vacío en columnas no textuales = sin valor
+    if let Value::String(s) = value {
+        if s.trim().is_empty() && !matches!(logical, LogicalType::Text | LogicalType::Any) {
+            return Ok(SqlValue::Null);
+        }
+    }
+
+    match (logical, value) {
+        (_, Value::Null) => Ok(SqlValue::Null),
+
+        (LogicalType::Integer, Value::Bool(b)) => Ok(SqlValue::Integer(*b as i64)),
+        (LogicalType::Integer, Value::Number(n)) => match n.as_i64() {
+            Some(i) => Ok(SqlValue::Integer(i)),
+            None => match n.as_f64().and_then(|f| parse_integer(&f.to_string())) {
+                Some(i) => Ok(SqlValue::Integer(i)),
+                None => invalid("un número entero"),
+            },
+        },
+        (LogicalType::Integer, Value::String(s)) => match parse_integer(s) {
+            Some(i) => Ok(SqlValue::Integer(i)),
+            None => invalid("un número entero"),
+        },
+
+        (LogicalType::Real, Value::Bool(b)) => Ok(SqlValue::Real(*b as i64 as f64)),
+        (LogicalType::Real, Value::Number(n)) => match n.as_f64() {
+            Some(f) => Ok(SqlValue::Real(f)),
+            None => invalid("un número"),
+        },
+        (LogicalType::Real, Value::String(s)) => match parse_number(s) {
+            Some(f) => Ok(SqlValue::Real(f)),
+            None => invalid("un número"),
+        },
+
+        (LogicalType::Boolean, Value::Bool(b)) => Ok(SqlValue::Integer(*b as i64)),
+        (LogicalType::Boolean, Value::Number(n)) => match n.as_i64() {
+            Some(0) => Ok(SqlValue::Integer(0)),
+            Some(1) => Ok(SqlValue::Integer(1)),
+            _ => invalid("sí/no"),
+        },
+        (LogicalType::Boolean, Value::String(s)) => match parse_bool(s) {
+            Some(b) => Ok(SqlValue::Integer(b as i64)),
+            None => invalid("sí/no"),
+        },
+
+        (LogicalType::Date, Value::String(s)) => match parse_date(s.trim()).or_else(|| {
+            DateTime::parse_from_rfc3339(s.trim()).ok().map(|dt| dt.date_naive())
+        }) {
+            Some(date) => Ok(SqlValue::Text(date.format("%Y-%m-%d").to_string())),
+            None => invalid("una fecha (AAAA-MM-DD)"),
+        },
+        (LogicalType::DateTime, Value::String(s)) => match normalize_datetime(s.trim()) {
+            Some(text) => Ok(SqlValue::Text(text)),
+            None => invalid("una fecha y hora"),
+        },
+        (LogicalType::Date | LogicalType::DateTime, _) => invalid("una fecha"),
+
+        (LogicalType::Blob, Value::String(s)) => match general_purpose::STANDARD.decode(s.trim()) {
+            Ok(bytes) => Ok(SqlValue::Blob(bytes)),
+            Err(_) => Err("el valor no es una imagen o archivo en base64 válido".to_string()),
+        },
+        (LogicalType::Blob, _) => invalid("una imagen o archivo en base64"),
+
+        (LogicalType::Text, Value::String(s)) => Ok(SqlValue::Text(s.clone())),
+        (LogicalType::Text, Value::Number(n)) => Ok(SqlValue::Text(n.to_string())),
+        (LogicalType::Text, Value::Bool(b)) => Ok(SqlValue::Text(b.to_string())),
+
+        (LogicalType::Any, Value::Bool(b)) => Ok(SqlValue::Integer(*b as i64)),
+        (LogicalType::Any, Value::Number(n)) => match (n.as_i64(), n.as_f64()) {
+            (Some(i), _) => Ok(SqlValue::Integer(i)),
+            (None, Some(f)) => Ok(SqlValue::Real(f)),
+            _ => invalid("un número válido"),
+        },
+        (LogicalType::Any, Value::String(s)) => Ok(SqlValue::Text(s.clone())),
+
+        // Listas y objetos se guardan como texto JSON en columnas de texto
+        (LogicalType::Text | LogicalType::Any, Value::Array(_) | Value::Object(_)) => {
+            Ok(SqlValue::Text(value.to_string()))
+        }
+        (_, Value::Array(_) | Value::Object(_)) => invalid("un valor simple"),
+    }
+}
+
+/// Convierte un valor leído de SQLite a JSON según el tipo lógico de la columna.
+/// Los BLOB se devuelven en base64.
+pub(crate) fn decode_value(value: ValueRef, logical: LogicalType) -> Value {
+    match value {
+        ValueRef::Null => Value::Null,
+        ValueRef::Integer(i) => match logical {
+            LogicalType::Boolean => Value::Bool(i != 0),
+            _ => Value::Number(i.into()),
+        },
+        ValueRef::Real(f) => match logical {
+            LogicalType::Integer if f.fract() == 0.0 && f.abs() < 9.0e15 => Value::Number((f as i64).into()),
+            _ => serde_json::Number::from_f64(f).map(Value::Number).unwrap_or(Value::Null),
+        },
+        ValueRef::Text(bytes) => {
+            let text = String::from_utf8_lossy(bytes).to_string();
+            // Texto guardado en columnas numéricas o booleanas (ej. por versiones anteriores)
+            let converted = match logical {
+                LogicalType::Integer => parse_integer(&text).map(|i| Value::Number(i.into())),
+                LogicalType::Real => parse_number(&text)
+                    .and_then(serde_json::Number::from_f64)
+                    .map(Value::Number),
+                LogicalType::Boolean => parse_bool(&text).map(Value::Bool),
+                _ => None,
+            };
+            converted.unwrap_or(Value::String(text))
+        }
+        ValueRef::Blob(bytes) => Value::String(general_purpose::STANDARD.encode(bytes)),
+    }
+}
+
+/// Conversión sin tipo de columna (claves primarias, parámetros de consultas, resultados SQL)
+pub(crate) fn json_to_sql(value: &Value) -> Result<SqlValue, String> {
+    encode_value(value, LogicalType::Any)
+}
+
+/// Conversión a JSON sin tipo de columna; los BLOB se devuelven en base64
+pub(crate) fn sql_to_json(value: &SqlValue) -> Value {
+    decode_value(ValueRef::from(value), LogicalType::Any)
+}
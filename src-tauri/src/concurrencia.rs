@@ -0,0 +1,179 @@
+/// =========================================================================
+/// Módulo: Control de concurrencia optimista
+///
+/// Varias estaciones pueden editar el mismo archivo `.db` compartido. Para
+/// no sobrescribir en silencio el trabajo de otra estación:
+/// - El frontend envía los valores originales del registro (tal como los
+///   leyó), que pueden incluir las columnas `version` o `updated_at`
+/// - Antes de escribir se comparan con los valores actuales dentro de la
+///   misma transacción
+/// - Si algo cambió, se devuelve un error de conflicto con los valores
+///   actuales para que el usuario pueda combinar los cambios
+/// - Al guardar se incrementa `version` y se actualiza `updated_at` si la
+///   tabla tiene esas columnas
+/// =========================================================================
+
+use std::collections::HashMap;
+
+use chrono::Local;
+use rusqlite::types::ValueRef;
+use rusqlite::{Connection, OptionalExtension};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::almacen_imagenes::is_image_ref;
+use crate::codec_valores::{find_codec, table_codecs};
+use crate::protocolo_imagenes::{image_url, is_image_url};
+
+/// Columna de versión que se incrementa en cada modificación
+pub(crate) const VERSION_COLUMN: &str = "version";
+/// Columna con la fecha de la última modificación
+pub(crate) const UPDATED_AT_COLUMN: &str = "updated_at";
+/// Prefijo de los errores de conflicto; le sigue un JSON con `UpdateConflict`
+pub(crate) const CONFLICT_PREFIX: &str = "CONFLICTO:";
+
+fn quote_identifier(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace("\"", "\"\""))
+}
+
+/// Detalle de un conflicto de edición
+#[derive(Debug, Serialize)]
+pub struct UpdateConflict {
+    pub message: String,
+    pub table_name: String,
+    pub pk_column: String,
+    pub pk_value: Value,
+    /// Columnas cuyo valor actual ya no coincide con el original
+    pub changed_columns: Vec<String>,
+    /// Valores actuales del registro (None si fue eliminado)
+    pub current_values: Option<HashMap<String, Value>>,
+}
+
+impl UpdateConflict {
+    /// Error listo para devolver al frontend: prefijo + JSON
+    fn into_error(self) -> String {
+        format!("{}{}", CONFLICT_PREFIX, serde_json::to_string(&self).unwrap_or_default())
+    }
+}
+
+/// Texto comparable de un valor: el frontend puede enviar "5" para un 5 numérico
+fn comparable(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => None,
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => {
+            // 5.0 y 5 son el mismo valor
+            match n.as_f64() {
+                Some(f) if f.fract() == 0.0 && f.abs() < 1e15 => Some(format!("{}", f as i64)),
+                _ => Some(n.to_string()),
+            }
+        }
+        other => Some(other.to_string()),
+    }
+}
+
+/// Lee el registro actual como JSON. Las imágenes se devuelven como URL del protocolo.
+pub(crate) fn current_row(
+    conn: &Connection,
+    db_name: &str,
+    table_name: &str,
+    pk_column: &str,
+    pk_value: &Value,
+) -> Result<Option<HashMap<String, Value>>, String> {
+    let codecs = table_codecs(conn, table_name)?;
+    let sql = format!(
+        "SELECT * FROM {} WHERE {} = ?",
+        quote_identifier(table_name),
+        quote_identifier(pk_column)
+    );
+    let mut stmt = conn.prepare(&sql)
+        .map_err(|e| format!("Error al preparar la consulta del registro: {}", e))?;
+
+    stmt.query_row([find_codec(&codecs, pk_column)?.encode(pk_value)?], |row| {
+        let mut map = HashMap::new();
+        for (i, codec) in codecs.iter().enumerate() {
+            let value = match row.get_ref(i)? {
+                ValueRef::Blob(_) => Value::String(image_url(db_name, table_name, pk_value, &codec.name)),
+                other if is_image_ref(other) => Value::String(image_url(db_name, table_name, pk_value, &codec.name)),
+                other => codec.decode(other),
+            };
+            map.insert(codec.name.clone(), value);
+        }
+        Ok(map)
+    })
+    .optional()
+    .map_err(|e| format!("Error al leer el registro: {}", e))
+}
+
+/// Comprueba que el registro no cambió desde que el frontend lo leyó.
+/// Debe llamarse dentro de la transacción que hace la modificación.
+/// Las imágenes (URLs del protocolo) no se comparan.
+pub(crate) fn check_unchanged(
+    conn: &Connection,
+    db_name: &str,
+    table_name: &str,
+    pk_column: &str,
+    pk_value: &Value,
+    original_values: &HashMap<String, Value>,
+) -> Result<(), String> {
+    let current = current_row(conn, db_name, table_name, pk_column, pk_value)?;
+
+    let changed_columns: Vec<String> = match &current {
+        Some(row) => original_values
+            .iter()
+            .filter(|(_, original)| !original.as_str().map_or(false, is_image_url))
+            .filter(|(column, original)| {
+                row.get(*column).map(comparable) != Some(comparable(original))
+            })
+            .map(|(column, _)| column.clone())
+            .collect(),
+        None => original_values.keys().cloned().collect(),
+    };
+
+    if changed_columns.is_empty() {
+        return Ok(());
+    }
+
+    let message = if current.is_some() {
+        format!(
+            "Otro usuario modificó este registro mientras lo editaba ({}). Revise los valores actuales.",
+            changed_columns.join(", ")
+        )
+    } else {
+        "Otro usuario eliminó este registro mientras lo editaba.".to_string()
+    };
+
+    Err(UpdateConflict {
+        message,
+        table_name: table_name.to_string(),
+        pk_column: pk_column.to_string(),
+        pk_value: pk_value.clone(),
+        changed_columns,
+        current_values: current,
+    }
+    .into_error())
+}
+
+/// Asignaciones SQL adicionales para marcar la modificación:
+/// `version = version + 1` y `updated_at = <ahora>` si la tabla tiene esas columnas
+/// y no se están asignando explícitamente.
+pub(crate) fn version_assignments(table_columns: &[String], assigned: &[String]) -> Vec<String> {
+    let mut parts = Vec::new();
+    let has = |name: &str| table_columns.iter().any(|c| c == name) && !assigned.iter().any(|c| c == name);
+
+    if has(VERSION_COLUMN) {
+        parts.push(format!(
+            "{} = COALESCE({}, 0) + 1",
+            quote_identifier(VERSION_COLUMN),
+            quote_identifier(VERSION_COLUMN)
+        ));
+    }
+    if has(UPDATED_AT_COLUMN) {
+        parts.push(format!(
+            "{} = '{}'",
+            quote_identifier(UPDATED_AT_COLUMN),
+            Local::now().to_rfc3339()
+        ));
+    }
+    parts
+}
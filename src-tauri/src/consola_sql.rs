@@ -0,0 +1,248 @@
+/// =========================================================================
+/// Módulo: Consola SQL de solo lectura
+///
+/// Permite a usuarios avanzados ejecutar consultas y ver sus resultados sin
+/// riesgo para el archivo de la base de datos:
+/// - La conexión se abre en modo SOLO LECTURA
+/// - Solo se aceptan sentencias SELECT, WITH y EXPLAIN (una por ejecución)
+/// - Se limita el número de filas y el tiempo de ejecución (progress handler)
+/// - Se devuelven columnas con su tipo, filas (los BLOB se resumen) y el
+///   plan de ejecución de la consulta
+/// =========================================================================
+
+use std::time::{Duration, Instant};
+
+use rusqlite::types::ValueRef;
+use rusqlite::{Connection, OpenFlags};
+use serde::Serialize;
+use serde_json::Value;
+use tauri::State;
+
+use crate::database_manager::AppState;
+use crate::codec_valores::{decode_value, json_to_sql, LogicalType};
+use crate::protocolo_imagenes::sniff_image_mime;
+
+/// Filas devueltas si el frontend no indica un límite.
+const DEFAULT_MAX_ROWS: usize = 1000;
+/// Límite máximo de filas que se puede solicitar.
+const HARD_MAX_ROWS: usize = 10_000;
+/// Tiempo máximo de ejecución por defecto, en milisegundos.
+const DEFAULT_TIMEOUT_MS: u64 = 5_000;
+/// Cada cuántas instrucciones de la VM de SQLite se revisa el tiempo.
+const PROGRESS_STEPS: i32 = 1_000;
+
+/// Columna del resultado
+#[derive(Debug, Serialize)]
+pub struct QueryColumn {
+    /// Nombre de la columna (o alias)
+    pub name: String,
+    /// Tipo declarado en la tabla, si la columna proviene directamente de una
+    pub declared_type: Option<String>,
+    /// Tipo detectado en los valores devueltos ("integer", "real", "text", "blob", "null" o "mixed")
+    pub value_type: String,
+}
+
+/// Paso del plan de ejecución (EXPLAIN QUERY PLAN)
+#[derive(Debug, Serialize)]
+pub struct QueryPlanStep {
+    pub id: i64,
+    pub parent: i64,
+    pub detail: String,
+}
+
+/// Resultado de `run_query`
+#[derive(Debug, Serialize)]
+pub struct QueryResult {
+    pub columns: Vec<QueryColumn>,
+    /// Filas como listas de valores en el orden de `columns`
+    pub rows: Vec<Vec<Value>>,
+    /// Indica si se alcanzó el límite de filas y hay más resultados
+    pub truncated: bool,
+    /// Plan de ejecución (vacío para sentencias EXPLAIN)
+    pub plan: Vec<QueryPlanStep>,
+    /// Duración de la ejecución en milisegundos
+    pub elapsed_ms: u128,
+}
+
+/// Quita comentarios y espacios iniciales para leer la primera palabra clave
+fn first_keyword(sql: &str) -> String {
+    let mut rest = sql.trim_start();
+    loop {
+        if let Some(after) = rest.strip_prefix("--") {
+            rest = after.split_once('\n').map(|(_, r)| r).unwrap_or("").trim_start();
+        } else if let Some(after) = rest.strip_prefix("/*") {
+            rest = after.split_once("*/").map(|(_, r)| r).unwrap_or("").trim_start();
+        } else {
+            break;
+        }
+    }
+    rest.chars()
+        .take_while(|c| c.is_ascii_alphabetic())
+        .collect::<String>()
+        .to_uppercase()
+}
+
+/// Resume un valor para mostrarlo en la consola
+fn value_to_json(value: ValueRef, logical: LogicalType) -> (Value, &'static str) {
+    let kind = match value {
+        ValueRef::Null => "null",
+        ValueRef::Integer(_) => "integer",
+        ValueRef::Real(_) => "real",
+        ValueRef::Text(_) => "text",
+        ValueRef::Blob(b) => {
+            return (
+                serde_json::json!({
+                    "blob": true,
+                    "size": b.len(),
+                    "mime": sniff_image_mime(b),
+                }),
+                "blob",
+            );
+        }
+    };
+    (decode_value(value, logical), kind)
+}
+
+/// Ejecuta una consulta de solo lectura y devuelve sus resultados.
+///
+/// # Argumentos
+/// - `sql`: una única sentencia SELECT, WITH o EXPLAIN
+/// - `params`: valores para los `?` de la consulta
+/// - `max_rows`: límite de filas (por defecto 1000, máximo 10000)
+/// - `timeout_ms`: tiempo máximo de ejecución (por defecto 5 segundos)
+#[tauri::command]
+pub fn run_query(
+    state: State<AppState>,
+    db_name: String,
+    sql: String,
+    params: Option<Vec<Value>>,
+    max_rows: Option<usize>,
+    timeout_ms: Option<u64>,
+) -> Result<QueryResult, String> {
+    let keyword = first_keyword(&sql);
+    if !matches!(keyword.as_str(), "SELECT" | "WITH" | "EXPLAIN") {
+        return Err("Solo se permiten consultas SELECT, WITH o EXPLAIN en la consola.".to_string());
+    }
+
+    let db_path = state.db_dir.join(format!("{}.db", db_name));
+    let sqlite_path = state.db_dir.join(format!("{}.sqlite", db_name));
+
+    let db_file = if db_path.exists() {
+        db_path
+    } else if sqlite_path.exists() {
+        sqlite_path
+    } else {
+        return Err(format!("Base de datos no encontrada: {}", db_name));
+    };
+
+    // Conexión de solo lectura: aunque algo se escape de la validación, SQLite no escribirá
+    let conn = Connection::open_with_flags(&db_file, OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX)
+        .map_err(|e| format!("Error al abrir la base de datos: {}", e))?;
+
+    // Límite de tiempo: el progress handler interrumpe la consulta al vencer el plazo
+    let timeout = Duration::from_millis(timeout_ms.unwrap_or(DEFAULT_TIMEOUT_MS));
+    let started = Instant::now();
+    conn.progress_handler(PROGRESS_STEPS, Some(move || started.elapsed() > timeout));
+
+    let query_params: Vec<rusqlite::types::Value> = params
+        .unwrap_or_default()
+        .iter()
+        .map(json_to_sql)
+        .collect::<Result<_, _>>()?;
+
+    // prepare() rechaza varias sentencias y readonly() confirma que no modifica datos
+    let mut stmt = conn.prepare(&sql)
+        .map_err(|e| format!("Error en la consulta: {}", e))?;
+    if !stmt.readonly() {
+        return Err("La consulta intenta modificar la base de datos y no está permitida.".to_string());
+    }
+
+    let declared_types: Vec<Option<String>> = stmt.columns()
+        .iter()
+        .map(|c| c.decl_type().map(|t| t.to_string()))
+        .collect();
+    let logical_types: Vec<LogicalType> = declared_types
+        .iter()
+        .map(|t| LogicalType::from_declared(t.as_deref().unwrap_or("")))
+        .collect();
+    let names: Vec<String> = stmt.column_names().into_iter().map(|s| s.to_string()).collect();
+    let mut value_types: Vec<Option<&'static str>> = vec![None; names.len()];
+
+    let limit = max_rows.unwrap_or(DEFAULT_MAX_ROWS).clamp(1, HARD_MAX_ROWS);
+    let mut rows_out: Vec<Vec<Value>> = Vec::new();
+    let mut truncated = false;
+
+    let mut rows = stmt.query(rusqlite::params_from_iter(query_params.iter()))
+        .map_err(|e| format!("Error al ejecutar la consulta: {}", e))?;
+
+    loop {
+        let row = match rows.next() {
+            Ok(Some(row)) => row,
+            Ok(None) => break,
+            Err(rusqlite::Error::SqliteFailure(err, _)) if err.code == rusqlite::ErrorCode::OperationInterrupted => {
+                return Err(format!("La consulta superó el tiempo máximo de {} ms.", timeout.as_millis()));
+            }
+            Err(e) => return Err(format!("Error al leer resultados: {}", e)),
+        };
+
+        if rows_out.len() >= limit {
+            truncated = true;
+            break;
+        }
+
+        let mut values = Vec::with_capacity(names.len());
+        for i in 0..names.len() {
+            let (value, kind) = value_to_json(row.get_ref(i).map_err(|e| e.to_string())?, logical_types[i]);
+            // El tipo se fija con el primer valor no nulo; si cambia, la columna es "mixed"
+            value_types[i] = match (value_types[i], kind) {
+                (_, "null") => value_types[i],
+                (None, k) => Some(k),
+                (Some(prev), k) if prev == k => Some(prev),
+                _ => Some("mixed"),
+            };
+            values.push(value);
+        }
+        rows_out.push(values);
+    }
+    drop(rows);
+    drop(stmt);
+
+    let elapsed_ms = started.elapsed().as_millis();
+    conn.progress_handler(0, None::<fn() -> bool>);
+
+    // Plan de ejecución de la consulta (no aplica a sentencias EXPLAIN)
+    let mut plan = Vec::new();
+    if keyword != "EXPLAIN" {
+        let mut plan_stmt = conn.prepare(&format!("EXPLAIN QUERY PLAN {}", sql))
+            .map_err(|e| format!("Error al obtener el plan de ejecución: {}", e))?;
+        plan = plan_stmt.query_map(rusqlite::params_from_iter(query_params.iter()), |row| {
+            Ok(QueryPlanStep {
+                id: row.get(0)?,
+                parent: row.get(1)?,
+                detail: row.get(3)?,
+            })
+        })
+        .map_err(|e| format!("Error al obtener el plan de ejecución: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Error al obtener el plan de ejecución: {}", e))?;
+    }
+
+    let columns = names
+        .into_iter()
+        .zip(declared_types)
+        .zip(value_types)
+        .map(|((name, declared_type), value_type)| QueryColumn {
+            name,
+            declared_type,
+            value_type: value_type.unwrap_or("null").to_string(),
+        })
+        .collect();
+
+    Ok(QueryResult {
+        columns,
+        rows: rows_out,
+        truncated,
+        plan,
+        elapsed_ms,
+    })
+}
@@ -4,9 +4,15 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
 use tauri::State;
-use base64::{Engine as _, engine::general_purpose};
 
+use crate::codec_valores::{decode_value, LogicalType};
 use crate::database_manager::AppState;
+use crate::filtros::{FilterNode, SortSpec};
+use crate::busqueda_tabla::primary_key_column;
+use crate::protocolo_imagenes::image_url;
+use crate::almacen_imagenes::is_image_ref;
+use crate::papelera::{DELETED_AT_COLUMN, DELETED_BY_COLUMN};
+use crate::vistas_guardadas::view_sql;
 
 /// Helper function to properly quote SQL identifiers (table names, column names)
 /// Handles identifiers with spaces or special characters by wrapping in double quotes
@@ -42,11 +48,25 @@ pub fn consulta_tabla(state: State<AppState>, db_name: String, table_name: Strin
     // Abrir la conexión a la base de datos
     let conn = rusqlite::Connection::open(&db_file).map_err(|e| format!("Error al abrir la base de datos: {}", e))?;
 
+    query_table_data(&conn, &db_name, table_name, None, &[], None)
+}
+
+/// Consulta los datos de una tabla aplicando, opcionalmente, un filtro, un orden
+/// y una selección de columnas. Sin orden explícito se ordena por "No." si existe.
+/// Es la base de `consulta_tabla` y de las vistas guardadas.
+pub(crate) fn query_table_data(
+    conn: &rusqlite::Connection,
+    db_name: &str,
+    table_name: String,
+    filter: Option<&FilterNode>,
+    sort: &[SortSpec],
+    visible_columns: Option<&[String]>,
+) -> Result<TableData, String> {
     // Obtener las columnas de la tabla con sus tipos
     let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", quote_identifier(&table_name)))
         .map_err(|e| format!("Error al preparar la consulta de columnas: {}", e))?;
 
-    let columns_info: Vec<(String, String)> = stmt.query_map([], |row| {
+    let all_columns_info: Vec<(String, String)> = stmt.query_map([], |row| {
         let name: String = row.get(1)?;
         let type_: String = row.get(2)?;
         Ok((name, type_))
@@ -54,42 +74,100 @@ pub fn consulta_tabla(state: State<AppState>, db_name: String, table_name: Strin
       .collect::<Result<Vec<(String, String)>, _>>()
       .map_err(|e| format!("Error al obtener columnas: {}", e))?;
 
+    // Filtro, orden y registros activos con la misma traducción que las vistas guardadas
+    let view = view_sql(conn, &table_name, filter, sort, visible_columns.unwrap_or(&[]))?;
+
+    // Con borrado lógico se ocultan los registros de la papelera y sus columnas de control
+    let hidden_columns: &[&str] = if view.soft_delete {
+        &[DELETED_AT_COLUMN, DELETED_BY_COLUMN]
+    } else {
+        &[]
+    };
+
+    // Columnas a devolver: la selección de la vista (en su orden) o todas
+    let columns_info: Vec<(String, String)> = match visible_columns {
+        Some(selected) if !selected.is_empty() => selected
+            .iter()
+            .map(|col| {
+                all_columns_info
+                    .iter()
+                    .find(|(name, _)| name == col)
+                    .cloned()
+                    .ok_or_else(|| format!("La columna '{}' no existe en la tabla", col))
+            })
+            .collect::<Result<Vec<_>, String>>()?,
+        _ => all_columns_info
+            .iter()
+            .filter(|(name, _)| !hidden_columns.contains(&name.as_str()))
+            .cloned()
+            .collect(),
+    };
     let columns: Vec<String> = columns_info.iter().map(|(name, _)| name.clone()).collect();
 
     // Consultar los datos de la tabla ordenados por "No." ascendente si existe la columna
-    let has_no_column = columns.iter().any(|col| col == "No.");
-    let order_clause = if has_no_column {
+    let order_clause = if !sort.is_empty() {
+        view.order_sql.clone()
+    } else if view.table_columns.iter().any(|col| col == "No.") {
         format!(" ORDER BY CAST({} AS INTEGER) ASC", quote_identifier("No."))
     } else {
         String::new()
     };
-    let query = format!("SELECT * FROM {}{}", quote_identifier(&table_name), order_clause);
+    // Las columnas BLOB no se leen: se marca si hay imagen (X'' sin leer los bytes)
+    // y se devuelve una URL del protocolo unea-img. Para armarla se necesita la clave primaria.
+    let mut select_list: Vec<String> = columns_info
+        .iter()
+        .map(|(name, type_)| {
+            if type_ == "BLOB" {
+                let col = quote_identifier(name);
+                format!("CASE WHEN typeof({}) = 'blob' THEN X'' ELSE {} END", col, col)
+            } else {
+                quote_identifier(name)
+            }
+        })
+        .collect();
+    let has_blob_columns = columns_info.iter().any(|(_, type_)| type_ == "BLOB");
+    if has_blob_columns {
+        select_list.push(quote_identifier(&primary_key_column(conn, &table_name)?));
+    }
+    let query = format!(
+        "SELECT {} FROM {}{}{}",
+        select_list.join(", "),
+        quote_identifier(&table_name),
+        view.where_sql,
+        order_clause
+    );
     let mut stmt = conn.prepare(&query)
         .map_err(|e| format!("Error al preparar la consulta de datos: {}", e))?;
+    let logical_types: Vec<LogicalType> = columns_info
+        .iter()
+        .map(|(_, type_)| LogicalType::from_declared(type_))
+        .collect();
 
-    let rows: Vec<HashMap<String, Value>> = stmt.query_map([], |row| {
+    let rows: Vec<HashMap<String, Value>> = stmt.query_map(rusqlite::params_from_iter(view.params.iter()), |row| {
         let mut map = HashMap::new();
+        let pk_value: Value = if has_blob_columns {
+            decode_value(row.get_ref(columns.len())?, LogicalType::Any)
+        } else {
+            Value::Null
+        };
         for (i, col_name) in columns.iter().enumerate() {
             let col_type = &columns_info[i].1;
-            let value: rusqlite::Result<Value> = match row.get_ref(i) {
-                Ok(rusqlite::types::ValueRef::Null) => Ok(Value::Null),
-                Ok(rusqlite::types::ValueRef::Integer(i)) => Ok(Value::Number(i.into())),
-                Ok(rusqlite::types::ValueRef::Real(f)) => Ok(Value::Number(serde_json::Number::from_f64(f).unwrap())),
-                Ok(rusqlite::types::ValueRef::Text(s)) => Ok(Value::String(String::from_utf8_lossy(s).to_string())),
-                Ok(rusqlite::types::ValueRef::Blob(b)) => {
+            let value = match row.get_ref(i)? {
+                rusqlite::types::ValueRef::Blob(b) => {
                     if col_type == "BLOB" {
-                        // Return base64 encoded data for BLOB columns (images)
-                        let base64_data = general_purpose::STANDARD.encode(b);
-                        Ok(Value::String(format!("data:image/png;base64,{}", base64_data)))
+                        // Return a lightweight unea-img:// URL for BLOB columns (images)
+                        Value::String(image_url(db_name, &table_name, &pk_value, col_name))
                     } else {
-                        Ok(Value::String(format!("BLOB({} bytes)", b.len())))
+                        Value::String(format!("BLOB({} bytes)", b.len()))
                     }
                 },
-                Err(e) => Err(e),
+                // Imagen guardada en el almacén por contenido
+                other if col_type == "BLOB" && is_image_ref(other) => {
+                    Value::String(image_url(db_name, &table_name, &pk_value, col_name))
+                },
+                other => decode_value(other, logical_types[i]),
             };
-            if let Ok(val) = value {
-                map.insert(col_name.clone(), val);
-            }
+            map.insert(col_name.clone(), value);
         }
         Ok(map)
     }).map_err(|e| format!("Error al ejecutar la consulta de datos: {}", e))?
@@ -26,7 +26,7 @@ pub fn add_date_column(db_path: &str, table_name: &str, column_name: &str) -> Re
 
     let sql = format!("ALTER TABLE {} ADD COLUMN {} DATETIME", quote_identifier(table_name), quote_identifier(column_name));
     match conn.execute(&sql, []) {
-        Ok(_) => Ok(()),
+        Ok(_) => crate::auditoria::log_operation(&conn, "add_column", Some(table_name), Some(column_name), Some("DATETIME")),
         Err(e) => {
             let error_msg = e.to_string();
             if error_msg.contains("duplicate column name") {
@@ -19,9 +19,9 @@ use tauri::State;                     // Acceso al estado global de la app
 use rusqlite::Result;                  // Manejo de resultados de SQLite
 use serde::{Deserialize, Serialize};   // Serialización / deserialización JSON
 use std::fs;                           // Operaciones con archivos
-use std::path::PathBuf;                // Manejo seguro de rutas
+use std::path::{Path, PathBuf};        // Manejo seguro de rutas
 use std::process::Command;             // Ejecución de comandos del sistema (CORREGIDO)
-use chrono::{TimeDelta, Utc};          // Manejo de fechas y tiempos
+use chrono::{Local, TimeDelta, Utc};   // Manejo de fechas y tiempos
 
 /* =========================================================================
    Estructuras de datos
@@ -77,6 +77,25 @@ fn format_file_size(bytes: u64) -> String {
     format!("{:.prec$} {}", size, UNITS[unit_index], prec = precision)
 }
 
+/// Crea una copia consistente de la base de datos en `<db_dir>/snapshots/` usando
+/// `VACUUM INTO`, antes de operaciones que no se pueden deshacer.
+/// Devuelve la ruta del archivo creado.
+pub(crate) fn create_snapshot(db_dir: &Path, db_file: &Path, db_name: &str, reason: &str) -> Result<PathBuf, String> {
+    let snapshots_dir = db_dir.join("snapshots");
+    fs::create_dir_all(&snapshots_dir)
+        .map_err(|e| format!("No se pudo crear el directorio de respaldos: {}", e))?;
+
+    let file_name = format!("{}_{}_{}.db", db_name, Local::now().format("%Y%m%d_%H%M%S"), reason);
+    let snapshot_path = snapshots_dir.join(file_name);
+
+    let conn = rusqlite::Connection::open(db_file)
+        .map_err(|e| format!("Error al abrir la base de datos: {}", e))?;
+    conn.execute("VACUUM INTO ?", [snapshot_path.to_string_lossy().to_string()])
+        .map_err(|e| format!("Error al crear el respaldo: {}", e))?;
+
+    Ok(snapshot_path)
+}
+
 /* =========================================================================
    Funciones expuestas como comandos Tauri
    ========================================================================= */
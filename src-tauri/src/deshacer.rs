@@ -0,0 +1,327 @@
+/// =========================================================================
+/// Módulo: Deshacer / rehacer ediciones de registros
+///
+/// Cada operación de escritura (modificar, eliminar, crear, operaciones
+/// masivas y lotes de cambios) registra en un diario en memoria la imagen
+/// de cada fila ANTES y DESPUÉS del cambio. El diario dura lo que dura la
+/// sesión de la aplicación y se lleva por base de datos.
+///
+/// - `undo_last` vuelve a escribir las imágenes "antes"
+/// - `redo` vuelve a escribir las imágenes "después"
+/// - Ambos se ejecutan en una transacción y se niegan si alguna fila fue
+///   modificada por otra estación desde entonces
+/// =========================================================================
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use rusqlite::types::Value as SqlValue;
+use rusqlite::{Connection, OptionalExtension, TransactionBehavior};
+use serde::Serialize;
+use tauri::State;
+
+use crate::auditoria::log_row_changes;
+use crate::busqueda_tabla::primary_key_column;
+use crate::database_manager::AppState;
+
+/// Operaciones que se conservan por base de datos.
+const MAX_ENTRIES: usize = 100;
+
+fn quote_identifier(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace("\"", "\"\""))
+}
+
+/// Fila: columna → valor. Normalmente completa (incluye los BLOB); una imagen
+/// parcial solo compara y escribe sus columnas.
+pub(crate) type RowImage = HashMap<String, SqlValue>;
+
+/// Cambio de una fila: `before` = None es un alta, `after` = None es una baja
+#[derive(Debug, Clone)]
+pub(crate) struct RowChange {
+    pub table_name: String,
+    pub pk_column: String,
+    pub before: Option<RowImage>,
+    pub after: Option<RowImage>,
+}
+
+/// Operación del usuario (puede abarcar muchas filas)
+#[derive(Debug, Clone)]
+struct JournalEntry {
+    description: String,
+    changes: Vec<RowChange>,
+}
+
+#[derive(Default)]
+struct Journal {
+    undo: Vec<JournalEntry>,
+    redo: Vec<JournalEntry>,
+}
+
+/// Diario de deshacer/rehacer de la sesión, por base de datos
+#[derive(Default)]
+pub struct UndoState {
+    journals: Mutex<HashMap<String, Journal>>,
+}
+
+impl UndoState {
+    /// Registra una operación ya confirmada. Borra lo que hubiera para rehacer.
+    pub(crate) fn record(&self, db_name: &str, description: impl Into<String>, changes: Vec<RowChange>) {
+        if changes.is_empty() {
+            return;
+        }
+        let mut journals = match self.journals.lock() {
+            Ok(journals) => journals,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let journal = journals.entry(db_name.to_string()).or_default();
+        journal.undo.push(JournalEntry { description: description.into(), changes });
+        if journal.undo.len() > MAX_ENTRIES {
+            journal.undo.remove(0);
+        }
+        journal.redo.clear();
+    }
+
+    /// Claves de las filas de `table_name` que todavía se pueden tocar al deshacer o rehacer
+    pub(crate) fn journal_row_keys(&self, db_name: &str, table_name: &str) -> Vec<SqlValue> {
+        let journals = match self.journals.lock() {
+            Ok(journals) => journals,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let Some(journal) = journals.get(db_name) else {
+            return Vec::new();
+        };
+        journal.undo.iter()
+            .chain(journal.redo.iter())
+            .flat_map(|entry| entry.changes.iter())
+            .filter(|change| change.table_name == table_name)
+            .filter_map(|change| {
+                change.before.as_ref()
+                    .or(change.after.as_ref())
+                    .and_then(|image| image.get(&change.pk_column))
+                    .cloned()
+            })
+            .collect()
+    }
+}
+
+/// Lee la imagen completa de una fila por su clave primaria.
+/// En tablas sin clave primaria se incluye `rowid` para poder identificarla.
+pub(crate) fn read_row(
+    conn: &Connection,
+    table_name: &str,
+    pk_column: &str,
+    pk_value: &SqlValue,
+) -> Result<Option<RowImage>, String> {
+    let select_list = if pk_column == "rowid" { "rowid, *" } else { "*" };
+    let sql = format!(
+        "SELECT {} FROM {} WHERE {} = ?",
+        select_list,
+        quote_identifier(table_name),
+        quote_identifier(pk_column)
+    );
+    let mut stmt = conn.prepare(&sql)
+        .map_err(|e| format!("Error al preparar la lectura del registro: {}", e))?;
+    let names: Vec<String> = stmt.column_names().into_iter().map(|s| s.to_string()).collect();
+
+    stmt.query_row([pk_value], |row| {
+        let mut image = RowImage::new();
+        for (i, name) in names.iter().enumerate() {
+            image.insert(name.clone(), row.get::<_, SqlValue>(i)?);
+        }
+        Ok(image)
+    })
+    .optional()
+    .map_err(|e| format!("Error al leer el registro: {}", e))
+}
+
+/// Cambio de una fila recién insertada, a partir de su rowid
+pub(crate) fn inserted_row(conn: &Connection, table_name: &str, rowid: i64) -> Result<RowChange, String> {
+    let pk_column = primary_key_column(conn, table_name)?;
+    let after = read_row(conn, table_name, "rowid", &SqlValue::Integer(rowid))?;
+    // Si la clave primaria no es el rowid, la imagen no necesita la columna extra
+    let after = after.map(|mut image| {
+        if pk_column != "rowid" {
+            image.remove("rowid");
+        }
+        image
+    });
+    Ok(RowChange {
+        table_name: table_name.to_string(),
+        pk_column,
+        before: None,
+        after,
+    })
+}
+
+/// Clave primaria de una imagen
+fn image_pk<'a>(image: &'a RowImage, pk_column: &str) -> Result<&'a SqlValue, String> {
+    image.get(pk_column)
+        .ok_or_else(|| format!("La imagen del registro no contiene la clave '{}'", pk_column))
+}
+
+/// Escribe `target` donde hoy está `expected` (deshacer: after → before; rehacer: before → after).
+/// Falla si la fila actual no coincide con `expected`.
+fn replay_change(
+    conn: &Connection,
+    change: &RowChange,
+    expected: &Option<RowImage>,
+    target: &Option<RowImage>,
+) -> Result<(), String> {
+    let table = quote_identifier(&change.table_name);
+    let pk = quote_identifier(&change.pk_column);
+    let conflict = || format!(
+        "Un registro de '{}' fue modificado por otra estación; no se puede deshacer/rehacer",
+        change.table_name
+    );
+
+    // Verificar que la fila está como la dejamos
+    match expected {
+        Some(image) => {
+            let current = read_row(conn, &change.table_name, &change.pk_column, image_pk(image, &change.pk_column)?)?;
+            let unchanged = current.map_or(false, |row| {
+                image.iter().all(|(column, value)| row.get(column) == Some(value))
+            });
+            if !unchanged {
+                return Err(conflict());
+            }
+        }
+        None => {
+            if let Some(image) = target {
+                let current = read_row(conn, &change.table_name, &change.pk_column, image_pk(image, &change.pk_column)?)?;
+                if current.is_some() {
+                    return Err(conflict());
+                }
+            }
+        }
+    }
+
+    match (expected, target) {
+        (Some(current), None) => {
+            conn.execute(
+                &format!("DELETE FROM {} WHERE {} = ?", table, pk),
+                [image_pk(current, &change.pk_column)?],
+            ).map_err(|e| format!("Error al eliminar el registro: {}", e))?;
+        }
+        (None, Some(image)) => {
+            let columns: Vec<&String> = image.keys().collect();
+            let sql = format!(
+                "INSERT INTO {} ({}) VALUES ({})",
+                table,
+                columns.iter().map(|c| quote_identifier(c)).collect::<Vec<_>>().join(", "),
+                vec!["?"; columns.len()].join(", ")
+            );
+            conn.execute(&sql, rusqlite::params_from_iter(columns.iter().map(|c| &image[*c])))
+                .map_err(|e| format!("Error al restaurar el registro: {}", e))?;
+        }
+        (Some(current), Some(image)) => {
+            let columns: Vec<&String> = image.keys().collect();
+            let sql = format!(
+                "UPDATE {} SET {} WHERE {} = ?",
+                table,
+                columns.iter().map(|c| format!("{} = ?", quote_identifier(c))).collect::<Vec<_>>().join(", "),
+                pk
+            );
+            let mut params: Vec<&SqlValue> = columns.iter().map(|c| &image[*c]).collect();
+            params.push(image_pk(current, &change.pk_column)?);
+            conn.execute(&sql, rusqlite::params_from_iter(params))
+                .map_err(|e| format!("Error al restaurar el registro: {}", e))?;
+        }
+        (None, None) => {}
+    }
+    Ok(())
+}
+
+/// Resultado de `undo_last` y `redo`
+#[derive(Debug, Serialize)]
+pub struct UndoResult {
+    /// Descripción de la operación deshecha o rehecha
+    pub description: String,
+    /// Filas restauradas
+    pub rows: usize,
+    /// Operaciones que quedan por deshacer
+    pub undo_available: usize,
+    /// Operaciones que quedan por rehacer
+    pub redo_available: usize,
+}
+
+/// Deshace (`undo = true`) o rehace la última operación del diario
+fn replay(state: &AppState, undo_state: &UndoState, db_name: &str, undo: bool) -> Result<UndoResult, String> {
+    let db_path = state.db_dir.join(format!("{}.db", db_name));
+    let sqlite_path = state.db_dir.join(format!("{}.sqlite", db_name));
+
+    let db_file = if db_path.exists() {
+        db_path
+    } else if sqlite_path.exists() {
+        sqlite_path
+    } else {
+        return Err(format!("Base de datos no encontrada: {}", db_name));
+    };
+
+    let mut journals = undo_state.journals.lock()
+        .map_err(|_| "El diario de cambios no está disponible".to_string())?;
+    let journal = journals.entry(db_name.to_string()).or_default();
+
+    let entry = if undo { journal.undo.last() } else { journal.redo.last() }
+        .cloned()
+        .ok_or_else(|| if undo { "No hay cambios para deshacer." } else { "No hay cambios para rehacer." }.to_string())?;
+
+    let mut conn = Connection::open(&db_file)
+        .map_err(|e| format!("Error al abrir la base de datos: {}", e))?;
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)
+        .map_err(|e| format!("Error al iniciar la transacción: {}", e))?;
+
+    // Cambios efectivamente aplicados, para la bitácora de auditoría
+    let mut applied = Vec::with_capacity(entry.changes.len());
+    if undo {
+        // Al deshacer se recorre en orden inverso
+        for change in entry.changes.iter().rev() {
+            replay_change(&tx, change, &change.after, &change.before)?;
+            applied.push(RowChange { before: change.after.clone(), after: change.before.clone(), ..change.clone() });
+        }
+    } else {
+        for change in entry.changes.iter() {
+            replay_change(&tx, change, &change.before, &change.after)?;
+            applied.push(change.clone());
+        }
+    }
+    log_row_changes(&tx, if undo { "undo_last" } else { "redo" }, &applied)?;
+
+    tx.commit()
+        .map_err(|e| format!("Error al guardar los cambios: {}", e))?;
+
+    // Solo al confirmar se mueve la operación a la otra pila
+    if undo {
+        journal.undo.pop();
+        journal.redo.push(entry.clone());
+    } else {
+        journal.redo.pop();
+        journal.undo.push(entry.clone());
+    }
+
+    Ok(UndoResult {
+        description: entry.description,
+        rows: entry.changes.len(),
+        undo_available: journal.undo.len(),
+        redo_available: journal.redo.len(),
+    })
+}
+
+/// Deshace la última operación de escritura hecha en esta sesión sobre la base de datos
+#[tauri::command]
+pub fn undo_last(
+    state: State<AppState>,
+    undo_state: State<UndoState>,
+    db_name: String,
+) -> Result<UndoResult, String> {
+    replay(&state, &undo_state, &db_name, true)
+}
+
+/// Vuelve a aplicar la última operación deshecha
+#[tauri::command]
+pub fn redo(
+    state: State<AppState>,
+    undo_state: State<UndoState>,
+    db_name: String,
+) -> Result<UndoResult, String> {
+    replay(&state, &undo_state, &db_name, false)
+}
@@ -1,17 +1,29 @@
+use rusqlite::types::ValueRef;
 use rusqlite::Result;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
 use tauri::State;
 use crate::database_manager::AppState;
-use base64::{Engine, engine::general_purpose::STANDARD};
+use crate::protocolo_imagenes::image_url;
+use crate::almacen_imagenes::is_image_ref;
+use crate::busqueda_tabla::primary_key_column;
+use crate::historial_registro::history_change_count;
+use crate::adjuntos::{record_attachments, AttachmentInfo};
+use crate::codec_valores::{json_to_sql, table_codecs};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct RecordDetails {
     /// Nombre de la tabla
     pub table_name: String,
-    /// Datos del registro
+    /// Datos del registro (las imágenes como URL unea-img://, igual que en `consulta_tabla`)
     pub record: HashMap<String, Value>,
+    /// URL unea-img:// de cada columna con imagen, para mostrarla sin usar el base64
+    pub image_urls: HashMap<String, String>,
+    /// Cambios del registro en la bitácora (pestaña "Historial", ver `get_record_history`)
+    pub history_changes: usize,
+    /// Archivos adjuntos del registro (ver `add_attachment`)
+    pub attachments: Vec<AttachmentInfo>,
 }
 
 /// Fetch detailed information for a specific record, including proper image handling
@@ -39,64 +51,72 @@ pub fn get_record_details(
         .map_err(|e| format!("Error opening database: {}", e))?;
 
     // Get column information to know data types
-    let mut pragma_stmt = conn.prepare(&format!("PRAGMA table_info(\"{}\")", table_name))
-        .map_err(|e| format!("Error preparing PRAGMA query: {}", e))?;
-
-    let columns_info: Vec<(String, String)> = pragma_stmt.query_map([], |row| {
-        let name: String = row.get(1)?;
-        let type_: String = row.get(2)?;
-        Ok((name, type_))
-    })
-    .map_err(|e| format!("Error querying table info: {}", e))?
-    .collect::<Result<Vec<_>, _>>()
-    .map_err(|e| format!("Error collecting column info: {}", e))?;
-
-    let column_names: Vec<String> = columns_info.iter().map(|(name, _)| name.clone()).collect();
+    let codecs = table_codecs(&conn, &table_name)?;
 
     // Query the specific record
     let query = format!("SELECT * FROM \"{}\" WHERE \"{}\" = ?", table_name, id_column);
     let mut stmt = conn.prepare(&query)
         .map_err(|e| format!("Error preparing record query: {}", e))?;
 
-    let param: Box<dyn rusqlite::ToSql> = match record_id {
-        serde_json::Value::String(s) => Box::new(s),
-        serde_json::Value::Number(n) => {
-            if let Some(i) = n.as_i64() {
-                Box::new(i)
-            } else if let Some(f) = n.as_f64() {
-                Box::new(f)
-            } else {
-                return Err("Unsupported number type".to_string());
-            }
-        }
-        _ => return Err("Unsupported record_id type".to_string()),
+    // "rowid" no es una columna declarada: el valor se usa sin convertir
+    let param = match codecs.iter().find(|c| c.name == id_column) {
+        Some(codec) => codec.encode(&record_id)?,
+        None => json_to_sql(&record_id)?,
     };
 
-    let record = stmt.query_row([&*param], |row| {
+    // Las imágenes (BLOB o referencia al almacén) no se leen: se sustituyen
+    // por su URL una vez conocida la clave primaria
+    let mut image_columns: Vec<String> = Vec::new();
+    let mut record = stmt.query_row([&param], |row| {
         let mut map = HashMap::new();
-        for (i, col_name) in column_names.iter().enumerate() {
-            let value: rusqlite::Result<Value> = match row.get_ref(i) {
-                Ok(rusqlite::types::ValueRef::Null) => Ok(Value::Null),
-                Ok(rusqlite::types::ValueRef::Integer(i)) => Ok(Value::Number(i.into())),
-                Ok(rusqlite::types::ValueRef::Real(f)) => Ok(Value::Number(serde_json::Number::from_f64(f).unwrap())),
-                Ok(rusqlite::types::ValueRef::Text(s)) => Ok(Value::String(String::from_utf8_lossy(s).to_string())),
-                Ok(rusqlite::types::ValueRef::Blob(b)) => {
-                    // For BLOB data (images), encode as base64
-                    let base64_data = STANDARD.encode(b);
-                    Ok(Value::String(base64_data))
-                },
-                Err(e) => Err(e),
+        for (i, codec) in codecs.iter().enumerate() {
+            let value = match row.get_ref(i)? {
+                ValueRef::Blob(_) => {
+                    image_columns.push(codec.name.clone());
+                    Value::Null
+                }
+                other if is_image_ref(other) => {
+                    image_columns.push(codec.name.clone());
+                    Value::Null
+                }
+                other => codec.decode(other),
             };
-            if let Ok(val) = value {
-                map.insert(col_name.clone(), val);
-            }
+            map.insert(codec.name.clone(), value);
         }
         Ok(map)
     })
     .map_err(|e| format!("Error fetching record: {}", e))?;
 
+    // URLs del protocolo de imágenes para las columnas que contienen BLOB
+    // (el protocolo localiza la fila por la clave primaria real de la tabla)
+    let pk_column = primary_key_column(&conn, &table_name)?;
+    let pk_value = match record.get(&pk_column) {
+        Some(v) => v.clone(),
+        None => conn
+            .query_row(
+                &format!("SELECT rowid FROM \"{}\" WHERE \"{}\" = ?", table_name, id_column),
+                [&param],
+                |row| row.get::<_, i64>(0),
+            )
+            .map(|rowid| Value::Number(rowid.into()))
+            .unwrap_or(Value::Null),
+    };
+    let image_urls: HashMap<String, String> = image_columns
+        .iter()
+        .map(|column| (column.clone(), image_url(&db_name, &table_name, &pk_value, column)))
+        .collect();
+    for (column, url) in &image_urls {
+        record.insert(column.clone(), Value::String(url.clone()));
+    }
+
+    let history_changes = history_change_count(&conn, &table_name, &pk_value)?;
+    let attachments = record_attachments(&conn, &table_name, &pk_value)?;
+
     Ok(RecordDetails {
         table_name,
         record,
+        image_urls,
+        history_changes,
+        attachments,
     })
 }
\ No newline at end of file
@@ -0,0 +1,204 @@
+/// =========================================================================
+/// Módulo: Duplicar registro
+///
+/// Copia un registro una o varias veces (ej. 30 monitores iguales que solo
+/// cambian en la serie):
+/// - Los identificadores ("No.", la columna `id_column` y la clave primaria
+///   si no es numérica) se asignan con la secuencia de cada columna, igual
+///   que al crear un registro
+/// - `clear_fields` deja vacías las columnas indicadas (ej. "Serie") y
+///   `overrides` les da otro valor
+/// - Las columnas de control (versión, fecha de modificación y papelera)
+///   no se copian
+///
+/// Todas las copias se crean en una sola transacción y se pueden deshacer
+/// como una sola operación.
+/// =========================================================================
+
+use std::collections::HashMap;
+
+use rusqlite::types::Value as SqlValue;
+use rusqlite::{Connection, TransactionBehavior};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::State;
+
+use crate::auditoria::log_row_changes;
+use crate::busqueda_tabla::primary_key_column;
+use crate::codec_valores::{find_codec, json_to_sql, sql_to_json, table_codecs, LogicalType};
+use crate::concurrencia::{UPDATED_AT_COLUMN, VERSION_COLUMN};
+use crate::database_manager::AppState;
+use crate::deshacer::{inserted_row, read_row, UndoState};
+use crate::papelera::{DELETED_AT_COLUMN, DELETED_BY_COLUMN};
+use crate::secuencias::{code_to_json, next_code};
+
+/// Columna numerada automáticamente en todas las tablas que la tienen
+const NUMBER_COLUMN: &str = "No.";
+/// Máximo de copias en una sola operación
+const MAX_COPIES: usize = 500;
+
+fn quote_identifier(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace("\"", "\"\""))
+}
+
+fn default_copies() -> usize {
+    1
+}
+
+/// Petición de duplicado
+#[derive(Debug, Deserialize)]
+pub struct DuplicateRequest {
+    pub table_name: String,
+    /// Clave primaria del registro a copiar
+    pub pk_value: Value,
+    /// Número de copias (por omisión 1)
+    #[serde(default = "default_copies")]
+    pub copies: usize,
+    /// Columnas que se dejan vacías en las copias (ej. "Serie")
+    #[serde(default)]
+    pub clear_fields: Vec<String>,
+    /// Valores que reemplazan a los del registro original
+    #[serde(default)]
+    pub overrides: HashMap<String, Value>,
+    /// Columna de ID adicional que se asigna con su secuencia
+    /// (la misma `id_column` de `crear_registro_con_auto_incremento`)
+    #[serde(default)]
+    pub id_column: Option<String>,
+}
+
+/// Resultado de `duplicate_record`
+#[derive(Debug, Serialize)]
+pub struct DuplicateResult {
+    pub pk_column: String,
+    /// Clave primaria de cada copia, en el orden en que se crearon
+    pub pk_values: Vec<Value>,
+}
+
+/// Copia un registro `copies` veces con IDs nuevos y devuelve sus claves
+#[tauri::command]
+pub fn duplicate_record(
+    state: State<AppState>,
+    undo_state: State<UndoState>,
+    db_name: String,
+    request: DuplicateRequest,
+) -> Result<DuplicateResult, String> {
+    if request.copies == 0 || request.copies > MAX_COPIES {
+        return Err(format!("El número de copias debe estar entre 1 y {}", MAX_COPIES));
+    }
+
+    let db_path = state.db_dir.join(format!("{}.db", db_name));
+    let sqlite_path = state.db_dir.join(format!("{}.sqlite", db_name));
+
+    let db_file = if db_path.exists() {
+        db_path
+    } else if sqlite_path.exists() {
+        sqlite_path
+    } else {
+        return Err(format!("Base de datos no encontrada: {}", db_name));
+    };
+
+    let mut conn = Connection::open(&db_file)
+        .map_err(|e| format!("Error al abrir la base de datos: {}", e))?;
+    // Los IDs se asignan dentro de la transacción de escritura
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)
+        .map_err(|e| format!("Error al iniciar la transacción: {}", e))?;
+
+    let codecs = table_codecs(&tx, &request.table_name)?;
+    let pk_column = primary_key_column(&tx, &request.table_name)?;
+
+    let pk_sql_value = json_to_sql(&request.pk_value)?;
+    let original = read_row(&tx, &request.table_name, &pk_column, &pk_sql_value)?
+        .ok_or_else(|| format!("No se encontró ninguna fila con {} = {}", pk_column, request.pk_value))?;
+
+    // Columnas con ID propio en cada copia. Una clave primaria INTEGER la
+    // asigna SQLite; cualquier otra se numera con su secuencia.
+    let mut id_columns: Vec<String> = Vec::new();
+    if codecs.iter().any(|c| c.name == NUMBER_COLUMN) {
+        id_columns.push(NUMBER_COLUMN.to_string());
+    }
+    if let Some(id_column) = &request.id_column {
+        find_codec(&codecs, id_column)?;
+        if !id_columns.contains(id_column) {
+            id_columns.push(id_column.clone());
+        }
+    }
+    let pk_is_rowid = pk_column == "rowid"
+        || find_codec(&codecs, &pk_column).map_or(false, |c| c.logical == LogicalType::Integer);
+    if !pk_is_rowid && !id_columns.contains(&pk_column) {
+        id_columns.push(pk_column.clone());
+    }
+    for column in request.clear_fields.iter().chain(request.overrides.keys()) {
+        find_codec(&codecs, column)?;
+        if column == &pk_column && pk_is_rowid {
+            return Err(format!("La clave primaria '{}' se asigna automáticamente", column));
+        }
+    }
+    // Un valor indicado por el usuario reemplaza al de la secuencia
+    id_columns.retain(|c| !request.overrides.contains_key(c));
+
+    // Valores que se copian tal cual; los campos a vaciar y reemplazar se convierten con su tipo
+    let skipped = [VERSION_COLUMN, UPDATED_AT_COLUMN, DELETED_AT_COLUMN, DELETED_BY_COLUMN];
+    let mut base: Vec<(String, SqlValue)> = Vec::new();
+    for codec in &codecs {
+        let name = codec.name.as_str();
+        if skipped.contains(&name) || id_columns.iter().any(|c| c == name) || (name == pk_column && pk_is_rowid) {
+            continue;
+        }
+        let value = if let Some(value) = request.overrides.get(name) {
+            codec.encode(value)?
+        } else if request.clear_fields.iter().any(|c| c == name) {
+            codec.encode(&Value::Null)?
+        } else {
+            original.get(name).cloned().unwrap_or(SqlValue::Null)
+        };
+        base.push((codec.name.clone(), value));
+    }
+
+    // Registro como JSON para los formatos de código que usan otros campos
+    let record: HashMap<String, Value> = base.iter().map(|(c, v)| (c.clone(), sql_to_json(v))).collect();
+
+    let mut columns: Vec<String> = base.iter().map(|(c, _)| c.clone()).collect();
+    columns.extend(id_columns.iter().cloned());
+    let sql = if columns.is_empty() {
+        format!("INSERT INTO {} DEFAULT VALUES", quote_identifier(&request.table_name))
+    } else {
+        format!(
+            "INSERT INTO {} ({}) VALUES ({})",
+            quote_identifier(&request.table_name),
+            columns.iter().map(|c| quote_identifier(c)).collect::<Vec<_>>().join(", "),
+            vec!["?"; columns.len()].join(", ")
+        )
+    };
+
+    let mut changes = Vec::with_capacity(request.copies);
+    let mut pk_values = Vec::with_capacity(request.copies);
+    for _ in 0..request.copies {
+        let mut params: Vec<SqlValue> = base.iter().map(|(_, v)| v.clone()).collect();
+        for id_column in &id_columns {
+            let code = next_code(&tx, &request.table_name, id_column, &record)?;
+            params.push(find_codec(&codecs, id_column)?.encode(&code_to_json(&code))?);
+        }
+        tx.execute(&sql, rusqlite::params_from_iter(params.iter()))
+            .map_err(|e| format!("Error al crear la copia: {}", e))?;
+
+        let inserted = inserted_row(&tx, &request.table_name, tx.last_insert_rowid())?;
+        let pk_value = inserted.after.as_ref()
+            .and_then(|image| image.get(&pk_column))
+            .map(sql_to_json)
+            .unwrap_or(Value::Null);
+        pk_values.push(pk_value);
+        changes.push(inserted);
+    }
+    log_row_changes(&tx, "duplicate_record", &changes)?;
+
+    tx.commit()
+        .map_err(|e| format!("Error al guardar los cambios: {}", e))?;
+
+    undo_state.record(
+        &db_name,
+        format!("Duplicar registro de {} ({} copias)", request.table_name, request.copies),
+        changes,
+    );
+
+    Ok(DuplicateResult { pk_column, pk_values })
+}
@@ -0,0 +1,597 @@
+/// =========================================================================
+/// Módulo: Edición masiva de registros
+///
+/// Funcionalidades:
+/// - Seleccionar registros por lista de claves primarias o por filtro
+///   (el mismo árbol de `filtros.rs`)
+/// - Asignar valores a muchas filas a la vez: valor fijo, vaciar,
+///   buscar/reemplazar texto y agregar texto al final
+/// - Vista previa de los cambios antes de aplicarlos
+/// - Eliminar muchos registros a la vez, devolviendo las filas borradas
+///   para poder restaurarlas
+/// - Todo se ejecuta en una sola transacción
+/// =========================================================================
+
+use std::collections::HashMap;
+
+use rusqlite::types::Value as SqlValue;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::State;
+
+use crate::adjuntos::discard_record_attachments;
+use crate::busqueda_tabla::primary_key_column;
+use crate::concurrencia::version_assignments;
+use crate::database_manager::AppState;
+use crate::deshacer::{read_row, RowChange, RowImage, UndoState};
+use crate::auditoria::log_row_changes;
+use crate::filtros::FilterNode;
+use crate::papelera::{active_rows_condition, is_soft_delete_enabled, DeletionMark};
+use crate::codec_valores::{json_to_sql, sql_to_json, ColumnCodec, LogicalType};
+
+/// Número máximo de filas que se devuelven en la vista previa.
+const PREVIEW_LIMIT: usize = 100;
+
+fn quote_identifier(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace("\"", "\"\""))
+}
+
+/// Registros sobre los que se aplica una operación masiva.
+/// Si se indican claves y filtro, deben cumplirse ambos.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct RowSelection {
+    /// Valores de la clave primaria de los registros seleccionados
+    #[serde(default)]
+    pub pk_values: Option<Vec<Value>>,
+    /// Filtro sobre las filas
+    #[serde(default)]
+    pub filter: Option<FilterNode>,
+}
+
+/// Asignación a una columna
+#[derive(Debug, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Assignment {
+    /// Asigna el mismo valor a todas las filas
+    Set { column: String, value: Value },
+    /// Deja la columna en NULL
+    Clear { column: String },
+    /// Reemplaza un texto por otro dentro de la columna (distingue mayúsculas)
+    Replace { column: String, find: String, replace: String },
+    /// Agrega texto al final del valor actual
+    Append { column: String, text: String },
+}
+
+impl Assignment {
+    fn column(&self) -> &str {
+        match self {
+            Assignment::Set { column, .. }
+            | Assignment::Clear { column }
+            | Assignment::Replace { column, .. }
+            | Assignment::Append { column, .. } => column,
+        }
+    }
+
+    /// Expresión SQL del nuevo valor y sus parámetros
+    fn to_sql(&self, codec: &ColumnCodec) -> Result<(String, Vec<SqlValue>), String> {
+        let col = quote_identifier(self.column());
+        match self {
+            Assignment::Set { value, .. } => Ok(("?".to_string(), vec![codec.encode(value)?])),
+            Assignment::Clear { .. } => Ok(("NULL".to_string(), Vec::new())),
+            Assignment::Replace { find, replace, .. } => {
+                if find.is_empty() {
+                    return Err(format!("El texto a buscar en '{}' no puede estar vacío", self.column()));
+                }
+                Ok((
+                    format!("REPLACE({}, ?, ?)", col),
+                    vec![SqlValue::Text(find.clone()), SqlValue::Text(replace.clone())],
+                ))
+            }
+            Assignment::Append { text, .. } => Ok((
+                format!("COALESCE({}, '') || ?", col),
+                vec![SqlValue::Text(text.clone())],
+            )),
+        }
+    }
+}
+
+/// Parámetros de `bulk_update_rows`
+#[derive(Debug, Deserialize)]
+pub struct BulkUpdateRequest {
+    pub table_name: String,
+    pub selection: RowSelection,
+    pub assignments: Vec<Assignment>,
+    /// Solo calcula los cambios sin aplicarlos
+    #[serde(default)]
+    pub preview: bool,
+}
+
+/// Cambio de una columna en un registro
+#[derive(Debug, Serialize)]
+pub struct ColumnChange {
+    pub column: String,
+    pub old_value: Value,
+    pub new_value: Value,
+}
+
+/// Registro que cambiaría (vista previa)
+#[derive(Debug, Serialize)]
+pub struct PreviewRow {
+    pub pk_value: Value,
+    pub changes: Vec<ColumnChange>,
+}
+
+/// Resultado de `bulk_update_rows`
+#[derive(Debug, Serialize)]
+pub struct BulkUpdateResult {
+    /// Columna de clave primaria usada para identificar los registros
+    pub pk_column: String,
+    /// Registros que cumplen la selección
+    pub matched_rows: usize,
+    /// Registros cuyo valor cambia (o cambió)
+    pub changed_rows: usize,
+    /// Indica si fue solo una vista previa
+    pub preview: bool,
+    /// Primeros registros que cambian, con valores antes y después (solo en vista previa)
+    pub rows: Vec<PreviewRow>,
+}
+
+/// Columna de la tabla con la información necesaria para validar
+pub(crate) struct ColumnMeta {
+    pub name: String,
+    pub col_type: String,
+    pub notnull: bool,
+}
+
+impl ColumnMeta {
+    /// Conversión de valores según el tipo de la columna
+    pub(crate) fn codec(&self) -> ColumnCodec {
+        ColumnCodec::new(&self.name, &self.col_type, self.notnull)
+    }
+}
+
+pub(crate) fn table_columns(conn: &Connection, table_name: &str) -> Result<Vec<ColumnMeta>, String> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", quote_identifier(table_name)))
+        .map_err(|e| format!("Error al preparar la consulta de columnas: {}", e))?;
+    let columns = stmt.query_map([], |row| {
+        Ok(ColumnMeta {
+            name: row.get(1)?,
+            col_type: row.get::<_, String>(2)?.to_uppercase(),
+            notnull: row.get::<_, i64>(3)? != 0,
+        })
+    })
+    .map_err(|e| format!("Error al ejecutar la consulta de columnas: {}", e))?
+    .collect::<Result<Vec<_>, _>>()
+    .map_err(|e| format!("Error al obtener columnas: {}", e))?;
+
+    if columns.is_empty() {
+        return Err(format!("No se encontró la tabla: {}", table_name));
+    }
+    Ok(columns)
+}
+
+/// Construye la condición SQL (sin "WHERE") de una selección de registros.
+/// Se exige una lista de claves o un filtro para no afectar toda la tabla por accidente.
+pub(crate) fn selection_condition(
+    selection: &RowSelection,
+    pk_column: &str,
+    valid_columns: &[String],
+    params: &mut Vec<SqlValue>,
+) -> Result<String, String> {
+    let mut parts = Vec::new();
+
+    if let Some(pk_values) = &selection.pk_values {
+        if pk_values.is_empty() {
+            return Err("No se seleccionó ningún registro".to_string());
+        }
+        for value in pk_values {
+            params.push(json_to_sql(value)?);
+        }
+        parts.push(format!(
+            "{} IN ({})",
+            quote_identifier(pk_column),
+            vec!["?"; pk_values.len()].join(", ")
+        ));
+    }
+
+    if let Some(filter) = &selection.filter {
+        parts.push(filter.to_sql(valid_columns, params)?);
+    }
+
+    if parts.is_empty() {
+        return Err("Debe indicar los registros por clave primaria o por filtro".to_string());
+    }
+    Ok(parts.join(" AND "))
+}
+
+/// Actualiza muchos registros a la vez en una sola transacción.
+///
+/// Ejemplo: mover todos los equipos con `Ubicación = "Bodega"` a "Aula 5"
+/// con la asignación `{ "type": "set", "column": "Ubicación", "value": "Aula 5" }`.
+/// Solo se modifican las filas cuyo valor realmente cambia.
+#[tauri::command]
+pub fn bulk_update_rows(
+    state: State<AppState>,
+    undo_state: State<UndoState>,
+    db_name: String,
+    request: BulkUpdateRequest,
+) -> Result<BulkUpdateResult, String> {
+    if request.assignments.is_empty() {
+        return Err("No hay datos para actualizar.".to_string());
+    }
+
+    let db_path = state.db_dir.join(format!("{}.db", db_name));
+    let sqlite_path = state.db_dir.join(format!("{}.sqlite", db_name));
+
+    let db_file = if db_path.exists() {
+        db_path
+    } else if sqlite_path.exists() {
+        sqlite_path
+    } else {
+        return Err(format!("Base de datos no encontrada: {}", db_name));
+    };
+
+    let mut conn = Connection::open(&db_file)
+        .map_err(|e| format!("Error al abrir la base de datos: {}", e))?;
+
+    let columns = table_columns(&conn, &request.table_name)?;
+    let column_names: Vec<String> = columns.iter().map(|c| c.name.clone()).collect();
+    let pk_column = primary_key_column(&conn, &request.table_name)?;
+
+    // Validación de las asignaciones contra el esquema real
+    let mut assigned: Vec<&str> = Vec::new();
+    let mut set_exprs: Vec<(ColumnCodec, String, Vec<SqlValue>)> = Vec::new();
+    for assignment in &request.assignments {
+        let name = assignment.column();
+        let meta = columns.iter().find(|c| c.name == name)
+            .ok_or_else(|| format!("La columna '{}' no existe en la tabla", name))?;
+        if name == pk_column {
+            return Err(format!("No se puede modificar la clave primaria '{}' de forma masiva", name));
+        }
+        if assigned.contains(&name) {
+            return Err(format!("La columna '{}' tiene más de una asignación", name));
+        }
+        let codec = meta.codec();
+        if codec.logical == LogicalType::Blob && !matches!(assignment, Assignment::Clear { .. }) {
+            return Err(format!("La columna '{}' es de imagen y solo se puede vaciar", name));
+        }
+        let clears = matches!(assignment, Assignment::Clear { .. })
+            || matches!(assignment, Assignment::Set { value: Value::Null, .. });
+        if meta.notnull && clears {
+            return Err(format!("La columna '{}' no admite valores vacíos", name));
+        }
+        assigned.push(name);
+
+        let (expr, params) = assignment.to_sql(&codec)?;
+        set_exprs.push((codec, expr, params));
+    }
+
+    let mut selection_params = Vec::new();
+    let mut condition = selection_condition(&request.selection, &pk_column, &column_names, &mut selection_params)?;
+    // Los registros en la papelera no se modifican
+    if let Some(active) = active_rows_condition(&conn, &request.table_name)? {
+        condition = format!("({}) AND {}", condition, active);
+    }
+
+    // Solo cuentan las filas donde al menos una columna cambia de valor
+    let changed_condition = set_exprs
+        .iter()
+        .map(|(col, expr, _)| format!("{} IS NOT {}", quote_identifier(&col.name), expr))
+        .collect::<Vec<_>>()
+        .join(" OR ");
+    let expr_params: Vec<SqlValue> = set_exprs.iter().flat_map(|(_, _, p)| p.iter().cloned()).collect();
+
+    let table = quote_identifier(&request.table_name);
+
+    let matched_rows: i64 = conn.query_row(
+        &format!("SELECT COUNT(*) FROM {} WHERE {}", table, condition),
+        rusqlite::params_from_iter(selection_params.iter()),
+        |row| row.get(0),
+    ).map_err(|e| format!("Error al contar los registros seleccionados: {}", e))?;
+
+    // Parámetros: expresiones (SET o SELECT), selección y expresiones de nuevo (condición de cambio)
+    let mut params: Vec<SqlValue> = expr_params.clone();
+    params.extend(selection_params.iter().cloned());
+    params.extend(expr_params.iter().cloned());
+
+    if request.preview {
+        let select_parts: Vec<String> = set_exprs
+            .iter()
+            .map(|(col, expr, _)| format!("{}, {}", quote_identifier(&col.name), expr))
+            .collect();
+        let changed_rows: i64 = conn.query_row(
+            &format!("SELECT COUNT(*) FROM {} WHERE ({}) AND ({})", table, condition, changed_condition),
+            rusqlite::params_from_iter(selection_params.iter().chain(expr_params.iter())),
+            |row| row.get(0),
+        ).map_err(|e| format!("Error al calcular la vista previa: {}", e))?;
+
+        // La clave va al final para que los índices de las expresiones coincidan con los parámetros
+        let sql = format!(
+            "SELECT {}, {} FROM {} WHERE ({}) AND ({}) LIMIT {}",
+            select_parts.join(", "),
+            quote_identifier(&pk_column),
+            table,
+            condition,
+            changed_condition,
+            PREVIEW_LIMIT
+        );
+        let mut stmt = conn.prepare(&sql)
+            .map_err(|e| format!("Error al preparar la vista previa: {}", e))?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(params.iter()), |row| {
+            let mut changes = Vec::new();
+            for (i, (codec, _, _)) in set_exprs.iter().enumerate() {
+                let old_value = codec.decode(row.get_ref(i * 2)?);
+                let new_value = codec.decode(row.get_ref(i * 2 + 1)?);
+                if old_value != new_value {
+                    changes.push(ColumnChange { column: codec.name.clone(), old_value, new_value });
+                }
+            }
+            Ok(PreviewRow {
+                pk_value: sql_to_json(&row.get(set_exprs.len() * 2)?),
+                changes,
+            })
+        })
+        .map_err(|e| format!("Error al calcular la vista previa: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Error al leer la vista previa: {}", e))?;
+
+        return Ok(BulkUpdateResult {
+            pk_column,
+            matched_rows: matched_rows as usize,
+            changed_rows: changed_rows as usize,
+            preview: true,
+            rows,
+        });
+    }
+
+    // Marca la modificación en "version" y "updated_at" para que otras estaciones la detecten
+    let mut set_clause: Vec<String> = set_exprs
+        .iter()
+        .map(|(col, expr, _)| format!("{} = {}", quote_identifier(&col.name), expr))
+        .collect();
+    let assigned: Vec<String> = assigned.iter().map(|c| c.to_string()).collect();
+    set_clause.extend(version_assignments(&column_names, &assigned));
+
+    let sql = format!(
+        "UPDATE {} SET {} WHERE ({}) AND ({})",
+        table,
+        set_clause.join(", "),
+        condition,
+        changed_condition
+    );
+
+    let tx = conn.transaction()
+        .map_err(|e| format!("Error al iniciar la transacción: {}", e))?;
+
+    // Claves e imágenes previas de las filas que van a cambiar (para deshacer)
+    let pk_sql = format!(
+        "SELECT {} FROM {} WHERE ({}) AND ({})",
+        quote_identifier(&pk_column),
+        table,
+        condition,
+        changed_condition
+    );
+    let pk_values: Vec<SqlValue> = {
+        let mut stmt = tx.prepare(&pk_sql)
+            .map_err(|e| format!("Error al preparar la consulta de registros: {}", e))?;
+        let values = stmt.query_map(
+            rusqlite::params_from_iter(selection_params.iter().chain(expr_params.iter())),
+            |row| row.get::<_, SqlValue>(0),
+        )
+        .map_err(|e| format!("Error al consultar los registros: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Error al leer los registros: {}", e))?;
+        values
+    };
+    let mut befores = Vec::with_capacity(pk_values.len());
+    for pk_value in &pk_values {
+        befores.push(read_row(&tx, &request.table_name, &pk_column, pk_value)?);
+    }
+
+    let changed_rows = tx.execute(&sql, rusqlite::params_from_iter(params.iter()))
+        .map_err(|e| format!("Error al ejecutar UPDATE: {}", e))?;
+
+    let mut changes = Vec::with_capacity(pk_values.len());
+    for (pk_value, before) in pk_values.iter().zip(befores) {
+        changes.push(RowChange {
+            table_name: request.table_name.clone(),
+            pk_column: pk_column.clone(),
+            before,
+            after: read_row(&tx, &request.table_name, &pk_column, pk_value)?,
+        });
+    }
+    log_row_changes(&tx, "bulk_update_rows", &changes)?;
+
+    tx.commit()
+        .map_err(|e| format!("Error al guardar los cambios: {}", e))?;
+
+    undo_state.record(
+        &db_name,
+        format!("Actualizar {} registros de {}", changed_rows, request.table_name),
+        changes,
+    );
+
+    Ok(BulkUpdateResult {
+        pk_column,
+        matched_rows: matched_rows as usize,
+        changed_rows,
+        preview: false,
+        rows: Vec::new(),
+    })
+}
+
+/// Parámetros de `bulk_delete_rows`
+#[derive(Debug, Deserialize)]
+pub struct BulkDeleteRequest {
+    pub table_name: String,
+    pub selection: RowSelection,
+    /// Solo cuenta los registros sin eliminarlos
+    #[serde(default)]
+    pub preview: bool,
+}
+
+/// Resultado de `bulk_delete_rows`
+#[derive(Debug, Serialize)]
+pub struct BulkDeleteResult {
+    /// Columna de clave primaria usada para identificar los registros
+    pub pk_column: String,
+    /// Registros que cumplen la selección
+    pub matched_rows: usize,
+    /// Registros eliminados (0 en vista previa)
+    pub deleted_rows: usize,
+    /// Indica si fue solo una vista previa
+    pub preview: bool,
+    /// Filas completas: las eliminadas, o las primeras que se eliminarían en vista previa.
+    /// Las imágenes (BLOB) van en base64 para poder reinsertarlas.
+    pub rows: Vec<HashMap<String, Value>>,
+}
+
+/// Lee filas completas con sus valores originales (incluidos los BLOB)
+pub(crate) fn read_raw_rows(
+    conn: &Connection,
+    sql: &str,
+    params: &[SqlValue],
+) -> Result<Vec<RowImage>, String> {
+    let mut stmt = conn.prepare(sql)
+        .map_err(|e| format!("Error al preparar la consulta: {}", e))?;
+    let names: Vec<String> = stmt.column_names().into_iter().map(|s| s.to_string()).collect();
+
+    let rows = stmt.query_map(rusqlite::params_from_iter(params.iter()), |row| {
+        let mut image = RowImage::new();
+        for (i, name) in names.iter().enumerate() {
+            image.insert(name.clone(), row.get::<_, SqlValue>(i)?);
+        }
+        Ok(image)
+    })
+    .map_err(|e| format!("Error al ejecutar la consulta: {}", e))?
+    .collect::<Result<Vec<_>, _>>()
+    .map_err(|e| format!("Error al leer los registros: {}", e))?;
+
+    Ok(rows)
+}
+
+/// Fila como JSON; los BLOB se codifican en base64 para poder restaurarlos
+pub(crate) fn image_to_json(image: &RowImage) -> HashMap<String, Value> {
+    image
+        .iter()
+        .map(|(name, value)| {
+            (name.clone(), sql_to_json(value))
+        })
+        .collect()
+}
+
+/// Elimina muchos registros a la vez en una sola transacción.
+///
+/// Con `preview = true` solo devuelve cuántos registros se eliminarían.
+/// Al eliminar, devuelve las filas borradas para poder restaurarlas.
+#[tauri::command]
+pub fn bulk_delete_rows(
+    state: State<AppState>,
+    undo_state: State<UndoState>,
+    db_name: String,
+    request: BulkDeleteRequest,
+) -> Result<BulkDeleteResult, String> {
+    let db_path = state.db_dir.join(format!("{}.db", db_name));
+    let sqlite_path = state.db_dir.join(format!("{}.sqlite", db_name));
+
+    let db_file = if db_path.exists() {
+        db_path
+    } else if sqlite_path.exists() {
+        sqlite_path
+    } else {
+        return Err(format!("Base de datos no encontrada: {}", db_name));
+    };
+
+    let mut conn = Connection::open(&db_file)
+        .map_err(|e| format!("Error al abrir la base de datos: {}", e))?;
+
+    let columns = table_columns(&conn, &request.table_name)?;
+    let column_names: Vec<String> = columns.iter().map(|c| c.name.clone()).collect();
+    let pk_column = primary_key_column(&conn, &request.table_name)?;
+
+    let mut params = Vec::new();
+    let mut condition = selection_condition(&request.selection, &pk_column, &column_names, &mut params)?;
+    let table = quote_identifier(&request.table_name);
+
+    // Con borrado lógico los registros van a la papelera (los que ya están allí no cuentan)
+    let soft_delete = is_soft_delete_enabled(&conn, &request.table_name)?;
+    if let Some(active) = active_rows_condition(&conn, &request.table_name)? {
+        condition = format!("({}) AND {}", condition, active);
+    }
+
+    // Las tablas sin clave primaria se identifican por rowid, que no forma parte de "*"
+    let select_list = if pk_column == "rowid" { "rowid, *" } else { "*" };
+
+    let tx = conn.transaction()
+        .map_err(|e| format!("Error al iniciar la transacción: {}", e))?;
+
+    let matched_rows: i64 = tx.query_row(
+        &format!("SELECT COUNT(*) FROM {} WHERE {}", table, condition),
+        rusqlite::params_from_iter(params.iter()),
+        |row| row.get(0),
+    ).map_err(|e| format!("Error al contar los registros seleccionados: {}", e))?;
+
+    if request.preview {
+        let rows = read_raw_rows(
+            &tx,
+            &format!("SELECT {} FROM {} WHERE {} LIMIT {}", select_list, table, condition, PREVIEW_LIMIT),
+            &params,
+        )?;
+        return Ok(BulkDeleteResult {
+            pk_column,
+            matched_rows: matched_rows as usize,
+            deleted_rows: 0,
+            preview: true,
+            rows: rows.iter().map(image_to_json).collect(),
+        });
+    }
+
+    let rows = read_raw_rows(
+        &tx,
+        &format!("SELECT {} FROM {} WHERE {}", select_list, table, condition),
+        &params,
+    )?;
+    let mark = soft_delete.then(DeletionMark::now);
+    let deleted_rows = match &mark {
+        Some(mark) => mark.mark(&tx, &request.table_name, &condition, &params)?,
+        None => tx.execute(
+            &format!("DELETE FROM {} WHERE {}", table, condition),
+            rusqlite::params_from_iter(params.iter()),
+        ).map_err(|e| format!("Error al ejecutar DELETE: {}", e))?,
+    };
+
+    let json_rows = rows.iter().map(image_to_json).collect();
+    let mut changes: Vec<RowChange> = Vec::with_capacity(rows.len());
+    for image in rows {
+        let pk_value = image.get(&pk_column).cloned();
+        changes.push(RowChange {
+            table_name: request.table_name.clone(),
+            pk_column: pk_column.clone(),
+            after: mark.as_ref().map(|mark| mark.apply(image.clone())),
+            before: Some(image),
+        });
+        // Sin papelera, los adjuntos de cada registro se eliminan con él
+        if let (None, Some(pk_value)) = (&mark, pk_value) {
+            changes.extend(discard_record_attachments(&tx, &request.table_name, &pk_value)?);
+        }
+    }
+    log_row_changes(&tx, "bulk_delete_rows", &changes)?;
+
+    tx.commit()
+        .map_err(|e| format!("Error al guardar los cambios: {}", e))?;
+
+    undo_state.record(
+        &db_name,
+        format!("Eliminar {} registros de {}", deleted_rows, request.table_name),
+        changes,
+    );
+
+    Ok(BulkDeleteResult {
+        pk_column,
+        matched_rows: matched_rows as usize,
+        deleted_rows,
+        preview: false,
+        rows: json_rows,
+    })
+}
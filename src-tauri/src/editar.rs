@@ -1,63 +1,48 @@
-use rusqlite::{Connection, Result, ToSql};
+use rusqlite::{Connection, Result, ToSql, TransactionBehavior};
 use rusqlite::types::Value;
 use tauri::State;
 use std::collections::HashMap;
 use serde_json;
-use base64::{Engine as _, engine::general_purpose};
 use crate::database_manager::AppState;
+use crate::protocolo_imagenes::is_image_url;
+use crate::concurrencia::{check_unchanged, version_assignments};
+use crate::deshacer::{inserted_row, read_row, RowChange, UndoState};
+use crate::auditoria::{log_operation, log_row_changes};
+use crate::papelera::delete_row;
+use crate::secuencias::{code_to_json, next_code};
+use crate::codec_valores::{find_codec, table_codecs};
+use crate::valores_predeterminados::apply_defaults;
 
 // Helper function to quote SQL identifiers (table names, column names) for SQLite
 fn quote_identifier(s: &str) -> String {
     format!("\"{}\"", s)
 }
 
-// Convierte un valor JSON de Serde a un valor SQL de Rusqlite.
-fn convert_json_to_sql(json_value: &serde_json::Value, column_type: Option<&String>) -> Result<Value, String> {
-    match json_value {
-        serde_json::Value::Null => Ok(Value::Null),
-        serde_json::Value::Bool(b) => Ok(Value::Integer(if *b { 1 } else { 0 })),
-        serde_json::Value::Number(n) => {
-            if let Some(i) = n.as_i64() {
-                Ok(Value::Integer(i))
-            } else if let Some(f) = n.as_f64() {
-                Ok(Value::Real(f))
-            } else {
-                Err("Número no soportado".to_string())
-            }
-        }
-        serde_json::Value::String(s) => {
-            // If it's a BLOB column and the string looks like base64, decode it
-            if column_type.as_ref().map_or(false, |t| *t == "BLOB") {
-                match general_purpose::STANDARD.decode(s) {
-                    Ok(bytes) => Ok(Value::Blob(bytes)),
-                    Err(_) => Ok(Value::Text(s.clone())), // Fallback to text if not valid base64
-                }
-            } else {
-                Ok(Value::Text(s.clone()))
-            }
-        },
-        _ => Err(format!("Tipo de dato no soportado para conversión: {:?}", json_value)),
-    }
-}
-
 // Comando de Tauri para actualizar una fila en cualquier tabla.
 #[tauri::command]
 pub fn update_table_row(
     state: State<AppState>,
+    undo_state: State<UndoState>,
     db_name: String,
     table_name: String,
     pk_column: String,
     pk_value: serde_json::Value,
     updates: HashMap<String, serde_json::Value>,
-    column_types: Option<HashMap<String, String>>,
-    _column_notnull: Option<HashMap<String, i32>>,
+    original_values: Option<HashMap<String, serde_json::Value>>,
 ) -> Result<bool, String> {
     if updates.is_empty() {
         return Err("No hay datos para actualizar.".to_string());
     }
 
-    // Use all updates directly since frontend validates null values
-    let filtered_updates = updates;
+    // Use all updates directly since frontend validates null values,
+    // except images returned unchanged as unea-img:// URLs (the BLOB stays as is)
+    let filtered_updates: HashMap<String, serde_json::Value> = updates
+        .into_iter()
+        .filter(|(_, v)| !v.as_str().map_or(false, is_image_url))
+        .collect();
+    if filtered_updates.is_empty() {
+        return Ok(true);
+    }
 
     // Determina la ruta del archivo de la base de datos.
     let db_path = state.db_dir.join(format!("{}.db", db_name));
@@ -70,15 +55,36 @@ pub fn update_table_row(
         return Err(format!("Base de datos no encontrada: {}", db_name));
     };
 
-    let conn = Connection::open(&db_file)
+    let mut conn = Connection::open(&db_file)
         .map_err(|e| format!("Error al abrir la base de datos: {}", e))?;
 
+    // Transacción inmediata: nadie puede escribir entre la verificación y el UPDATE.
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)
+        .map_err(|e| format!("Error al iniciar la transacción: {}", e))?;
+
+    // Si el frontend envía los valores que leyó, se verifica que nadie los haya cambiado.
+    if let Some(original) = original_values.as_ref().filter(|o| !o.is_empty()) {
+        check_unchanged(&tx, &db_name, &table_name, &pk_column, &pk_value, original)?;
+    }
+
+    // Los valores se convierten según el tipo declarado de cada columna.
+    let codecs = table_codecs(&tx, &table_name)?;
+
+    // Imagen previa de la fila para poder deshacer el cambio.
+    let pk_sql_value = find_codec(&codecs, &pk_column)?.encode(&pk_value)?;
+    let before = read_row(&tx, &table_name, &pk_column, &pk_sql_value)?;
+
     // Construye la cláusula SET de la consulta SQL.
-    let set_clause: Vec<String> = filtered_updates
+    let mut set_clause: Vec<String> = filtered_updates
         .keys()
         .map(|k| format!("{} = ?", quote_identifier(k)))
         .collect();
 
+    // Marca la modificación en las columnas "version" y "updated_at", si existen.
+    let all_columns: Vec<String> = codecs.iter().map(|c| c.name.clone()).collect();
+    let assigned: Vec<String> = filtered_updates.keys().cloned().collect();
+    set_clause.extend(version_assignments(&all_columns, &assigned));
+
     // Construye la consulta SQL completa.
     let sql = format!(
         "UPDATE {} SET {} WHERE {} = ?",
@@ -90,26 +96,38 @@ pub fn update_table_row(
     // Prepara los parámetros para la consulta.
     let mut params: Vec<Value> = Vec::new();
     for key in filtered_updates.keys() {
-        let value = &filtered_updates[key];
-        let col_type = column_types.as_ref().and_then(|ct| ct.get(key));
-        params.push(convert_json_to_sql(value, col_type)?);
+        params.push(find_codec(&codecs, key)?.encode(&filtered_updates[key])?);
     }
-    params.push(convert_json_to_sql(&pk_value, None)?);
-
-
+    params.push(pk_sql_value.clone());
 
     let params_refs: Vec<&dyn ToSql> = params.iter().map(|v| v as &dyn ToSql).collect();
 
     // Ejecuta la consulta de actualización.
-    let rows_affected = conn.execute(&sql, params_refs.as_slice())
+    let rows_affected = tx.execute(&sql, params_refs.as_slice())
         .map_err(|e| format!("Error al ejecutar UPDATE: {}", e))?;
 
-
-
     if rows_affected == 0 {
         return Err(format!("No se encontró ninguna fila con {} = {:?}", pk_column, pk_value));
     }
 
+    // La clave primaria pudo cambiar en esta misma edición.
+    let new_pk = match filtered_updates.get(&pk_column) {
+        Some(v) => find_codec(&codecs, &pk_column)?.encode(v)?,
+        None => pk_sql_value,
+    };
+    let change = RowChange {
+        table_name: table_name.clone(),
+        pk_column: pk_column.clone(),
+        before,
+        after: read_row(&tx, &table_name, &pk_column, &new_pk)?,
+    };
+    log_row_changes(&tx, "update_table_row", std::slice::from_ref(&change))?;
+
+    tx.commit()
+        .map_err(|e| format!("Error al guardar los cambios: {}", e))?;
+
+    undo_state.record(&db_name, format!("Editar registro de {}", table_name), vec![change]);
+
     Ok(true)
 }
 
@@ -117,6 +135,7 @@ pub fn update_table_row(
 #[tauri::command]
 pub fn delete_table_row(
     state: State<AppState>,
+    undo_state: State<UndoState>,
     db_name: String,
     table_name: String,
     pk_column: String,
@@ -133,24 +152,17 @@ pub fn delete_table_row(
         return Err(format!("Base de datos no encontrada: {}", db_name));
     };
 
-    let conn = Connection::open(&db_file)
+    let mut conn = Connection::open(&db_file)
         .map_err(|e| format!("Error al abrir la base de datos: {}", e))?;
-
-    // Construye la consulta SQL de eliminación.
-    let sql = format!(
-        "DELETE FROM {} WHERE {} = ?",
-        quote_identifier(&table_name),
-        quote_identifier(&pk_column)
-    );
+    let tx = conn.transaction()
+        .map_err(|e| format!("Error al iniciar la transacción: {}", e))?;
 
     // Convierte el valor de la clave primaria a un valor SQL.
-    let pk_sql_value = convert_json_to_sql(&pk_value, None)?;
+    let pk_sql_value = find_codec(&table_codecs(&tx, &table_name)?, &pk_column)?.encode(&pk_value)?;
 
-    println!("DELETE SQL: {}, PK column: {}, PK value: {} (type: {})", sql, pk_column, pk_value, pk_value);
-
-    // Ejecuta la consulta de eliminación.
-    let rows_affected = conn.execute(&sql, &[&pk_sql_value as &dyn ToSql])
-        .map_err(|e| format!("Error al ejecutar DELETE: {}", e))?;
+    // Elimina la fila (o la envía a la papelera si la tabla usa borrado lógico)
+    // y conserva su imagen anterior para poder deshacer.
+    let (rows_affected, changes) = delete_row(&tx, &table_name, &pk_column, &pk_sql_value)?;
 
     println!("DELETE ejecutado: {} filas afectadas", rows_affected);
 
@@ -158,6 +170,13 @@ pub fn delete_table_row(
         return Err(format!("No se encontró ninguna fila con {} = {:?}", pk_column, pk_value));
     }
 
+    log_row_changes(&tx, "delete_table_row", &changes)?;
+
+    tx.commit()
+        .map_err(|e| format!("Error al guardar los cambios: {}", e))?;
+
+    undo_state.record(&db_name, format!("Eliminar registro de {}", table_name), changes);
+
     Ok(true)
 }
 
@@ -173,10 +192,10 @@ pub struct ExecuteSqlParams {
 #[tauri::command]
 pub fn crear_registro_con_auto_incremento_no(
     state: State<AppState>,
+    undo_state: State<UndoState>,
     db_name: String,
     table_name: String,
     data: HashMap<String, serde_json::Value>,
-    column_types: Option<HashMap<String, String>>,
 ) -> Result<bool, String> {
     // Determina la ruta del archivo de la base de datos.
     let db_path = state.db_dir.join(format!("{}.db", db_name));
@@ -189,48 +208,26 @@ pub fn crear_registro_con_auto_incremento_no(
         return Err(format!("Base de datos no encontrada: {}", db_name));
     };
 
-    let conn = Connection::open(&db_file)
+    let mut conn = Connection::open(&db_file)
         .map_err(|e| format!("Error al abrir la base de datos: {}", e))?;
 
-    // Obtener el último valor de "No." en la tabla ordenado numéricamente
-    let last_no_query = format!("SELECT {} FROM {} WHERE {} IS NOT NULL AND {} != '' ORDER BY CAST({} AS INTEGER) DESC LIMIT 1", quote_identifier("No."), quote_identifier(&table_name), quote_identifier("No."), quote_identifier("No."), quote_identifier("No."));
-    let mut stmt = conn.prepare(&last_no_query)
-        .map_err(|e| format!("Error al preparar consulta para obtener último No.: {}", e))?;
-
-    let last_no: Option<i64> = stmt.query_row([], |row| row.get(0))
-        .ok(); // Si no hay filas, devuelve None
-
-    let next_no = last_no.map(|n| n + 1).unwrap_or(1);
-
-    // Verificar que el número no exista ya (por si acaso)
-    let check_query = format!("SELECT COUNT(*) FROM {} WHERE {} = ?", quote_identifier(&table_name), quote_identifier("No."));
-    let mut check_stmt = conn.prepare(&check_query)
-        .map_err(|e| format!("Error al preparar consulta de verificación: {}", e))?;
-
-    let count: i64 = check_stmt.query_row([next_no], |row| row.get(0))
-        .unwrap_or(0);
-
-    let final_no = if count > 0 {
-        // Si ya existe, buscar el siguiente número disponible
-        let find_next_query = format!("SELECT {} + 1 FROM {} WHERE {} + 1 NOT IN (SELECT {} FROM {}) AND {} IS NOT NULL ORDER BY {} LIMIT 1", quote_identifier("No."), quote_identifier(&table_name), quote_identifier("No."), quote_identifier("No."), quote_identifier(&table_name), quote_identifier("No."), quote_identifier("No."));
-        let mut find_stmt = conn.prepare(&find_next_query)
-            .map_err(|e| format!("Error al preparar consulta para encontrar siguiente número: {}", e))?;
-
-        find_stmt.query_row([], |row| row.get(0))
-            .unwrap_or(next_no + 1)
-    } else {
-        next_no
-    };
+    // El número se asigna con la secuencia de la tabla dentro de la transacción
+    // de escritura, así dos estaciones no reciben el mismo "No."
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)
+        .map_err(|e| format!("Error al iniciar la transacción: {}", e))?;
+    let codecs = table_codecs(&tx, &table_name)?;
+    let mut data = data;
+    apply_defaults(&tx, &table_name, &mut data)?;
+    let final_no = next_code(&tx, &table_name, "No.", &data)?;
 
     // Log detallado del proceso siguiendo el formato solicitado
-    println!("Último número encontrado en no: {:?}", last_no);
     println!("Nuevo número asignado: {}", final_no);
     println!();
     println!("Nuevo registro agregado correctamente:");
 
     // Crear los datos con el nuevo número "No."
     let mut data_with_no = data.clone();
-    data_with_no.insert("No.".to_string(), serde_json::Value::Number(serde_json::Number::from(final_no)));
+    data_with_no.insert("No.".to_string(), code_to_json(&final_no));
 
     // Construir la consulta INSERT
     let columns: Vec<String> = data_with_no.keys().map(|k| quote_identifier(k)).collect();
@@ -246,17 +243,23 @@ pub fn crear_registro_con_auto_incremento_no(
     // Preparar los parámetros
     let mut params: Vec<Value> = Vec::new();
     for key in data_with_no.keys() {
-        let value = &data_with_no[key];
-        let col_type = column_types.as_ref().and_then(|ct| ct.get(key));
-        params.push(convert_json_to_sql(value, col_type)?);
+        params.push(find_codec(&codecs, key)?.encode(&data_with_no[key])?);
     }
 
     let params_refs: Vec<&dyn ToSql> = params.iter().map(|v| v as &dyn ToSql).collect();
 
     // Ejecutar la inserción
-    let _rows_affected = conn.execute(&sql, params_refs.as_slice())
+    let _rows_affected = tx.execute(&sql, params_refs.as_slice())
         .map_err(|e| format!("Error al ejecutar INSERT: {}", e))?;
 
+    // Registrar el alta para poder deshacerla
+    let inserted = inserted_row(&tx, &table_name, tx.last_insert_rowid())?;
+    log_row_changes(&tx, "crear_registro_con_auto_incremento_no", std::slice::from_ref(&inserted))?;
+
+    tx.commit()
+        .map_err(|e| format!("Error al guardar los cambios: {}", e))?;
+    undo_state.record(&db_name, format!("Crear registro en {}", table_name), vec![inserted]);
+
     // Mostrar los datos del registro creado en el formato solicitado
     for (key, value) in &data_with_no {
         if key == "No." {
@@ -308,5 +311,7 @@ pub fn execute_sql(
     conn.execute(&params.sql, rusqlite::params_from_iter(&params.params))
         .map_err(|e| format!("Error al ejecutar SQL: {}", e))?;
 
+    log_operation(&conn, "sql", None, None, Some(&params.sql))?;
+
     Ok(true)
 }
\ No newline at end of file
@@ -51,7 +51,7 @@ pub fn delete_column(
     };
 
     // 3. Abrir la conexión con la base de datos.
-    let conn = Connection::open(&db_file)
+    let mut conn = Connection::open(&db_file)
         .map_err(|e| format!("Error al abrir la base de datos: {}", e))?;
 
     // 4. Construir la sentencia SQL para eliminar la columna.
@@ -62,9 +62,31 @@ pub fn delete_column(
         quote_identifier(&column_name)
     );
 
-    // 5. Ejecutar la sentencia SQL.
-    conn.execute(&sql, [])
+    // 5. Si la columna forma parte del índice de búsqueda, los triggers la referencian
+    //    y SQLite rechazaría el DROP COLUMN. Se elimina el índice y se reconstruye después,
+    //    todo dentro de una transacción para no perder el índice si algo falla.
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let mut indexed_columns = Vec::new();
+    if crate::busqueda_tabla::has_search_index(&tx, &table_name)? {
+        indexed_columns = crate::busqueda_tabla::indexed_columns(&tx, &table_name)?;
+        crate::busqueda_tabla::drop_search_index(&tx, &table_name)?;
+    }
+    //    Lo mismo ocurre con los triggers del almacén de imágenes.
+    crate::almacen_imagenes::forget_store_columns(&tx, &table_name, Some(&column_name))?;
+
+    // 6. Ejecutar la sentencia SQL.
+    tx.execute(&sql, [])
         .map_err(|e| format!("Error al eliminar la columna '{}': {}. Es posible que su versión de SQLite no soporte DROP COLUMN.", column_name, e))?;
 
+    // 7. Reconstruir el índice de búsqueda sin la columna eliminada.
+    indexed_columns.retain(|c| c != &column_name);
+    if !indexed_columns.is_empty() {
+        crate::busqueda_tabla::create_search_index(&tx, &table_name, &indexed_columns)?;
+    }
+
+    crate::auditoria::log_operation(&tx, "drop_column", Some(&table_name), Some(&column_name), None)?;
+
+    tx.commit().map_err(|e| format!("Error al confirmar transacción: {}", e))?;
+
     Ok(())
 }
@@ -1,16 +1,24 @@
+use rusqlite::types::ValueRef;
 use rusqlite::Connection;
+use serde_json::Value as JsonValue;
 use std::collections::HashMap;
 use tauri::State;
 
 use crate::database_manager::AppState;
-use crate::io_utils::{TableExport, rusqlite_to_json}; // Usa el módulo compartido
+use crate::codec_valores::{decode_value, table_codecs, LogicalType};
+use crate::almacen_imagenes::is_image_ref;
+use crate::io_utils::TableExport; // Usa el módulo compartido
+use crate::papelera::{DELETED_AT_COLUMN, DELETED_BY_COLUMN};
+use crate::vistas_guardadas::{load_view, view_sql};
 
 // Exporta la estructura y los datos de una tabla a un string JSON.
+// Si se indica `view_id`, solo se exportan las filas y columnas de esa vista guardada.
 #[tauri::command]
 pub fn export_table_to_json(
     state: State<AppState>,
     db_name: String,
     table_name: String,
+    view_id: Option<i64>,
 ) -> Result<String, String> {
     // Verificar que la base de datos existe antes de proceder
     let db_path = state.db_dir.join(format!("{}.db", db_name));
@@ -33,20 +41,65 @@ pub fn export_table_to_json(
         |row| row.get(0),
     ).map_err(|e| format!("No se pudo obtener la estructura de la tabla: {}", e))?;
 
-    // Prepara la consulta para obtener todos los datos.
-    let mut stmt = conn.prepare(&format!("SELECT * FROM \"{}\"", table_name))
+    // Prepara la consulta para obtener los datos: toda la tabla o la vista guardada.
+    let mut params: Vec<rusqlite::types::Value> = Vec::new();
+    let sql = match view_id {
+        Some(id) => {
+            let view = load_view(&conn, id)?;
+            if view.table_name != table_name {
+                return Err(format!("La vista '{}' pertenece a la tabla '{}'", view.name, view.table_name));
+            }
+            let view_query = view_sql(&conn, &table_name, view.filter.as_ref(), &view.sort, &view.columns)?;
+            params = view_query.params;
+
+            // Sin columnas elegidas se exportan todas, salvo las de control de la papelera
+            let hidden_columns: &[&str] = if view_query.soft_delete {
+                &[DELETED_AT_COLUMN, DELETED_BY_COLUMN]
+            } else {
+                &[]
+            };
+            let columns = if view_query.columns.is_empty() {
+                view_query.table_columns.iter().filter(|c| !hidden_columns.contains(&c.as_str())).cloned().collect()
+            } else {
+                view_query.columns
+            };
+            let select_list = columns.iter().map(|c| format!("\"{}\"", c.replace('"', "\"\""))).collect::<Vec<_>>().join(", ");
+            format!(
+                "SELECT {} FROM \"{}\"{}{}",
+                select_list,
+                table_name,
+                view_query.where_sql,
+                view_query.order_sql
+            )
+        }
+        None => format!("SELECT * FROM \"{}\"", table_name),
+    };
+    let mut stmt = conn.prepare(&sql)
         .map_err(|e| e.to_string())?;
 
     let column_names: Vec<String> = stmt.column_names().into_iter().map(|s| s.to_string()).collect();
-    let mut rows = stmt.query([]).map_err(|e| e.to_string())?;
+    let table_codecs = table_codecs(&conn, &table_name)?;
+    let logical_types: Vec<LogicalType> = column_names
+        .iter()
+        .map(|name| {
+            table_codecs.iter().find(|c| &c.name == name).map_or(LogicalType::Any, |c| c.logical)
+        })
+        .collect();
+    let mut rows = stmt.query(rusqlite::params_from_iter(params.iter())).map_err(|e| e.to_string())?;
     let mut data = Vec::new();
 
     // Itera sobre cada fila y la convierte a un objeto JSON.
     while let Some(row) = rows.next().map_err(|e| e.to_string())? {
         let mut row_map = HashMap::new();
         for (i, col_name) in column_names.iter().enumerate() {
-            let value = row.get_ref(i).unwrap().into();
-            row_map.insert(col_name.clone(), rusqlite_to_json(value));
+            let value = match row.get_ref(i).map_err(|e| e.to_string())? {
+                ValueRef::Blob(_) => JsonValue::Null, // Los blobs no se exportan.
+                // Tampoco las referencias al almacén de imágenes (solo en columnas de imagen:
+                // un texto con la misma forma en una columna TEXT se exporta).
+                other if matches!(logical_types[i], LogicalType::Blob | LogicalType::Any) && is_image_ref(other) => JsonValue::Null,
+                other => decode_value(other, logical_types[i]),
+            };
+            row_map.insert(col_name.clone(), value);
         }
         data.push(row_map);
     }
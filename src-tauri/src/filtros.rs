@@ -0,0 +1,207 @@
+/// =========================================================================
+/// Módulo: Filtros y ordenamiento compartidos
+///
+/// Define el árbol de filtros que envía el frontend (condiciones combinadas
+/// con AND / OR / NOT) y su traducción a una cláusula WHERE parametrizada.
+/// Lo usan las vistas guardadas y cualquier comando que reciba un filtro.
+///
+/// Ejemplo de filtro en JSON:
+/// ```json
+/// { "type": "and", "children": [
+///     { "type": "condition", "column": "Estatus", "op": "eq", "value": "En reparación" },
+///     { "type": "condition", "column": "Plantel", "op": "eq", "value": "Centro" }
+/// ] }
+/// ```
+/// =========================================================================
+
+use rusqlite::types::Value as SqlValue;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+fn quote_identifier(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace("\"", "\"\""))
+}
+
+/// Operadores de comparación disponibles en una condición
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    /// Contiene el texto (sin distinguir mayúsculas)
+    Contains,
+    StartsWith,
+    EndsWith,
+    /// El valor es uno de los de la lista
+    In,
+    IsNull,
+    IsNotNull,
+    /// NULL o texto vacío (ej. "sin responsable")
+    IsEmpty,
+    IsNotEmpty,
+}
+
+/// Nodo del árbol de filtros
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FilterNode {
+    And { children: Vec<FilterNode> },
+    Or { children: Vec<FilterNode> },
+    Not { child: Box<FilterNode> },
+    Condition {
+        column: String,
+        op: FilterOp,
+        #[serde(default)]
+        value: Value,
+    },
+}
+
+/// Criterio de ordenamiento
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SortSpec {
+    /// Columna por la que se ordena
+    pub column: String,
+    /// Orden descendente
+    #[serde(default)]
+    pub descending: bool,
+}
+
+/// Convierte un valor JSON de una condición en parámetro SQL
+fn json_param(value: &Value) -> Result<SqlValue, String> {
+    match value {
+        Value::Null => Ok(SqlValue::Null),
+        Value::Bool(b) => Ok(SqlValue::Integer(if *b { 1 } else { 0 })),
+        Value::Number(n) => {
+            if let Some(i) = n.as_i64() {
+                Ok(SqlValue::Integer(i))
+            } else if let Some(f) = n.as_f64() {
+                Ok(SqlValue::Real(f))
+            } else {
+                Err("Número no soportado en el filtro".to_string())
+            }
+        }
+        Value::String(s) => Ok(SqlValue::Text(s.clone())),
+        _ => Err(format!("Valor no soportado en el filtro: {}", value)),
+    }
+}
+
+/// Texto de una condición LIKE, escapando los comodines del usuario
+fn like_pattern(value: &Value, prefix: &str, suffix: &str) -> Result<SqlValue, String> {
+    let text = match value {
+        Value::String(s) => s.clone(),
+        Value::Number(n) => n.to_string(),
+        _ => return Err("Las condiciones de texto requieren un valor de texto".to_string()),
+    };
+    let escaped = text.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+    Ok(SqlValue::Text(format!("{}{}{}", prefix, escaped, suffix)))
+}
+
+impl FilterNode {
+    /// Traduce el árbol a una expresión SQL con parámetros `?`.
+    /// `valid_columns` son las columnas reales de la tabla; cualquier otra se rechaza.
+    pub fn to_sql(&self, valid_columns: &[String], params: &mut Vec<SqlValue>) -> Result<String, String> {
+        match self {
+            FilterNode::And { children } | FilterNode::Or { children } => {
+                if children.is_empty() {
+                    return Ok("1".to_string());
+                }
+                let joiner = if matches!(self, FilterNode::And { .. }) { " AND " } else { " OR " };
+                let parts = children
+                    .iter()
+                    .map(|c| c.to_sql(valid_columns, params))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(format!("({})", parts.join(joiner)))
+            }
+            FilterNode::Not { child } => Ok(format!("NOT ({})", child.to_sql(valid_columns, params)?)),
+            FilterNode::Condition { column, op, value } => {
+                if !valid_columns.iter().any(|c| c == column) {
+                    return Err(format!("La columna '{}' no existe en la tabla", column));
+                }
+                let col = quote_identifier(column);
+
+                let sql = match op {
+                    FilterOp::Eq | FilterOp::Ne | FilterOp::Lt | FilterOp::Le | FilterOp::Gt | FilterOp::Ge => {
+                        let symbol = match op {
+                            FilterOp::Eq => "=",
+                            FilterOp::Ne => "<>",
+                            FilterOp::Lt => "<",
+                            FilterOp::Le => "<=",
+                            FilterOp::Gt => ">",
+                            _ => ">=",
+                        };
+                        params.push(json_param(value)?);
+                        format!("{} {} ?", col, symbol)
+                    }
+                    FilterOp::Contains => {
+                        params.push(like_pattern(value, "%", "%")?);
+                        format!("{} LIKE ? ESCAPE '\\'", col)
+                    }
+                    FilterOp::StartsWith => {
+                        params.push(like_pattern(value, "", "%")?);
+                        format!("{} LIKE ? ESCAPE '\\'", col)
+                    }
+                    FilterOp::EndsWith => {
+                        params.push(like_pattern(value, "%", "")?);
+                        format!("{} LIKE ? ESCAPE '\\'", col)
+                    }
+                    FilterOp::In => {
+                        let items = value.as_array()
+                            .ok_or("El operador 'in' requiere una lista de valores")?;
+                        if items.is_empty() {
+                            return Ok("0".to_string());
+                        }
+                        for item in items {
+                            params.push(json_param(item)?);
+                        }
+                        let placeholders = vec!["?"; items.len()].join(", ");
+                        format!("{} IN ({})", col, placeholders)
+                    }
+                    FilterOp::IsNull => format!("{} IS NULL", col),
+                    FilterOp::IsNotNull => format!("{} IS NOT NULL", col),
+                    FilterOp::IsEmpty => format!("({} IS NULL OR TRIM({}) = '')", col, col),
+                    FilterOp::IsNotEmpty => format!("({} IS NOT NULL AND TRIM({}) <> '')", col, col),
+                };
+                Ok(sql)
+            }
+        }
+    }
+}
+
+/// Construye " WHERE ..." a partir de un filtro opcional (cadena vacía si no hay filtro)
+pub fn where_clause(
+    filter: Option<&FilterNode>,
+    valid_columns: &[String],
+    params: &mut Vec<SqlValue>,
+) -> Result<String, String> {
+    match filter {
+        Some(node) => Ok(format!(" WHERE {}", node.to_sql(valid_columns, params)?)),
+        None => Ok(String::new()),
+    }
+}
+
+/// Construye " ORDER BY ..." a partir de la lista de criterios.
+/// La columna "No." se ordena numéricamente, igual que en `consulta_tabla`.
+pub fn order_clause(sort: &[SortSpec], valid_columns: &[String]) -> Result<String, String> {
+    if sort.is_empty() {
+        return Ok(String::new());
+    }
+    let parts = sort
+        .iter()
+        .map(|s| {
+            if !valid_columns.iter().any(|c| c == &s.column) {
+                return Err(format!("La columna '{}' no existe en la tabla", s.column));
+            }
+            let expr = if s.column == "No." {
+                format!("CAST({} AS INTEGER)", quote_identifier(&s.column))
+            } else {
+                quote_identifier(&s.column)
+            };
+            Ok(format!("{} {}", expr, if s.descending { "DESC" } else { "ASC" }))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    Ok(format!(" ORDER BY {}", parts.join(", ")))
+}
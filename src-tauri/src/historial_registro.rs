@@ -0,0 +1,529 @@
+/// =========================================================================
+/// Módulo: Historial de un registro
+///
+/// Reconstruye las versiones de un registro a partir de la bitácora de
+/// auditoría (`_unea_audit`):
+/// - Cada lote de la bitácora que toca el registro es una versión
+/// - Se parte del registro actual y se recorren los lotes del más reciente
+///   al más antiguo aplicando los valores anteriores, de modo que también
+///   funciona con registros creados antes de que existiera la bitácora
+/// - Se puede comparar dos versiones y restaurar el registro a una de
+///   ellas (o recuperarlo si fue eliminado)
+/// - La bitácora solo guarda un resumen de los BLOB: esas columnas se
+///   comparan por su huella y no se pueden restaurar desde el historial
+/// =========================================================================
+
+use std::collections::{BTreeSet, HashMap};
+
+use rusqlite::types::Value as SqlValue;
+use rusqlite::{Connection, OpenFlags, TransactionBehavior};
+use serde::Serialize;
+use serde_json::Value;
+use tauri::State;
+
+use crate::auditoria::{audit_value, audit_value_to_json, has_audit_table, is_blob_summary, log_row_changes, pk_text, AUDIT_TABLE};
+use crate::busqueda_tabla::primary_key_column;
+use crate::concurrencia::{version_assignments, UPDATED_AT_COLUMN, VERSION_COLUMN};
+use crate::database_manager::AppState;
+use crate::deshacer::{read_row, RowChange, RowImage, UndoState};
+use crate::edicion_masiva::table_columns;
+use crate::codec_valores::json_to_sql;
+
+fn quote_identifier(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace("\"", "\"\""))
+}
+
+/// Cambio de una columna entre dos estados del registro
+#[derive(Debug, Serialize)]
+pub struct FieldChange {
+    pub column: String,
+    pub old_value: Value,
+    pub new_value: Value,
+}
+
+/// Versión del registro tal como se envía al frontend
+#[derive(Debug, Serialize)]
+pub struct RecordVersion {
+    /// Número de versión (1 = la más antigua)
+    pub version: usize,
+    /// 0 en la versión "original"
+    pub batch_id: i64,
+    /// Fecha y hora (RFC 3339); vacía en la versión "original"
+    pub timestamp: String,
+    pub station: Option<String>,
+    pub user_name: Option<String>,
+    /// "insert", "update", "delete", u "original" para el estado anterior a la
+    /// primera entrada de la bitácora (registros creados antes de la bitácora)
+    pub operation: String,
+    /// Comando que produjo el cambio
+    pub context: Option<String>,
+    /// Columnas que cambiaron en esta versión
+    pub changes: Vec<FieldChange>,
+    /// false si en esta versión el registro quedó eliminado
+    pub exists: bool,
+    /// Valores completos del registro en esta versión (las imágenes se resumen)
+    pub values: HashMap<String, Value>,
+}
+
+/// Historial completo de un registro
+#[derive(Debug, Serialize)]
+pub struct RecordHistory {
+    pub table_name: String,
+    pub pk_column: String,
+    pub pk_value: Value,
+    /// Indica si hoy el registro está eliminado
+    pub deleted: bool,
+    /// Versiones de la más antigua a la más reciente
+    pub versions: Vec<RecordVersion>,
+}
+
+/// Versión reconstruida con los valores de la bitácora (los BLOB como resumen)
+struct RawVersion {
+    batch_id: i64,
+    timestamp: String,
+    station: Option<String>,
+    user_name: Option<String>,
+    operation: String,
+    context: Option<String>,
+    /// (columna, valor anterior, valor nuevo)
+    changes: Vec<(String, SqlValue, SqlValue)>,
+    exists: bool,
+    image: RowImage,
+}
+
+impl RawVersion {
+    fn to_json(&self, version: usize) -> RecordVersion {
+        RecordVersion {
+            version,
+            batch_id: self.batch_id,
+            timestamp: self.timestamp.clone(),
+            station: self.station.clone(),
+            user_name: self.user_name.clone(),
+            operation: self.operation.clone(),
+            context: self.context.clone(),
+            changes: self.changes
+                .iter()
+                .map(|(column, old, new)| FieldChange {
+                    column: column.clone(),
+                    old_value: audit_value_to_json(old.clone()),
+                    new_value: audit_value_to_json(new.clone()),
+                })
+                .collect(),
+            exists: self.exists,
+            values: image_to_audit_json(&self.image),
+        }
+    }
+}
+
+/// Fila con los BLOB sustituidos por su resumen, para compararla con la bitácora
+fn summarize_blobs(image: &RowImage) -> RowImage {
+    image.iter()
+        .map(|(column, value)| (column.clone(), audit_value(value)))
+        .collect()
+}
+
+fn image_to_audit_json(image: &RowImage) -> HashMap<String, Value> {
+    image.iter()
+        .map(|(column, value)| (column.clone(), audit_value_to_json(value.clone())))
+        .collect()
+}
+
+fn open_database(state: &AppState, db_name: &str, read_only: bool) -> Result<Connection, String> {
+    let db_path = state.db_dir.join(format!("{}.db", db_name));
+    let sqlite_path = state.db_dir.join(format!("{}.sqlite", db_name));
+
+    let db_file = if db_path.exists() {
+        db_path
+    } else if sqlite_path.exists() {
+        sqlite_path
+    } else {
+        return Err(format!("Base de datos no encontrada: {}", db_name));
+    };
+
+    let result = if read_only {
+        Connection::open_with_flags(&db_file, OpenFlags::SQLITE_OPEN_READ_ONLY)
+    } else {
+        Connection::open(&db_file)
+    };
+    result.map_err(|e| format!("Error al abrir la base de datos: {}", e))
+}
+
+/// Número de cambios (altas, modificaciones y bajas) de un registro en la bitácora
+pub(crate) fn history_change_count(conn: &Connection, table_name: &str, pk_value: &Value) -> Result<usize, String> {
+    if !has_audit_table(conn)? {
+        return Ok(0);
+    }
+    let Some(pk) = pk_text(&json_to_sql(pk_value)?) else {
+        return Ok(0);
+    };
+    let count: i64 = conn.query_row(
+        &format!(
+            "SELECT COUNT(DISTINCT batch_id || ':' || operation) FROM {} WHERE table_name = ? AND pk_value = ?
+             AND operation IN ('insert', 'update', 'delete')",
+            AUDIT_TABLE
+        ),
+        [table_name, pk.as_str()],
+        |row| row.get(0),
+    ).map_err(|e| format!("Error al consultar el historial del registro: {}", e))?;
+    Ok(count as usize)
+}
+
+/// Reconstruye las versiones del registro, de la más antigua a la más reciente
+fn load_versions(
+    conn: &Connection,
+    table_name: &str,
+    pk_column: &str,
+    pk_value: &SqlValue,
+) -> Result<(Option<RowImage>, Vec<RawVersion>), String> {
+    let current = read_row(conn, table_name, pk_column, pk_value)?;
+    if !has_audit_table(conn)? {
+        return Ok((current, Vec::new()));
+    }
+    let pk = pk_text(pk_value)
+        .ok_or_else(|| "La clave primaria del registro no puede ser nula".to_string())?;
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT batch_id, timestamp, station, user_name, operation, column_name, old_value, new_value, context
+         FROM {} WHERE table_name = ? AND pk_value = ? AND operation IN ('insert', 'update', 'delete')
+         ORDER BY id",
+        AUDIT_TABLE
+    )).map_err(|e| format!("Error al preparar la consulta del historial: {}", e))?;
+
+    let entries = stmt.query_map([table_name, pk.as_str()], |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, Option<String>>(2)?,
+            row.get::<_, Option<String>>(3)?,
+            row.get::<_, String>(4)?,
+            row.get::<_, Option<String>>(5)?,
+            row.get::<_, SqlValue>(6)?,
+            row.get::<_, SqlValue>(7)?,
+            row.get::<_, Option<String>>(8)?,
+        ))
+    })
+    .map_err(|e| format!("Error al consultar el historial: {}", e))?
+    .collect::<Result<Vec<_>, _>>()
+    .map_err(|e| format!("Error al leer el historial: {}", e))?;
+
+    // Agrupar entradas consecutivas del mismo lote y la misma operación
+    let mut versions: Vec<RawVersion> = Vec::new();
+    for (batch_id, timestamp, station, user_name, operation, column, old, new, context) in entries {
+        let same = versions.last().map_or(false, |v| v.batch_id == batch_id && v.operation == operation);
+        if !same {
+            versions.push(RawVersion {
+                batch_id,
+                timestamp,
+                station,
+                user_name,
+                operation,
+                context,
+                changes: Vec::new(),
+                exists: true,
+                image: RowImage::new(),
+            });
+        }
+        if let (Some(column), Some(version)) = (column, versions.last_mut()) {
+            version.changes.push((column, old, new));
+        }
+    }
+
+    // Recorrer hacia atrás desde el estado actual
+    let mut exists = current.is_some();
+    let mut image = current.as_ref().map(summarize_blobs).unwrap_or_default();
+    for version in versions.iter_mut().rev() {
+        version.exists = exists;
+        version.image = image.clone();
+        match version.operation.as_str() {
+            "insert" => {
+                exists = false;
+                image = RowImage::new();
+            }
+            operation => {
+                // Antes de una baja el registro existía con los valores anteriores
+                if operation == "delete" {
+                    exists = true;
+                }
+                for (column, old, _) in &version.changes {
+                    image.insert(column.clone(), old.clone());
+                }
+            }
+        }
+    }
+
+    // El registro ya existía antes de su primera entrada en la bitácora
+    if exists && !versions.is_empty() {
+        versions.insert(0, RawVersion {
+            batch_id: 0,
+            timestamp: String::new(),
+            station: None,
+            user_name: None,
+            operation: "original".to_string(),
+            context: None,
+            changes: Vec::new(),
+            exists,
+            image,
+        });
+    }
+
+    Ok((current, versions))
+}
+
+/// Devuelve el historial completo de versiones de un registro
+#[tauri::command]
+pub fn get_record_history(
+    state: State<AppState>,
+    db_name: String,
+    table_name: String,
+    pk_value: Value,
+) -> Result<RecordHistory, String> {
+    let conn = open_database(&state, &db_name, true)?;
+    let pk_column = primary_key_column(&conn, &table_name)?;
+    let (current, versions) = load_versions(&conn, &table_name, &pk_column, &json_to_sql(&pk_value)?)?;
+
+    Ok(RecordHistory {
+        table_name,
+        pk_column,
+        pk_value,
+        deleted: current.is_none(),
+        versions: versions.iter().enumerate().map(|(i, v)| v.to_json(i + 1)).collect(),
+    })
+}
+
+/// Diferencias entre dos versiones de un registro
+#[derive(Debug, Serialize)]
+pub struct VersionDiff {
+    pub from_version: usize,
+    pub to_version: usize,
+    pub from_exists: bool,
+    pub to_exists: bool,
+    /// Columnas con valor distinto (old_value = versión `from`, new_value = versión `to`)
+    pub changes: Vec<FieldChange>,
+}
+
+fn version_at(versions: &[RawVersion], version: usize) -> Result<&RawVersion, String> {
+    if version == 0 || version > versions.len() {
+        return Err(format!(
+            "La versión {} no existe; el registro tiene {} versiones",
+            version,
+            versions.len()
+        ));
+    }
+    Ok(&versions[version - 1])
+}
+
+/// Compara dos versiones cualesquiera de un registro
+#[tauri::command]
+pub fn diff_record_versions(
+    state: State<AppState>,
+    db_name: String,
+    table_name: String,
+    pk_value: Value,
+    from_version: usize,
+    to_version: usize,
+) -> Result<VersionDiff, String> {
+    let conn = open_database(&state, &db_name, true)?;
+    let pk_column = primary_key_column(&conn, &table_name)?;
+    let (_, versions) = load_versions(&conn, &table_name, &pk_column, &json_to_sql(&pk_value)?)?;
+
+    let from = version_at(&versions, from_version)?;
+    let to = version_at(&versions, to_version)?;
+
+    let columns: BTreeSet<&String> = from.image.keys().chain(to.image.keys()).collect();
+    let changes = columns
+        .into_iter()
+        .filter_map(|column| {
+            let old = from.image.get(column).cloned().unwrap_or(SqlValue::Null);
+            let new = to.image.get(column).cloned().unwrap_or(SqlValue::Null);
+            (old != new).then(|| FieldChange {
+                column: column.clone(),
+                old_value: audit_value_to_json(old),
+                new_value: audit_value_to_json(new),
+            })
+        })
+        .collect();
+
+    Ok(VersionDiff {
+        from_version,
+        to_version,
+        from_exists: from.exists,
+        to_exists: to.exists,
+        changes,
+    })
+}
+
+/// Resultado de restaurar un registro
+#[derive(Debug, Serialize)]
+pub struct RestoreResult {
+    /// Versión restaurada
+    pub version: usize,
+    /// Indica si el registro estaba eliminado y se volvió a crear
+    pub undeleted: bool,
+    /// Valores del registro después de restaurarlo (las imágenes se resumen)
+    pub values: HashMap<String, Value>,
+    /// Columnas BLOB que no se restauraron porque la bitácora solo guarda su resumen
+    pub skipped_columns: Vec<String>,
+}
+
+/// Escribe los valores de una versión en el registro. Sin `version` se recupera
+/// un registro eliminado con la última versión en la que existía.
+fn restore_version(
+    state: &AppState,
+    undo_state: &UndoState,
+    db_name: &str,
+    table_name: &str,
+    pk_value: &Value,
+    version: Option<usize>,
+    context: &str,
+) -> Result<RestoreResult, String> {
+    let mut conn = open_database(state, db_name, false)?;
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)
+        .map_err(|e| format!("Error al iniciar la transacción: {}", e))?;
+
+    let pk_column = primary_key_column(&tx, table_name)?;
+    let pk_sql = json_to_sql(pk_value)?;
+    let (current, versions) = load_versions(&tx, table_name, &pk_column, &pk_sql)?;
+    if version.is_none() && current.is_some() {
+        return Err("El registro no está eliminado".to_string());
+    }
+
+    let version = match version {
+        Some(v) => v,
+        None => versions
+            .iter()
+            .rposition(|v| v.exists)
+            .map(|i| i + 1)
+            .ok_or_else(|| "El registro no tiene ninguna versión que se pueda restaurar".to_string())?,
+    };
+    let target = version_at(&versions, version)?;
+    if !target.exists {
+        return Err(format!(
+            "En la versión {} el registro está eliminado; elija una versión anterior",
+            version
+        ));
+    }
+
+    // Solo columnas que siguen existiendo en la tabla
+    let mut valid_columns: Vec<String> = table_columns(&tx, table_name)?
+        .into_iter()
+        .map(|c| c.name)
+        .collect();
+    if pk_column == "rowid" {
+        valid_columns.push("rowid".to_string());
+    }
+    let table = quote_identifier(table_name);
+
+    // Los BLOB resumidos en la bitácora no se pueden reconstruir: se dejan como
+    // están, y solo se informan si difieren de la versión pedida
+    let mut skipped_columns: Vec<String> = Vec::new();
+    let mut restorable = |column: &String| -> bool {
+        let value = &target.image[column];
+        if !is_blob_summary(value) {
+            return true;
+        }
+        let unchanged = current
+            .as_ref()
+            .and_then(|row| row.get(column))
+            .map_or(false, |current_value| audit_value(current_value) == *value);
+        if !unchanged {
+            skipped_columns.push(column.clone());
+        }
+        false
+    };
+
+    match &current {
+        Some(_) => {
+            // `version` y `updated_at` no retroceden: marcan una modificación nueva
+            let columns: Vec<&String> = target.image
+                .keys()
+                .filter(|c| valid_columns.contains(c) && *c != VERSION_COLUMN && *c != UPDATED_AT_COLUMN)
+                .filter(|c| restorable(c))
+                .collect();
+            if !columns.is_empty() {
+                let mut set_clause: Vec<String> = columns.iter()
+                    .map(|c| format!("{} = ?", quote_identifier(c)))
+                    .collect();
+                let assigned: Vec<String> = columns.iter().map(|c| c.to_string()).collect();
+                set_clause.extend(version_assignments(&valid_columns, &assigned));
+                let sql = format!(
+                    "UPDATE {} SET {} WHERE {} = ?",
+                    table,
+                    set_clause.join(", "),
+                    quote_identifier(&pk_column)
+                );
+                let mut params: Vec<&SqlValue> = columns.iter().map(|c| &target.image[*c]).collect();
+                params.push(&pk_sql);
+                tx.execute(&sql, rusqlite::params_from_iter(params))
+                    .map_err(|e| format!("Error al restaurar el registro: {}", e))?;
+            }
+        }
+        None => {
+            let columns: Vec<&String> = target.image
+                .keys()
+                .filter(|c| valid_columns.contains(c))
+                .filter(|c| restorable(c))
+                .collect();
+            let sql = format!(
+                "INSERT INTO {} ({}) VALUES ({})",
+                table,
+                columns.iter().map(|c| quote_identifier(c)).collect::<Vec<_>>().join(", "),
+                vec!["?"; columns.len()].join(", ")
+            );
+            tx.execute(&sql, rusqlite::params_from_iter(columns.iter().map(|c| &target.image[*c])))
+                .map_err(|e| format!("Error al recuperar el registro: {}", e))?;
+        }
+    }
+
+    let after = read_row(&tx, table_name, &pk_column, &pk_sql)?;
+    let change = RowChange {
+        table_name: table_name.to_string(),
+        pk_column,
+        before: current.clone(),
+        after: after.clone(),
+    };
+    log_row_changes(&tx, context, std::slice::from_ref(&change))?;
+
+    tx.commit()
+        .map_err(|e| format!("Error al guardar los cambios: {}", e))?;
+
+    let undeleted = current.is_none();
+    let description = if undeleted {
+        format!("Recuperar registro eliminado de {}", table_name)
+    } else {
+        format!("Restaurar registro de {} a la versión {}", table_name, version)
+    };
+    undo_state.record(db_name, description, vec![change]);
+
+    Ok(RestoreResult {
+        version,
+        undeleted,
+        values: after.as_ref().map(image_to_audit_json).unwrap_or_default(),
+        skipped_columns,
+    })
+}
+
+/// Restaura un registro a una versión de su historial.
+/// Si el registro está eliminado, se vuelve a crear con los valores de esa versión.
+#[tauri::command]
+pub fn restore_record_version(
+    state: State<AppState>,
+    undo_state: State<UndoState>,
+    db_name: String,
+    table_name: String,
+    pk_value: Value,
+    version: usize,
+) -> Result<RestoreResult, String> {
+    restore_version(&state, &undo_state, &db_name, &table_name, &pk_value, Some(version), "restore_record_version")
+}
+
+/// Recupera un registro eliminado con los valores que tenía antes de su baja
+#[tauri::command]
+pub fn undelete_record(
+    state: State<AppState>,
+    undo_state: State<UndoState>,
+    db_name: String,
+    table_name: String,
+    pk_value: Value,
+) -> Result<RestoreResult, String> {
+    restore_version(&state, &undo_state, &db_name, &table_name, &pk_value, None, "undelete_record")
+}
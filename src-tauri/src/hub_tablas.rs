@@ -27,6 +27,35 @@ fn normalize_path(path: PathBuf) -> String {
     }
 }
 
+// Tablas internas que guardan configuración o datos ligados a una tabla por su nombre
+const TABLE_CONFIG_TABLES: [&str; 6] = [
+    crate::papelera::SOFT_DELETE_TABLE,
+    crate::secuencias::SEQUENCES_TABLE,
+    crate::valores_predeterminados::DEFAULTS_TABLE,
+    crate::plantillas_registro::PRESETS_TABLE,
+    crate::vistas_guardadas::VIEWS_TABLE,
+    crate::adjuntos::ATTACHMENTS_TABLE,
+];
+
+// Borra la configuración interna de una tabla que se elimina o se reemplaza,
+// para que una tabla nueva con el mismo nombre no herede papelera, secuencias,
+// valores predeterminados, plantillas, vistas ni adjuntos de la anterior
+pub(crate) fn forget_table_config(conn: &rusqlite::Connection, table_name: &str) -> Result<(), String> {
+    for internal_table in TABLE_CONFIG_TABLES {
+        let exists: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name=?",
+            [internal_table],
+            |row| row.get(0),
+        ).map_err(|e| format!("Error al consultar la configuración de la tabla: {}", e))?;
+        if exists == 0 {
+            continue;
+        }
+        conn.execute(&format!("DELETE FROM \"{}\" WHERE table_name = ?", internal_table), [table_name])
+            .map_err(|e| format!("Error al eliminar la configuración de la tabla: {}", e))?;
+    }
+    Ok(())
+}
+
 // Función auxiliar para obtener la ruta de la carpeta de imágenes
 fn get_images_dir(state: &State<AppState>, db_name: &str) -> PathBuf {
     state.db_dir.join("images").join(db_name)
@@ -83,6 +112,8 @@ pub fn create_table(state: State<AppState>, db_name: String, table_name: String)
     conn.execute(&sql, [])
         .map_err(|e| format!("Error al crear la tabla: {}", e))?;
 
+    crate::auditoria::log_operation(&conn, "create_table", Some(&table_name), None, Some(&sql))?;
+
     Ok(())
 }
 
@@ -103,7 +134,7 @@ pub fn list_tables(state: State<AppState>, db_name: String) -> Result<Vec<TableI
         .map_err(|e| format!("Error al abrir la base de datos: {}", e))?;
 
     let mut stmt = conn
-        .prepare("SELECT name FROM sqlite_master WHERE type='table' AND name NOT LIKE 'sqlite_%'")
+        .prepare("SELECT name FROM sqlite_master WHERE type='table' AND name NOT LIKE 'sqlite_%' AND name NOT LIKE '\\_unea\\_%' ESCAPE '\\'")
         .map_err(|e| format!("Error al preparar la consulta: {}", e))?;
 
     let table_names = stmt
@@ -144,9 +175,16 @@ pub fn delete_table(
     let conn = rusqlite::Connection::open(&db_file)
         .map_err(|e| format!("Error al abrir la base de datos: {}", e))?;
 
+    // Eliminar primero el índice de búsqueda asociado, si existe
+    crate::busqueda_tabla::drop_search_index(&conn, &table_name)?;
+    crate::almacen_imagenes::forget_store_columns(&conn, &table_name, None)?;
+    forget_table_config(&conn, &table_name)?;
+
     conn.execute(&format!("DROP TABLE IF EXISTS \"{}\"", table_name), [])
         .map_err(|e| format!("Error al eliminar la tabla: {}", e))?;
 
+    crate::auditoria::log_operation(&conn, "drop_table", Some(&table_name), None, None)?;
+
     // Eliminar imagen asociada si existe
     let images_dir = get_images_dir(&state, &db_name);
     let extensions = ["jpg", "jpeg", "png", "gif", "webp"];
@@ -0,0 +1,483 @@
+/// =========================================================================
+/// Módulo: Importación masiva de imágenes por nombre de archivo
+///
+/// Después de fotografiar los equipos se tiene una carpeta con archivos como
+/// `SN12345.jpg` o `SN12345_2.jpg`. Este módulo asigna cada foto al registro
+/// cuya columna clave (Serie, No., ID...) coincide con el nombre del archivo:
+/// - Los patrones indican cómo se forma el nombre: `{key}` es el valor de la
+///   columna clave y `{n}` cualquier número (ej. `{key}_{n}` para `SN12345_2`).
+///   La comparación no distingue mayúsculas
+/// - Cada imagen se procesa (rotación, tamaño, calidad) y se guarda en la
+///   columna de imagen elegida, o en el almacén por contenido si la columna
+///   lo usa
+/// - El resultado separa las imágenes asignadas, las que no coinciden con
+///   ningún registro y las ambiguas (varios registros con la misma clave, o
+///   varias fotos para el mismo registro)
+/// - `dry_run` devuelve el mismo informe sin modificar la base de datos
+///
+/// Las imágenes se procesan por tandas fuera de la transacción, y cada tanda
+/// se guarda en una transacción corta para no bloquear a las demás estaciones.
+/// Toda la importación se deshace como una sola operación, salvo que sus
+/// imágenes superen el límite del diario de deshacer. Los registros en la
+/// papelera no se consideran.
+/// =========================================================================
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+use regex::Regex;
+use rusqlite::types::Value as SqlValue;
+use rusqlite::{Connection, TransactionBehavior};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::State;
+
+use crate::auditoria::{log_row_changes, pk_text};
+use crate::busqueda_tabla::primary_key_column;
+use crate::codec_valores::{find_codec, sql_to_json, table_codecs, LogicalType};
+use crate::database_manager::AppState;
+use crate::deshacer::{RowChange, UndoState};
+use crate::papelera::active_rows_condition;
+use crate::procesar_imagenes::{process_image_file, write_record_image, ImageOptions, ProcessedImage};
+use crate::protocolo_imagenes::sniff_image_mime;
+
+/// Extensiones de los archivos que se revisan; el resto de la carpeta se ignora
+const IMAGE_EXTENSIONS: [&str; 7] = ["jpg", "jpeg", "png", "gif", "bmp", "webp", "jfif"];
+/// Imágenes que se procesan antes de guardarlas en una transacción
+const BATCH_SIZE: usize = 16;
+/// Bytes de imagen que se conservan en el diario de deshacer (128 MB); una
+/// importación mayor no se puede deshacer
+const MAX_UNDO_BYTES: usize = 128 * 1024 * 1024;
+
+fn quote_identifier(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace("\"", "\"\""))
+}
+
+fn default_patterns() -> Vec<String> {
+    vec!["{key}".to_string(), "{key}_{n}".to_string(), "{key}-{n}".to_string()]
+}
+
+/// Petición de importación
+#[derive(Debug, Deserialize)]
+pub struct BulkImageImportRequest {
+    pub table_name: String,
+    /// Carpeta con las fotos (no se revisan subcarpetas)
+    pub folder: String,
+    /// Columna cuyo valor aparece en el nombre del archivo
+    pub key_column: String,
+    /// Columna donde se guarda la imagen
+    pub image_column: String,
+    /// Patrones del nombre del archivo (sin extensión), en orden de preferencia
+    #[serde(default = "default_patterns")]
+    pub patterns: Vec<String>,
+    /// Solo genera el informe, sin guardar nada
+    #[serde(default)]
+    pub dry_run: bool,
+    /// Opciones de procesamiento de las imágenes
+    #[serde(default)]
+    pub options: ImageOptions,
+}
+
+/// Imagen asignada a un registro
+#[derive(Debug, Serialize)]
+pub struct MatchedImage {
+    pub file_name: String,
+    /// Valor de la columna clave
+    pub key: String,
+    pub pk_value: Value,
+    /// El registro ya tenía una imagen en la columna
+    pub replaces_existing: bool,
+}
+
+/// Imagen que no se asignó porque hay más de una posibilidad
+#[derive(Debug, Serialize)]
+pub struct AmbiguousImage {
+    pub file_name: String,
+    pub reason: String,
+    /// Claves primarias de los registros posibles
+    pub candidates: Vec<Value>,
+}
+
+/// Imagen que no se pudo leer o procesar
+#[derive(Debug, Serialize)]
+pub struct FailedImage {
+    pub file_name: String,
+    pub error: String,
+}
+
+/// Informe de la importación
+#[derive(Debug, Serialize)]
+pub struct BulkImageImportReport {
+    pub dry_run: bool,
+    pub pk_column: String,
+    pub matched: Vec<MatchedImage>,
+    /// Archivos cuyo nombre no coincide con ningún registro
+    pub unmatched: Vec<String>,
+    pub ambiguous: Vec<AmbiguousImage>,
+    pub failed: Vec<FailedImage>,
+    /// Archivos de la carpeta que no son imágenes
+    pub ignored_files: usize,
+    /// false si las imágenes superan el límite del diario y la importación no se puede deshacer
+    pub undoable: bool,
+}
+
+/// Convierte un patrón como `{key}_{n}` en una expresión regular que captura la clave
+fn pattern_regex(pattern: &str) -> Result<Regex, String> {
+    if pattern.matches("{key}").count() != 1 {
+        return Err(format!("El patrón '{}' debe incluir {{key}} una vez", pattern));
+    }
+    let mut regex = String::from("(?i)^");
+    for (i, part) in pattern.split("{key}").enumerate() {
+        if i > 0 {
+            regex.push_str("(?P<key>.+?)");
+        }
+        let literals: Vec<String> = part.split("{n}").map(regex::escape).collect();
+        regex.push_str(&literals.join(r"\d+"));
+    }
+    regex.push('$');
+    Regex::new(&regex).map_err(|e| format!("Patrón inválido '{}': {}", pattern, e))
+}
+
+/// Forma en que se comparan las claves y los nombres de archivo
+fn normalize_key(key: &str) -> String {
+    key.trim().to_lowercase()
+}
+
+/// Registros activos de la tabla agrupados por el valor de la columna clave
+struct KeyIndex {
+    /// Clave normalizada → (clave primaria, valor de la clave, tiene imagen)
+    records: HashMap<String, Vec<(SqlValue, String, bool)>>,
+}
+
+impl KeyIndex {
+    fn load(conn: &Connection, table_name: &str, pk_column: &str, key_column: &str, image_column: &str) -> Result<Self, String> {
+        let mut sql = format!(
+            "SELECT {}, {}, {} IS NOT NULL FROM {}",
+            quote_identifier(pk_column),
+            quote_identifier(key_column),
+            quote_identifier(image_column),
+            quote_identifier(table_name)
+        );
+        if let Some(condition) = active_rows_condition(conn, table_name)? {
+            sql.push_str(&format!(" WHERE {}", condition));
+        }
+        let mut stmt = conn.prepare(&sql)
+            .map_err(|e| format!("Error al preparar la lectura de los registros: {}", e))?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, SqlValue>(0)?, row.get::<_, SqlValue>(1)?, row.get::<_, bool>(2)?))
+        })
+        .map_err(|e| format!("Error al leer los registros: {}", e))?;
+
+        let mut records: HashMap<String, Vec<(SqlValue, String, bool)>> = HashMap::new();
+        for row in rows {
+            let (pk, key, has_image) = row.map_err(|e| format!("Error al leer los registros: {}", e))?;
+            let Some(key) = pk_text(&key) else {
+                continue;
+            };
+            let normalized = normalize_key(&key);
+            if !normalized.is_empty() {
+                records.entry(normalized).or_default().push((pk, key.trim().to_string(), has_image));
+            }
+        }
+        Ok(KeyIndex { records })
+    }
+}
+
+/// Resultado de comparar un archivo con las claves
+enum FileMatch {
+    Unmatched,
+    Ambiguous(String, Vec<Value>),
+    Matched { key: String, pk: SqlValue, has_image: bool, pattern: usize },
+}
+
+/// Foto asignada a un registro, antes de elegir una por registro
+struct Candidate {
+    pattern: usize,
+    file_name: String,
+    key: String,
+    pk: SqlValue,
+    has_image: bool,
+}
+
+fn match_file(stem: &str, patterns: &[Regex], index: &KeyIndex, key_column: &str) -> FileMatch {
+    // Claves existentes que produce cada patrón, en orden de preferencia
+    let mut keys: Vec<(usize, String)> = Vec::new();
+    for (i, regex) in patterns.iter().enumerate() {
+        let Some(key) = regex.captures(stem).and_then(|c| c.name("key")).map(|k| normalize_key(k.as_str())) else {
+            continue;
+        };
+        if index.records.contains_key(&key) && !keys.iter().any(|(_, k)| k == &key) {
+            keys.push((i, key));
+        }
+    }
+
+    match keys.as_slice() {
+        [] => FileMatch::Unmatched,
+        [(pattern, key)] => {
+            let records = &index.records[key];
+            if records.len() > 1 {
+                return FileMatch::Ambiguous(
+                    format!("Hay {} registros con {} = '{}'", records.len(), key_column, records[0].1),
+                    records.iter().map(|(pk, _, _)| sql_to_json(pk)).collect(),
+                );
+            }
+            let (pk, key, has_image) = records[0].clone();
+            FileMatch::Matched { key, pk, has_image, pattern: *pattern }
+        }
+        several => FileMatch::Ambiguous(
+            format!(
+                "El nombre coincide con varias claves: {}",
+                several.iter().map(|(_, k)| format!("'{}'", index.records[k][0].1)).collect::<Vec<_>>().join(", ")
+            ),
+            several
+                .iter()
+                .flat_map(|(_, k)| index.records[k].iter().map(|(pk, _, _)| sql_to_json(pk)))
+                .collect(),
+        ),
+    }
+}
+
+/// Comprueba por los primeros bytes que el archivo sea una imagen compatible
+fn check_magic_bytes(path: &Path) -> Result<(), String> {
+    let mut header = [0u8; 16];
+    let read = fs::File::open(path)
+        .and_then(|mut file| file.read(&mut header))
+        .map_err(|e| format!("Error al leer el archivo: {}", e))?;
+    sniff_image_mime(&header[..read])
+        .map(|_| ())
+        .ok_or_else(|| "El archivo no es una imagen compatible (PNG, JPEG, GIF, WebP o BMP)".to_string())
+}
+
+/// Bytes de BLOB que ocupa un cambio en el diario de deshacer
+fn change_bytes(change: &RowChange) -> usize {
+    change.before
+        .iter()
+        .chain(change.after.iter())
+        .flat_map(|image| image.values())
+        .map(|value| match value {
+            SqlValue::Blob(bytes) => bytes.len(),
+            _ => 0,
+        })
+        .sum()
+}
+
+/// Guarda una tanda de imágenes ya procesadas en una transacción corta
+fn write_batch(
+    conn: &mut Connection,
+    request: &BulkImageImportRequest,
+    pk_column: &str,
+    batch: Vec<(Candidate, Result<ProcessedImage, String>)>,
+    report: &mut BulkImageImportReport,
+) -> Result<Vec<RowChange>, String> {
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)
+        .map_err(|e| format!("Error al iniciar la transacción: {}", e))?;
+
+    let mut changes = Vec::new();
+    let mut matched = Vec::new();
+    let mut failed = Vec::new();
+    for (Candidate { file_name, key, pk, has_image, .. }, image) in batch {
+        let result = image.and_then(|image| {
+            write_record_image(
+                &tx,
+                &request.table_name,
+                pk_column,
+                &pk,
+                &request.image_column,
+                &image,
+                request.options.thumbnail_column.as_deref(),
+            )
+        });
+        match result {
+            Ok(change) => {
+                changes.push(change);
+                matched.push(MatchedImage {
+                    file_name,
+                    key,
+                    pk_value: sql_to_json(&pk),
+                    replaces_existing: has_image,
+                });
+            }
+            Err(error) => failed.push(FailedImage { file_name, error }),
+        }
+    }
+
+    log_row_changes(&tx, "import_images_by_filename", &changes)?;
+    tx.commit()
+        .map_err(|e| format!("Error al guardar los cambios: {}", e))?;
+
+    // El informe solo refleja lo que quedó guardado
+    report.matched.extend(matched);
+    report.failed.extend(failed);
+    Ok(changes)
+}
+
+/// Asigna las fotos de una carpeta a los registros por su nombre de archivo
+/// y las guarda en la columna de imagen (o solo informa, con `dry_run`)
+#[tauri::command]
+pub fn import_images_by_filename(
+    state: State<AppState>,
+    undo_state: State<UndoState>,
+    db_name: String,
+    request: BulkImageImportRequest,
+) -> Result<BulkImageImportReport, String> {
+    let patterns = if request.patterns.is_empty() { default_patterns() } else { request.patterns.clone() };
+    let patterns = patterns.iter().map(|p| pattern_regex(p)).collect::<Result<Vec<_>, _>>()?;
+
+    let folder = Path::new(&request.folder);
+    if !folder.is_dir() {
+        return Err(format!("No se encontró la carpeta: {}", request.folder));
+    }
+    let mut files: Vec<(String, String)> = Vec::new();
+    let mut ignored_files = 0;
+    for entry in fs::read_dir(folder).map_err(|e| format!("Error al leer la carpeta: {}", e))? {
+        let path = entry.map_err(|e| format!("Error al leer la carpeta: {}", e))?.path();
+        if !path.is_file() {
+            continue;
+        }
+        let extension = path.extension().and_then(|e| e.to_str()).map(|e| e.to_lowercase());
+        match (path.file_name().and_then(|n| n.to_str()), path.file_stem().and_then(|s| s.to_str())) {
+            (Some(name), Some(stem)) if extension.map_or(false, |e| IMAGE_EXTENSIONS.contains(&e.as_str())) => {
+                files.push((name.to_string(), stem.to_string()));
+            }
+            _ => ignored_files += 1,
+        }
+    }
+    files.sort_by_key(|(name, _)| name.to_lowercase());
+
+    let db_path = state.db_dir.join(format!("{}.db", db_name));
+    let sqlite_path = state.db_dir.join(format!("{}.sqlite", db_name));
+
+    let db_file = if db_path.exists() {
+        db_path
+    } else if sqlite_path.exists() {
+        sqlite_path
+    } else {
+        return Err(format!("Base de datos no encontrada: {}", db_name));
+    };
+
+    let mut conn = Connection::open(&db_file)
+        .map_err(|e| format!("Error al abrir la base de datos: {}", e))?;
+
+    let codecs = table_codecs(&conn, &request.table_name)?;
+    find_codec(&codecs, &request.key_column)?;
+    for column in std::iter::once(&request.image_column).chain(request.options.thumbnail_column.as_ref()) {
+        if !matches!(find_codec(&codecs, column)?.logical, LogicalType::Blob | LogicalType::Any) {
+            return Err(format!("La columna '{}' no es de tipo imagen (BLOB)", column));
+        }
+    }
+    let pk_column = primary_key_column(&conn, &request.table_name)?;
+    let index = KeyIndex::load(&conn, &request.table_name, &pk_column, &request.key_column, &request.image_column)?;
+
+    let mut report = BulkImageImportReport {
+        dry_run: request.dry_run,
+        pk_column: pk_column.clone(),
+        matched: Vec::new(),
+        unmatched: Vec::new(),
+        ambiguous: Vec::new(),
+        failed: Vec::new(),
+        ignored_files,
+        undoable: true,
+    };
+
+    // Fotos por registro: se queda la del patrón preferido y, a igual patrón, la primera por nombre
+    let mut by_record: HashMap<String, Vec<Candidate>> = HashMap::new();
+    for (file_name, stem) in &files {
+        match match_file(stem, &patterns, &index, &request.key_column) {
+            FileMatch::Unmatched => report.unmatched.push(file_name.clone()),
+            FileMatch::Ambiguous(reason, candidates) => {
+                report.ambiguous.push(AmbiguousImage { file_name: file_name.clone(), reason, candidates });
+            }
+            FileMatch::Matched { key, pk, has_image, pattern } => {
+                let record = pk_text(&pk).unwrap_or_default();
+                by_record.entry(record).or_default().push(Candidate {
+                    pattern,
+                    file_name: file_name.clone(),
+                    key,
+                    pk,
+                    has_image,
+                });
+            }
+        }
+    }
+
+    let mut selected = Vec::new();
+    for (_, mut candidates) in by_record {
+        candidates.sort_by_key(|c| (c.pattern, c.file_name.to_lowercase()));
+        let mut candidates = candidates.into_iter();
+        let Some(first) = candidates.next() else {
+            continue;
+        };
+        for other in candidates {
+            report.ambiguous.push(AmbiguousImage {
+                file_name: other.file_name,
+                reason: format!("El registro ya recibe la imagen '{}'", first.file_name),
+                candidates: vec![sql_to_json(&other.pk)],
+            });
+        }
+        selected.push(first);
+    }
+    selected.sort_by_key(|c| c.file_name.to_lowercase());
+
+    if request.dry_run {
+        for Candidate { file_name, key, pk, has_image, .. } in selected {
+            match check_magic_bytes(&folder.join(&file_name)) {
+                Ok(()) => report.matched.push(MatchedImage {
+                    file_name,
+                    key,
+                    pk_value: sql_to_json(&pk),
+                    replaces_existing: has_image,
+                }),
+                Err(error) => report.failed.push(FailedImage { file_name, error }),
+            }
+        }
+        report.ambiguous.sort_by_key(|a| a.file_name.to_lowercase());
+        return Ok(report);
+    }
+
+    // Redimensionar y recodificar es lo lento: se hace sin transacción y solo
+    // la escritura de cada tanda bloquea la base de datos
+    let mut changes = Vec::new();
+    let mut error = None;
+    let mut pending = selected.into_iter().peekable();
+    while pending.peek().is_some() {
+        let batch: Vec<(Candidate, Result<ProcessedImage, String>)> = pending
+            .by_ref()
+            .take(BATCH_SIZE)
+            .map(|candidate| {
+                let path = folder.join(&candidate.file_name);
+                let image = process_image_file(&path.to_string_lossy(), &request.options).map(|(image, _)| image);
+                (candidate, image)
+            })
+            .collect();
+        match write_batch(&mut conn, &request, &pk_column, batch, &mut report) {
+            Ok(batch_changes) => changes.extend(batch_changes),
+            Err(e) => {
+                error = Some(e);
+                break;
+            }
+        }
+    }
+    report.ambiguous.sort_by_key(|a| a.file_name.to_lowercase());
+    report.matched.sort_by_key(|m| m.file_name.to_lowercase());
+    report.failed.sort_by_key(|f| f.file_name.to_lowercase());
+
+    // Las tandas ya guardadas se deshacen juntas aunque una posterior falle
+    let saved = changes.len();
+    if changes.iter().map(change_bytes).sum::<usize>() > MAX_UNDO_BYTES {
+        report.undoable = false;
+    } else {
+        undo_state.record(
+            &db_name,
+            format!("Importar {} imágenes en {}", saved, request.table_name),
+            changes,
+        );
+    }
+
+    match error {
+        Some(e) if saved > 0 => Err(format!("{} ({} imágenes ya se guardaron)", e, saved)),
+        Some(e) => Err(e),
+        None => Ok(report),
+    }
+}
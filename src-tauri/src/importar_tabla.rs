@@ -1,9 +1,48 @@
+use rusqlite::types::Value as SqlValue;
 use rusqlite::{Connection, ToSql};
+use serde::Serialize;
+use serde_json::Value as JsonValue;
 use tauri::State;
 use regex::Regex;
 
 use crate::database_manager::AppState;
-use crate::io_utils::{TableExport, json_to_rusqlite};
+use crate::codec_valores::{find_codec, json_to_sql, table_codecs, ColumnCodec};
+use crate::io_utils::TableExport;
+
+/// Celda que no cumple el tipo de su columna y se guardó tal cual
+/// (ej. "N/A" en una columna DATE)
+#[derive(Debug, Serialize)]
+pub struct CoercedCell {
+    /// Posición de la fila en el archivo (desde 1)
+    pub row: usize,
+    pub column: String,
+    pub value: JsonValue,
+    /// Motivo por el que no se pudo convertir
+    pub reason: String,
+}
+
+/// Resultado de la importación
+#[derive(Debug, Serialize)]
+pub struct ImportResult {
+    pub table_name: String,
+    pub imported_rows: usize,
+    /// Celdas guardadas sin convertir
+    pub coerced_cells: Vec<CoercedCell>,
+}
+
+// Convierte una celda según su columna. Los datos antiguos pueden no cumplir
+// el tipo ("" o "N/A" en columnas INTEGER, DATE o NOT NULL): en ese caso se
+// guarda el valor tal cual y se informa la celda en lugar de rechazar el archivo.
+fn encode_cell(codec: &ColumnCodec, value: &JsonValue) -> Result<(SqlValue, Option<String>), String> {
+    match codec.encode(value) {
+        Ok(SqlValue::Null) if codec.notnull && !value.is_null() => Ok((
+            json_to_sql(value)?,
+            Some(format!("Columna '{}': la columna no admite valores vacíos", codec.name)),
+        )),
+        Ok(encoded) => Ok((encoded, None)),
+        Err(e) => Ok((json_to_sql(value)?, Some(e))),
+    }
+}
 
 // Importa una tabla desde un string JSON.
 #[tauri::command]
@@ -11,7 +50,7 @@ pub fn import_table_from_json(
     state: State<AppState>,
     db_name: String,
     json_content: String,
-) -> Result<bool, String> {
+) -> Result<ImportResult, String> {
     import_table_from_json_internal(state, db_name, json_content, false, None)
 }
 
@@ -23,7 +62,7 @@ pub fn import_table_from_json_with_options(
     json_content: String,
     force_replace: bool,
     new_table_name: Option<String>,
-) -> Result<bool, String> {
+) -> Result<ImportResult, String> {
     import_table_from_json_internal(state, db_name, json_content, force_replace, new_table_name)
 }
 
@@ -33,7 +72,7 @@ fn import_table_from_json_internal(
     json_content: String,
     force_replace: bool,
     new_table_name: Option<String>,
-) -> Result<bool, String> {
+) -> Result<ImportResult, String> {
     // Verificar que la base de datos existe antes de proceder
     let db_path = state.db_dir.join(format!("{}.db", db_name));
     let sqlite_path = state.db_dir.join(format!("{}.sqlite", db_name));
@@ -93,6 +132,9 @@ fn import_table_from_json_internal(
 
     // Elimina la tabla si ya existe y se fuerza reemplazo
     if force_replace {
+        crate::busqueda_tabla::drop_search_index(&tx, &import_data.table_name)?;
+        crate::almacen_imagenes::forget_store_columns(&tx, &import_data.table_name, None)?;
+        crate::hub_tablas::forget_table_config(&tx, &import_data.table_name)?;
         tx.execute(&format!("DROP TABLE IF EXISTS \"{}\"", import_data.table_name), [])
             .map_err(|e| e.to_string())?;
     }
@@ -102,31 +144,64 @@ fn import_table_from_json_internal(
         .map_err(|e| format!("Error al crear tabla: {}", e))?;
 
     // Inserta los datos si existen
+    let mut coerced_cells = Vec::new();
     if !import_data.data.is_empty() {
         let first_row = &import_data.data[0];
         let columns: Vec<String> = first_row.keys().cloned().collect();
         let column_list = columns.iter().map(|c| format!("\"{}\"", c)).collect::<Vec<_>>().join(", ");
         let value_placeholders = columns.iter().map(|_| "?").collect::<Vec<_>>().join(", ");
 
+        // Los valores se convierten según el tipo declarado en la tabla creada
+        let table_codecs = table_codecs(&tx, &import_data.table_name)?;
+        let codecs = columns
+            .iter()
+            .map(|col| find_codec(&table_codecs, col))
+            .collect::<Result<Vec<_>, _>>()?;
+
         let insert_sql = format!(
             "INSERT INTO \"{}\" ({}) VALUES ({})",
             import_data.table_name, column_list, value_placeholders
         );
 
-        for row_map in &import_data.data {
-            let params: Result<Vec<_>, _> = columns
-                .iter()
-                .map(|col| json_to_rusqlite(row_map.get(col).unwrap()))
-                .collect();
-            let params = params?;
+        for (index, row_map) in import_data.data.iter().enumerate() {
+            let mut params = Vec::with_capacity(columns.len());
+            for (col, codec) in columns.iter().zip(&codecs) {
+                let value = row_map.get(col).unwrap_or(&JsonValue::Null);
+                let (encoded, reason) = encode_cell(codec, value)?;
+                if let Some(reason) = reason {
+                    coerced_cells.push(CoercedCell {
+                        row: index + 1,
+                        column: col.clone(),
+                        value: value.clone(),
+                        reason,
+                    });
+                }
+                params.push(encoded);
+            }
             let params_refs: Vec<&dyn ToSql> = params.iter().map(|v| v as &dyn ToSql).collect();
             tx.execute(&insert_sql, &params_refs[..])
                 .map_err(|e| format!("Error insertando fila: {}", e))?;
         }
     }
 
+    crate::auditoria::log_operation(
+        &tx,
+        "import_table",
+        Some(&import_data.table_name),
+        None,
+        Some(&format!(
+            "{} filas importadas, {} celdas sin convertir",
+            import_data.data.len(),
+            coerced_cells.len()
+        )),
+    )?;
+
     // Confirma la transacción
     tx.commit().map_err(|e| format!("Error al confirmar transacción: {}", e))?;
 
-    Ok(true)
+    Ok(ImportResult {
+        imported_rows: import_data.data.len(),
+        table_name: import_data.table_name,
+        coerced_cells,
+    })
 }
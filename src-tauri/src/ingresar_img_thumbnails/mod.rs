@@ -109,6 +109,8 @@ pub fn add_new_column(
     conn.execute(&sql, [])
         .map_err(|e| format!("Error al agregar la columna '{}': {}", column_name, e))?;
 
+    crate::auditoria::log_operation(&conn, "add_column", Some(&table_name), Some(&column_name), Some(sql_type))?;
+
     // Si todo fue exitoso, devuelve Ok.
     Ok(())
 }
@@ -0,0 +1,432 @@
+/// =========================================================================
+/// Módulo: Lote de cambios atómico
+///
+/// La edición en la tabla envía cada cambio por separado; si el tercero
+/// falla, los dos primeros ya quedaron guardados. `apply_changes` recibe
+/// la lista ordenada de cambios (altas, modificaciones y bajas, en una o
+/// varias tablas) y los aplica todos o ninguno:
+/// 1. Se valida el lote completo contra el esquema (tablas y columnas)
+/// 2. Se ejecutan los cambios en orden dentro de una transacción
+/// 3. Si alguno falla se revierte todo y se indica cuál falló
+///
+/// Las altas reciben el siguiente código de "No." (si la tabla la tiene) y de
+/// `id_column` dentro de la misma transacción (ver `secuencias.rs`).
+///
+/// Las modificaciones pueden incluir `original_values` para detectar
+/// conflictos con otras estaciones (ver `concurrencia.rs`).
+/// =========================================================================
+
+use std::collections::HashMap;
+
+use rusqlite::types::Value as SqlValue;
+use rusqlite::{Connection, Transaction, TransactionBehavior};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::State;
+
+use crate::busqueda_tabla::primary_key_column;
+use crate::concurrencia::{check_unchanged, version_assignments};
+use crate::database_manager::AppState;
+use crate::deshacer::{inserted_row, read_row, RowChange, UndoState};
+use crate::auditoria::log_row_changes;
+use crate::papelera::delete_row;
+use crate::valores_predeterminados::apply_defaults;
+use crate::secuencias::{code_to_json, next_code};
+use crate::edicion_masiva::{table_columns, ColumnMeta};
+use crate::protocolo_imagenes::is_image_url;
+
+/// Columna de numeración correlativa de los registros
+const NUMBER_COLUMN: &str = "No.";
+
+fn quote_identifier(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace("\"", "\"\""))
+}
+
+/// Cambio individual del lote
+#[derive(Debug, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Change {
+    /// Alta de un registro
+    Insert {
+        table_name: String,
+        values: HashMap<String, Value>,
+        /// Columna de código (ID) que recibe el siguiente valor de su secuencia
+        #[serde(default)]
+        id_column: Option<String>,
+    },
+    /// Modificación de un registro identificado por su clave primaria
+    Update {
+        table_name: String,
+        pk_value: Value,
+        values: HashMap<String, Value>,
+        /// Valores que el frontend leyó; si otro usuario los cambió, hay conflicto
+        #[serde(default)]
+        original_values: Option<HashMap<String, Value>>,
+    },
+    /// Baja de un registro identificado por su clave primaria
+    Delete {
+        table_name: String,
+        pk_value: Value,
+    },
+}
+
+impl Change {
+    fn table_name(&self) -> &str {
+        match self {
+            Change::Insert { table_name, .. }
+            | Change::Update { table_name, .. }
+            | Change::Delete { table_name, .. } => table_name,
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            Change::Insert { .. } => "insert",
+            Change::Update { .. } => "update",
+            Change::Delete { .. } => "delete",
+        }
+    }
+}
+
+/// Resultado de cada cambio del lote
+#[derive(Debug, Serialize)]
+pub struct ChangeResult {
+    /// Posición del cambio en el lote (desde 0)
+    pub index: usize,
+    /// "insert", "update" o "delete"
+    pub kind: String,
+    pub table_name: String,
+    /// Filas afectadas por el cambio
+    pub affected_rows: usize,
+    /// Identificador (rowid) generado en las altas
+    pub generated_id: Option<i64>,
+    /// Código asignado en las altas: el de `id_column` o, si no se indicó, el de "No."
+    pub generated_code: Option<Value>,
+    /// Error del cambio, si falló
+    pub error: Option<String>,
+}
+
+/// Resultado de `apply_changes`
+#[derive(Debug, Serialize)]
+pub struct ApplyChangesResult {
+    /// Indica si el lote completo quedó guardado
+    pub committed: bool,
+    /// Resultado de cada cambio, en el mismo orden del lote
+    pub results: Vec<ChangeResult>,
+}
+
+/// Esquema de una tabla usado durante el lote
+pub(crate) struct TableSchema {
+    pub pk_column: String,
+    pub columns: Vec<ColumnMeta>,
+}
+
+impl TableSchema {
+    pub(crate) fn load(conn: &Connection, table_name: &str) -> Result<Self, String> {
+        Ok(TableSchema {
+            columns: table_columns(conn, table_name)?,
+            pk_column: primary_key_column(conn, table_name)?,
+        })
+    }
+
+    /// Convierte el valor recibido según el tipo de la columna.
+    pub(crate) fn convert(&self, column: &str, value: &Value) -> Result<SqlValue, String> {
+        let meta = self.columns.iter().find(|c| c.name == column)
+            .ok_or_else(|| format!("La columna '{}' no existe en la tabla", column))?;
+        meta.codec().encode(value)
+    }
+
+    /// Valores a escribir: se descartan las imágenes que vuelven sin cambios como URL
+    fn prepare_values(&self, values: &HashMap<String, Value>) -> Result<Vec<(String, SqlValue)>, String> {
+        let mut prepared = Vec::new();
+        for (column, value) in values {
+            if value.as_str().map_or(false, is_image_url) {
+                continue;
+            }
+            prepared.push((column.clone(), self.convert(column, value)?));
+        }
+        Ok(prepared)
+    }
+}
+
+/// Valida un cambio contra el esquema sin ejecutarlo
+fn validate_change(change: &Change, schema: &TableSchema) -> Result<(), String> {
+    let check_columns = |values: &HashMap<String, Value>| -> Result<(), String> {
+        for column in values.keys() {
+            if !schema.columns.iter().any(|c| &c.name == column) {
+                return Err(format!("La columna '{}' no existe en la tabla", column));
+            }
+        }
+        Ok(())
+    };
+
+    match change {
+        Change::Insert { values, id_column, .. } => {
+            if let Some(id_column) = id_column {
+                if !schema.columns.iter().any(|c| &c.name == id_column) {
+                    return Err(format!("La columna '{}' no existe en la tabla", id_column));
+                }
+            }
+            check_columns(values)
+        }
+        Change::Update { pk_value, values, .. } => {
+            if pk_value.is_null() {
+                return Err("Falta la clave primaria del registro a modificar".to_string());
+            }
+            if values.is_empty() {
+                return Err("No hay datos para actualizar.".to_string());
+            }
+            check_columns(values)
+        }
+        Change::Delete { pk_value, .. } => {
+            if pk_value.is_null() {
+                return Err("Falta la clave primaria del registro a eliminar".to_string());
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Resultado de ejecutar un cambio dentro de la transacción
+struct ExecutedChange {
+    affected_rows: usize,
+    generated_id: Option<i64>,
+    generated_code: Option<Value>,
+    /// Cambios de filas para poder deshacerlo (una baja definitiva incluye
+    /// los adjuntos del registro)
+    row_changes: Vec<RowChange>,
+}
+
+impl ExecutedChange {
+    fn new(affected_rows: usize, row_changes: Vec<RowChange>) -> Self {
+        ExecutedChange { affected_rows, generated_id: None, generated_code: None, row_changes }
+    }
+}
+
+/// Ejecuta un cambio dentro de la transacción.
+fn execute_change(
+    tx: &Transaction,
+    db_name: &str,
+    change: &Change,
+    schema: &TableSchema,
+) -> Result<ExecutedChange, String> {
+    let table = quote_identifier(change.table_name());
+    let pk = quote_identifier(&schema.pk_column);
+
+    match change {
+        Change::Insert { table_name, values, id_column } => {
+            // Los campos que faltan toman el valor predeterminado de la columna
+            let mut values = values.clone();
+            apply_defaults(tx, table_name, &mut values)?;
+
+            // "No." y la columna de ID reciben el siguiente código de su secuencia;
+            // un valor indicado por el usuario reemplaza al de la secuencia
+            let mut id_columns: Vec<String> = Vec::new();
+            if schema.columns.iter().any(|c| c.name == NUMBER_COLUMN) {
+                id_columns.push(NUMBER_COLUMN.to_string());
+            }
+            if let Some(column) = id_column {
+                if !id_columns.contains(column) {
+                    id_columns.push(column.clone());
+                }
+            }
+            id_columns.retain(|c| values.get(c).map_or(true, |v| v.is_null() || v.as_str() == Some("")));
+            for column in &id_columns {
+                let code = next_code(tx, table_name, column, &values)?;
+                values.insert(column.clone(), code_to_json(&code));
+            }
+            let generated_code = id_column.as_deref()
+                .or_else(|| schema.columns.iter().any(|c| c.name == NUMBER_COLUMN).then_some(NUMBER_COLUMN))
+                .and_then(|c| values.get(c))
+                .filter(|v| !v.is_null())
+                .cloned();
+
+            let values = schema.prepare_values(&values)?;
+            let sql = if values.is_empty() {
+                format!("INSERT INTO {} DEFAULT VALUES", table)
+            } else {
+                format!(
+                    "INSERT INTO {} ({}) VALUES ({})",
+                    table,
+                    values.iter().map(|(c, _)| quote_identifier(c)).collect::<Vec<_>>().join(", "),
+                    vec!["?"; values.len()].join(", ")
+                )
+            };
+            let affected = tx.execute(&sql, rusqlite::params_from_iter(values.iter().map(|(_, v)| v)))
+                .map_err(|e| format!("Error al ejecutar INSERT: {}", e))?;
+            let rowid = tx.last_insert_rowid();
+            Ok(ExecutedChange {
+                affected_rows: affected,
+                generated_id: Some(rowid),
+                generated_code,
+                row_changes: vec![inserted_row(tx, change.table_name(), rowid)?],
+            })
+        }
+        Change::Update { table_name, pk_value, values, original_values } => {
+            if let Some(original) = original_values.as_ref().filter(|o| !o.is_empty()) {
+                check_unchanged(tx, db_name, table_name, &schema.pk_column, pk_value, original)?;
+            }
+            let values = schema.prepare_values(values)?;
+            if values.is_empty() {
+                return Ok(ExecutedChange::new(0, Vec::new()));
+            }
+            let pk_sql_value = schema.convert(&schema.pk_column, pk_value)?;
+            let before = read_row(tx, table_name, &schema.pk_column, &pk_sql_value)?;
+            // La clave primaria puede cambiar en la misma modificación
+            let new_pk = values.iter()
+                .find(|(c, _)| c == &schema.pk_column)
+                .map(|(_, v)| v.clone())
+                .unwrap_or_else(|| pk_sql_value.clone());
+            let mut set_clause: Vec<String> = values.iter().map(|(c, _)| format!("{} = ?", quote_identifier(c))).collect();
+            let all_columns: Vec<String> = schema.columns.iter().map(|c| c.name.clone()).collect();
+            let assigned: Vec<String> = values.iter().map(|(c, _)| c.clone()).collect();
+            set_clause.extend(version_assignments(&all_columns, &assigned));
+            let sql = format!(
+                "UPDATE {} SET {} WHERE {} = ?",
+                table,
+                set_clause.join(", "),
+                pk
+            );
+            let mut params: Vec<SqlValue> = values.into_iter().map(|(_, v)| v).collect();
+            params.push(pk_sql_value);
+            let affected = tx.execute(&sql, rusqlite::params_from_iter(params.iter()))
+                .map_err(|e| format!("Error al ejecutar UPDATE: {}", e))?;
+            if affected == 0 {
+                return Err(format!("No se encontró ninguna fila con {} = {}", schema.pk_column, pk_value));
+            }
+            let row_change = RowChange {
+                table_name: table_name.clone(),
+                pk_column: schema.pk_column.clone(),
+                before,
+                after: read_row(tx, table_name, &schema.pk_column, &new_pk)?,
+            };
+            Ok(ExecutedChange::new(affected, vec![row_change]))
+        }
+        Change::Delete { table_name, pk_value } => {
+            let pk_sql_value = schema.convert(&schema.pk_column, pk_value)?;
+            // Con borrado lógico el registro va a la papelera
+            let (affected, row_changes) = delete_row(tx, table_name, &schema.pk_column, &pk_sql_value)?;
+            if affected == 0 {
+                return Err(format!("No se encontró ninguna fila con {} = {}", schema.pk_column, pk_value));
+            }
+            Ok(ExecutedChange::new(affected, row_changes))
+        }
+    }
+}
+
+/// Aplica un lote ordenado de cambios en una sola transacción (todo o nada).
+///
+/// Si la validación o algún cambio falla, no se guarda nada y `committed` es
+/// `false`; el error aparece en el resultado del cambio correspondiente.
+#[tauri::command]
+pub fn apply_changes(
+    state: State<AppState>,
+    undo_state: State<UndoState>,
+    db_name: String,
+    changes: Vec<Change>,
+) -> Result<ApplyChangesResult, String> {
+    if changes.is_empty() {
+        return Err("No hay cambios para aplicar.".to_string());
+    }
+
+    let db_path = state.db_dir.join(format!("{}.db", db_name));
+    let sqlite_path = state.db_dir.join(format!("{}.sqlite", db_name));
+
+    let db_file = if db_path.exists() {
+        db_path
+    } else if sqlite_path.exists() {
+        sqlite_path
+    } else {
+        return Err(format!("Base de datos no encontrada: {}", db_name));
+    };
+
+    let mut conn = Connection::open(&db_file)
+        .map_err(|e| format!("Error al abrir la base de datos: {}", e))?;
+
+    // 1. Validar todo el lote antes de tocar la base de datos
+    let mut schemas: HashMap<String, TableSchema> = HashMap::new();
+    let mut results: Vec<ChangeResult> = Vec::new();
+    let mut valid = true;
+
+    for (index, change) in changes.iter().enumerate() {
+        let table_name = change.table_name().to_string();
+        if !schemas.contains_key(&table_name) {
+            match TableSchema::load(&conn, &table_name) {
+                Ok(schema) => {
+                    schemas.insert(table_name.clone(), schema);
+                }
+                Err(e) => {
+                    valid = false;
+                    results.push(ChangeResult {
+                        index,
+                        kind: change.kind().to_string(),
+                        table_name,
+                        affected_rows: 0,
+                        generated_id: None,
+                        generated_code: None,
+                        error: Some(e),
+                    });
+                    continue;
+                }
+            }
+        }
+
+        let error = validate_change(change, &schemas[&table_name]).err();
+        valid &= error.is_none();
+        results.push(ChangeResult {
+            index,
+            kind: change.kind().to_string(),
+            table_name,
+            affected_rows: 0,
+            generated_id: None,
+            generated_code: None,
+            error,
+        });
+    }
+
+    if !valid {
+        return Ok(ApplyChangesResult { committed: false, results });
+    }
+
+    // 2. Ejecutar en orden dentro de una transacción
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)
+        .map_err(|e| format!("Error al iniciar la transacción: {}", e))?;
+
+    let mut failed = false;
+    let mut row_changes = Vec::new();
+    for (change, result) in changes.iter().zip(results.iter_mut()) {
+        match execute_change(&tx, &db_name, change, &schemas[change.table_name()]) {
+            Ok(executed) => {
+                result.affected_rows = executed.affected_rows;
+                result.generated_id = executed.generated_id;
+                result.generated_code = executed.generated_code;
+                row_changes.extend(executed.row_changes);
+            }
+            Err(e) => {
+                result.error = Some(e);
+                failed = true;
+                break;
+            }
+        }
+    }
+
+    // 3. Un cambio falló: se revierte el lote completo
+    if failed {
+        tx.rollback()
+            .map_err(|e| format!("Error al revertir la transacción: {}", e))?;
+        for result in results.iter_mut() {
+            result.affected_rows = 0;
+            result.generated_id = None;
+            result.generated_code = None;
+        }
+        return Ok(ApplyChangesResult { committed: false, results });
+    }
+
+    log_row_changes(&tx, "apply_changes", &row_changes)?;
+    tx.commit()
+        .map_err(|e| format!("Error al guardar los cambios: {}", e))?;
+
+    undo_state.record(&db_name, format!("Aplicar {} cambios", changes.len()), row_changes);
+
+    Ok(ApplyChangesResult { committed: true, results })
+}
@@ -17,6 +17,31 @@ mod subir_imagen;
 mod detalle_registro;
 mod obtener_info_columnas;
 mod crear_columna_fecha;
+mod busqueda_tabla;
+mod busqueda_global;
+mod filtros;
+mod vistas_guardadas;
+mod agregaciones;
+mod protocolo_imagenes;
+mod consola_sql;
+mod scripts_sql;
+mod edicion_masiva;
+mod lote_cambios;
+mod concurrencia;
+mod deshacer;
+mod auditoria;
+mod historial_registro;
+mod papelera;
+mod secuencias;
+mod renumerar;
+mod codec_valores;
+mod valores_predeterminados;
+mod duplicar_registro;
+mod plantillas_registro;
+mod adjuntos;
+mod procesar_imagenes;
+mod almacen_imagenes;
+mod importar_imagenes;
 use tauri::Builder;
 
 use database_manager::{
@@ -37,6 +62,27 @@ use validar_columna::check_column_exists;
 use subir_imagen::upload_image_for_record;
 use detalle_registro::get_record_details;
 use obtener_info_columnas::get_column_info;
+use busqueda_tabla::{ build_search_index, delete_search_index, search_table, get_search_index_info };
+use busqueda_global::{ GlobalSearchState, global_search, cancel_global_search };
+use vistas_guardadas::{ create_saved_view, list_saved_views, update_saved_view, delete_saved_view, run_saved_view };
+use agregaciones::aggregate_table;
+use consola_sql::run_query;
+use scripts_sql::run_sql_script;
+use edicion_masiva::{ bulk_update_rows, bulk_delete_rows };
+use lote_cambios::apply_changes;
+use deshacer::{ UndoState, undo_last, redo };
+use auditoria::{ query_audit_log, export_audit_log };
+use historial_registro::{ get_record_history, diff_record_versions, restore_record_version, undelete_record };
+use papelera::{ get_soft_delete_config, set_soft_delete, list_deleted_rows, restore_deleted_rows, purge_deleted_rows };
+use secuencias::{ list_sequences, set_sequence, delete_sequence, preview_sequence };
+use renumerar::renumber_column;
+use valores_predeterminados::{ list_column_defaults, set_column_default, delete_column_default, get_new_record_defaults };
+use duplicar_registro::duplicate_record;
+use plantillas_registro::{ create_record_preset, list_record_presets, update_record_preset, delete_record_preset };
+use adjuntos::{ add_attachment, list_attachments, extract_attachment, delete_attachment };
+use procesar_imagenes::store_record_image;
+use almacen_imagenes::{ migrate_column_to_image_store, migrate_column_to_blob, image_store_stats, collect_image_garbage };
+use importar_imagenes::import_images_by_filename;
 use dirs;
 
 fn main() {
@@ -47,6 +93,9 @@ fn main() {
             db_dir,
             active_db: None,
         })
+        .manage(GlobalSearchState::default())
+        .manage(UndoState::default())
+        .register_uri_scheme_protocol(protocolo_imagenes::IMAGE_SCHEME, protocolo_imagenes::handle_image_request)
         .invoke_handler(
             tauri::generate_handler![
                 list_databases,
@@ -76,6 +125,60 @@ fn main() {
                 upload_image_for_record,
                 get_record_details,
                 get_column_info,
+                build_search_index,
+                delete_search_index,
+                search_table,
+                get_search_index_info,
+                global_search,
+                cancel_global_search,
+                create_saved_view,
+                list_saved_views,
+                update_saved_view,
+                delete_saved_view,
+                run_saved_view,
+                aggregate_table,
+                run_query,
+                run_sql_script,
+                bulk_update_rows,
+                bulk_delete_rows,
+                apply_changes,
+                undo_last,
+                redo,
+                query_audit_log,
+                export_audit_log,
+                get_record_history,
+                diff_record_versions,
+                restore_record_version,
+                undelete_record,
+                get_soft_delete_config,
+                set_soft_delete,
+                list_deleted_rows,
+                restore_deleted_rows,
+                purge_deleted_rows,
+                list_sequences,
+                set_sequence,
+                delete_sequence,
+                preview_sequence,
+                renumber_column,
+                list_column_defaults,
+                set_column_default,
+                delete_column_default,
+                get_new_record_defaults,
+                duplicate_record,
+                create_record_preset,
+                list_record_presets,
+                update_record_preset,
+                delete_record_preset,
+                add_attachment,
+                list_attachments,
+                extract_attachment,
+                delete_attachment,
+                store_record_image,
+                migrate_column_to_image_store,
+                migrate_column_to_blob,
+                image_store_stats,
+                collect_image_garbage,
+                import_images_by_filename,
             ]
         )
         .run(tauri::generate_context!())
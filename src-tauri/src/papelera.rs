@@ -0,0 +1,494 @@
+/// =========================================================================
+/// Módulo: Papelera (borrado lógico)
+///
+/// Cada tabla puede activar el borrado lógico. Con él activo:
+/// - Eliminar un registro no lo borra: se marcan las columnas `deleted_at`
+///   (fecha) y `deleted_by` (usuario), que se agregan a la tabla al activarlo
+/// - Los registros marcados no aparecen en `consulta_tabla`, las vistas
+///   guardadas ni las búsquedas
+/// - La papelera se puede listar, restaurar y vaciar; al vaciarla sin
+///   indicar registros se eliminan los que superan los días de retención
+///
+/// La configuración se guarda en la tabla interna `_unea_soft_delete`, de
+/// modo que viaja con el archivo `.db`.
+/// =========================================================================
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Duration, Local};
+use rusqlite::types::Value as SqlValue;
+use rusqlite::{Connection, OptionalExtension, TransactionBehavior};
+use serde::Serialize;
+use serde_json::Value;
+use tauri::State;
+
+use crate::adjuntos::discard_record_attachments;
+use crate::auditoria::{current_user, log_operation, log_row_changes};
+use crate::busqueda_tabla::primary_key_column;
+use crate::database_manager::AppState;
+use crate::deshacer::{read_row, RowChange, RowImage, UndoState};
+use crate::edicion_masiva::{image_to_json, read_raw_rows, table_columns};
+use crate::codec_valores::json_to_sql;
+
+/// Tabla interna con las tablas que usan borrado lógico
+pub(crate) const SOFT_DELETE_TABLE: &str = "_unea_soft_delete";
+/// Fecha de eliminación (RFC 3339); NULL = registro activo
+pub(crate) const DELETED_AT_COLUMN: &str = "deleted_at";
+/// Usuario que eliminó el registro
+pub(crate) const DELETED_BY_COLUMN: &str = "deleted_by";
+
+fn quote_identifier(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace("\"", "\"\""))
+}
+
+fn ensure_soft_delete_table(conn: &Connection) -> Result<(), String> {
+    conn.execute(
+        &format!(
+            "CREATE TABLE IF NOT EXISTS {} (
+                table_name TEXT PRIMARY KEY,
+                retention_days INTEGER,
+                enabled_at TEXT NOT NULL
+            )",
+            SOFT_DELETE_TABLE
+        ),
+        [],
+    ).map_err(|e| format!("Error al crear la tabla de configuración de la papelera: {}", e))?;
+    Ok(())
+}
+
+/// Configuración de la papelera de una tabla: None si no usa borrado lógico,
+/// Some(días de retención) si lo usa.
+fn soft_delete_config(conn: &Connection, table_name: &str) -> Result<Option<Option<u32>>, String> {
+    let exists: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name=?",
+        [SOFT_DELETE_TABLE],
+        |row| row.get(0),
+    ).map_err(|e| format!("Error al consultar la configuración de la papelera: {}", e))?;
+    if exists == 0 {
+        return Ok(None);
+    }
+    conn.query_row(
+        &format!("SELECT retention_days FROM {} WHERE table_name = ?", SOFT_DELETE_TABLE),
+        [table_name],
+        |row| row.get::<_, Option<u32>>(0),
+    )
+    .optional()
+    .map_err(|e| format!("Error al leer la configuración de la papelera: {}", e))
+}
+
+/// Indica si la tabla usa borrado lógico
+pub(crate) fn is_soft_delete_enabled(conn: &Connection, table_name: &str) -> Result<bool, String> {
+    Ok(soft_delete_config(conn, table_name)?.is_some())
+}
+
+/// Condición SQL (sin "WHERE") que deja solo los registros activos,
+/// o None si la tabla no usa borrado lógico
+pub(crate) fn active_rows_condition(conn: &Connection, table_name: &str) -> Result<Option<String>, String> {
+    Ok(is_soft_delete_enabled(conn, table_name)?
+        .then(|| format!("{} IS NULL", quote_identifier(DELETED_AT_COLUMN))))
+}
+
+/// Marca de eliminación que se escribe en los registros
+pub(crate) struct DeletionMark {
+    pub deleted_at: String,
+    pub deleted_by: String,
+}
+
+impl DeletionMark {
+    pub(crate) fn now() -> Self {
+        DeletionMark {
+            deleted_at: Local::now().to_rfc3339(),
+            deleted_by: current_user(),
+        }
+    }
+
+    /// Marca como eliminados los registros activos que cumplen `condition`
+    pub(crate) fn mark(
+        &self,
+        conn: &Connection,
+        table_name: &str,
+        condition: &str,
+        params: &[SqlValue],
+    ) -> Result<usize, String> {
+        let sql = format!(
+            "UPDATE {} SET {} = ?, {} = ? WHERE ({}) AND {} IS NULL",
+            quote_identifier(table_name),
+            quote_identifier(DELETED_AT_COLUMN),
+            quote_identifier(DELETED_BY_COLUMN),
+            condition,
+            quote_identifier(DELETED_AT_COLUMN)
+        );
+        let mut all_params = vec![
+            SqlValue::Text(self.deleted_at.clone()),
+            SqlValue::Text(self.deleted_by.clone()),
+        ];
+        all_params.extend(params.iter().cloned());
+        conn.execute(&sql, rusqlite::params_from_iter(all_params.iter()))
+            .map_err(|e| format!("Error al enviar los registros a la papelera: {}", e))
+    }
+
+    /// Imagen de la fila después de marcarla
+    pub(crate) fn apply(&self, mut image: RowImage) -> RowImage {
+        image.insert(DELETED_AT_COLUMN.to_string(), SqlValue::Text(self.deleted_at.clone()));
+        image.insert(DELETED_BY_COLUMN.to_string(), SqlValue::Text(self.deleted_by.clone()));
+        image
+    }
+}
+
+/// Elimina un registro por su clave primaria: lo envía a la papelera si la
+/// tabla usa borrado lógico, o lo borra si no (junto con sus adjuntos).
+/// Devuelve filas afectadas y los cambios, empezando por el del registro.
+pub(crate) fn delete_row(
+    conn: &Connection,
+    table_name: &str,
+    pk_column: &str,
+    pk_value: &SqlValue,
+) -> Result<(usize, Vec<RowChange>), String> {
+    let before = read_row(conn, table_name, pk_column, pk_value)?;
+
+    let (affected, after) = if is_soft_delete_enabled(conn, table_name)? {
+        let mark = DeletionMark::now();
+        let condition = format!("{} = ?", quote_identifier(pk_column));
+        let affected = mark.mark(conn, table_name, &condition, std::slice::from_ref(pk_value))?;
+        (affected, before.clone().filter(|_| affected > 0).map(|image| mark.apply(image)))
+    } else {
+        let affected = conn.execute(
+            &format!(
+                "DELETE FROM {} WHERE {} = ?",
+                quote_identifier(table_name),
+                quote_identifier(pk_column)
+            ),
+            [pk_value],
+        ).map_err(|e| format!("Error al ejecutar DELETE: {}", e))?;
+        (affected, None)
+    };
+    let hard_deleted = affected > 0 && after.is_none();
+
+    let mut changes = vec![RowChange {
+        table_name: table_name.to_string(),
+        pk_column: pk_column.to_string(),
+        before,
+        after,
+    }];
+    if hard_deleted {
+        changes.extend(discard_record_attachments(conn, table_name, pk_value)?);
+    }
+    Ok((affected, changes))
+}
+
+fn open_database(state: &AppState, db_name: &str) -> Result<Connection, String> {
+    let db_path = state.db_dir.join(format!("{}.db", db_name));
+    let sqlite_path = state.db_dir.join(format!("{}.sqlite", db_name));
+
+    let db_file = if db_path.exists() {
+        db_path
+    } else if sqlite_path.exists() {
+        sqlite_path
+    } else {
+        return Err(format!("Base de datos no encontrada: {}", db_name));
+    };
+
+    Connection::open(&db_file)
+        .map_err(|e| format!("Error al abrir la base de datos: {}", e))
+}
+
+/// Configuración de la papelera tal como se envía al frontend
+#[derive(Debug, Serialize)]
+pub struct SoftDeleteConfig {
+    pub table_name: String,
+    /// Indica si la tabla usa borrado lógico
+    pub enabled: bool,
+    /// Días que se conservan los registros en la papelera (None = sin límite)
+    pub retention_days: Option<u32>,
+    /// Registros que hay en la papelera
+    pub deleted_rows: usize,
+}
+
+fn read_config(conn: &Connection, table_name: &str) -> Result<SoftDeleteConfig, String> {
+    let config = soft_delete_config(conn, table_name)?;
+    let deleted_rows = match config {
+        Some(_) => conn.query_row(
+            &format!(
+                "SELECT COUNT(*) FROM {} WHERE {} IS NOT NULL",
+                quote_identifier(table_name),
+                quote_identifier(DELETED_AT_COLUMN)
+            ),
+            [],
+            |row| row.get::<_, i64>(0),
+        ).map_err(|e| format!("Error al contar los registros de la papelera: {}", e))? as usize,
+        None => 0,
+    };
+    Ok(SoftDeleteConfig {
+        table_name: table_name.to_string(),
+        enabled: config.is_some(),
+        retention_days: config.flatten(),
+        deleted_rows,
+    })
+}
+
+/// Devuelve la configuración de la papelera de una tabla
+#[tauri::command]
+pub fn get_soft_delete_config(
+    state: State<AppState>,
+    db_name: String,
+    table_name: String,
+) -> Result<SoftDeleteConfig, String> {
+    let conn = open_database(&state, &db_name)?;
+    table_columns(&conn, &table_name)?;
+    read_config(&conn, &table_name)
+}
+
+/// Activa o desactiva el borrado lógico de una tabla.
+///
+/// Al activarlo se agregan las columnas `deleted_at` y `deleted_by` si faltan.
+/// Para desactivarlo la papelera debe estar vacía, para que los registros
+/// eliminados no reaparezcan.
+#[tauri::command]
+pub fn set_soft_delete(
+    state: State<AppState>,
+    db_name: String,
+    table_name: String,
+    enabled: bool,
+    retention_days: Option<u32>,
+) -> Result<SoftDeleteConfig, String> {
+    let mut conn = open_database(&state, &db_name)?;
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)
+        .map_err(|e| format!("Error al iniciar la transacción: {}", e))?;
+
+    let existing: Vec<String> = table_columns(&tx, &table_name)?.into_iter().map(|c| c.name).collect();
+
+    if enabled {
+        for column in [DELETED_AT_COLUMN, DELETED_BY_COLUMN] {
+            if !existing.iter().any(|c| c == column) {
+                tx.execute(
+                    &format!(
+                        "ALTER TABLE {} ADD COLUMN {} TEXT",
+                        quote_identifier(&table_name),
+                        quote_identifier(column)
+                    ),
+                    [],
+                ).map_err(|e| format!("Error al agregar la columna '{}': {}", column, e))?;
+                log_operation(&tx, "add_column", Some(&table_name), Some(column), Some("TEXT"))?;
+            }
+        }
+        ensure_soft_delete_table(&tx)?;
+        tx.execute(
+            &format!(
+                "INSERT INTO {} (table_name, retention_days, enabled_at) VALUES (?, ?, ?)
+                 ON CONFLICT(table_name) DO UPDATE SET retention_days = excluded.retention_days",
+                SOFT_DELETE_TABLE
+            ),
+            rusqlite::params![table_name, retention_days, Local::now().to_rfc3339()],
+        ).map_err(|e| format!("Error al guardar la configuración de la papelera: {}", e))?;
+
+        let detail = match retention_days {
+            Some(days) => format!("activado, retención {} días", days),
+            None => "activado, sin retención".to_string(),
+        };
+        log_operation(&tx, "soft_delete", Some(&table_name), None, Some(&detail))?;
+    } else if is_soft_delete_enabled(&tx, &table_name)? {
+        let config = read_config(&tx, &table_name)?;
+        if config.deleted_rows > 0 {
+            return Err(format!(
+                "La papelera de '{}' tiene {} registros; restáurelos o vacíela antes de desactivarla",
+                table_name, config.deleted_rows
+            ));
+        }
+        tx.execute(
+            &format!("DELETE FROM {} WHERE table_name = ?", SOFT_DELETE_TABLE),
+            [&table_name],
+        ).map_err(|e| format!("Error al guardar la configuración de la papelera: {}", e))?;
+        log_operation(&tx, "soft_delete", Some(&table_name), None, Some("desactivado"))?;
+    }
+
+    let config = read_config(&tx, &table_name)?;
+    tx.commit()
+        .map_err(|e| format!("Error al guardar los cambios: {}", e))?;
+    Ok(config)
+}
+
+/// Registros en la papelera de una tabla
+#[derive(Debug, Serialize)]
+pub struct DeletedRows {
+    pub pk_column: String,
+    /// Filas completas (las imágenes en base64), las eliminadas más recientemente primero
+    pub rows: Vec<HashMap<String, Value>>,
+}
+
+/// Lista los registros que están en la papelera de una tabla
+#[tauri::command]
+pub fn list_deleted_rows(
+    state: State<AppState>,
+    db_name: String,
+    table_name: String,
+) -> Result<DeletedRows, String> {
+    let conn = open_database(&state, &db_name)?;
+    if !is_soft_delete_enabled(&conn, &table_name)? {
+        return Err(format!("La tabla '{}' no usa papelera", table_name));
+    }
+    let pk_column = primary_key_column(&conn, &table_name)?;
+    let select_list = if pk_column == "rowid" { "rowid, *" } else { "*" };
+    let rows = read_raw_rows(
+        &conn,
+        &format!(
+            "SELECT {} FROM {} WHERE {} IS NOT NULL ORDER BY {} DESC",
+            select_list,
+            quote_identifier(&table_name),
+            quote_identifier(DELETED_AT_COLUMN),
+            quote_identifier(DELETED_AT_COLUMN)
+        ),
+        &[],
+    )?;
+
+    Ok(DeletedRows {
+        pk_column,
+        rows: rows.iter().map(image_to_json).collect(),
+    })
+}
+
+/// Saca registros de la papelera. Devuelve cuántos se restauraron.
+#[tauri::command]
+pub fn restore_deleted_rows(
+    state: State<AppState>,
+    undo_state: State<UndoState>,
+    db_name: String,
+    table_name: String,
+    pk_values: Vec<Value>,
+) -> Result<usize, String> {
+    let mut conn = open_database(&state, &db_name)?;
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)
+        .map_err(|e| format!("Error al iniciar la transacción: {}", e))?;
+
+    if !is_soft_delete_enabled(&tx, &table_name)? {
+        return Err(format!("La tabla '{}' no usa papelera", table_name));
+    }
+    let pk_column = primary_key_column(&tx, &table_name)?;
+    let sql = format!(
+        "UPDATE {} SET {} = NULL, {} = NULL WHERE {} = ? AND {} IS NOT NULL",
+        quote_identifier(&table_name),
+        quote_identifier(DELETED_AT_COLUMN),
+        quote_identifier(DELETED_BY_COLUMN),
+        quote_identifier(&pk_column),
+        quote_identifier(DELETED_AT_COLUMN)
+    );
+
+    let mut changes = Vec::new();
+    for pk_value in &pk_values {
+        let pk_sql_value = json_to_sql(pk_value)?;
+        let before = read_row(&tx, &table_name, &pk_column, &pk_sql_value)?;
+        let affected = tx.execute(&sql, [&pk_sql_value])
+            .map_err(|e| format!("Error al restaurar el registro: {}", e))?;
+        if affected > 0 {
+            changes.push(RowChange {
+                table_name: table_name.clone(),
+                pk_column: pk_column.clone(),
+                before,
+                after: read_row(&tx, &table_name, &pk_column, &pk_sql_value)?,
+            });
+        }
+    }
+    log_row_changes(&tx, "restore_deleted_rows", &changes)?;
+
+    tx.commit()
+        .map_err(|e| format!("Error al guardar los cambios: {}", e))?;
+
+    let restored = changes.len();
+    undo_state.record(
+        &db_name,
+        format!("Restaurar {} registros de la papelera de {}", restored, table_name),
+        changes,
+    );
+    Ok(restored)
+}
+
+/// Elimina definitivamente registros de la papelera. Devuelve cuántos se eliminaron.
+///
+/// Con `pk_values` se eliminan esos registros. Sin ellos se eliminan los que
+/// llevan en la papelera más de `older_than_days` días (o los días de
+/// retención configurados para la tabla).
+#[tauri::command]
+pub fn purge_deleted_rows(
+    state: State<AppState>,
+    undo_state: State<UndoState>,
+    db_name: String,
+    table_name: String,
+    pk_values: Option<Vec<Value>>,
+    older_than_days: Option<u32>,
+) -> Result<usize, String> {
+    let mut conn = open_database(&state, &db_name)?;
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)
+        .map_err(|e| format!("Error al iniciar la transacción: {}", e))?;
+
+    let retention_days = match soft_delete_config(&tx, &table_name)? {
+        Some(days) => days,
+        None => return Err(format!("La tabla '{}' no usa papelera", table_name)),
+    };
+    let pk_column = primary_key_column(&tx, &table_name)?;
+
+    // Claves de los registros a eliminar
+    let targets: Vec<SqlValue> = match pk_values {
+        Some(values) => values.iter().map(json_to_sql).collect::<Result<Vec<_>, _>>()?,
+        None => {
+            let days = older_than_days.or(retention_days).ok_or_else(|| {
+                "La tabla no tiene días de retención; indique los registros o los días".to_string()
+            })?;
+            let cutoff = Local::now() - Duration::days(days as i64);
+
+            let mut stmt = tx.prepare(&format!(
+                "SELECT {}, {} FROM {} WHERE {} IS NOT NULL",
+                quote_identifier(&pk_column),
+                quote_identifier(DELETED_AT_COLUMN),
+                quote_identifier(&table_name),
+                quote_identifier(DELETED_AT_COLUMN)
+            )).map_err(|e| format!("Error al preparar la consulta de la papelera: {}", e))?;
+            let rows = stmt.query_map([], |row| Ok((row.get::<_, SqlValue>(0)?, row.get::<_, String>(1)?)))
+                .map_err(|e| format!("Error al consultar la papelera: {}", e))?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| format!("Error al leer la papelera: {}", e))?;
+
+            rows.into_iter()
+                // Una fecha ilegible no se elimina por antigüedad
+                .filter(|(_, deleted_at)| {
+                    DateTime::parse_from_rfc3339(deleted_at).map_or(false, |d| d < cutoff)
+                })
+                .map(|(pk, _)| pk)
+                .collect()
+        }
+    };
+
+    let sql = format!(
+        "DELETE FROM {} WHERE {} = ? AND {} IS NOT NULL",
+        quote_identifier(&table_name),
+        quote_identifier(&pk_column),
+        quote_identifier(DELETED_AT_COLUMN)
+    );
+    let mut changes = Vec::new();
+    let mut purged = 0;
+    for pk_value in &targets {
+        let before = read_row(&tx, &table_name, &pk_column, pk_value)?;
+        let affected = tx.execute(&sql, [pk_value])
+            .map_err(|e| format!("Error al vaciar la papelera: {}", e))?;
+        if affected > 0 {
+            purged += 1;
+            changes.push(RowChange {
+                table_name: table_name.clone(),
+                pk_column: pk_column.clone(),
+                before,
+                after: None,
+            });
+            // Los adjuntos se van con el registro (y vuelven si se deshace)
+            changes.extend(discard_record_attachments(&tx, &table_name, pk_value)?);
+        }
+    }
+    log_row_changes(&tx, "purge_deleted_rows", &changes)?;
+
+    tx.commit()
+        .map_err(|e| format!("Error al guardar los cambios: {}", e))?;
+
+    undo_state.record(
+        &db_name,
+        format!("Vaciar {} registros de la papelera de {}", purged, table_name),
+        changes,
+    );
+    Ok(purged)
+}
@@ -0,0 +1,304 @@
+/// =========================================================================
+/// Módulo: Plantillas de captura rápida
+///
+/// Una plantilla es un conjunto de valores con nombre, por tabla (ej.
+/// "Laptop Dell Latitude aula"), que llena de antemano los campos de un
+/// registro nuevo:
+/// - Crear, listar, actualizar y eliminar plantillas
+/// - `crear_registro_con_auto_incremento` acepta el nombre de una plantilla;
+///   los datos que envía el usuario tienen prioridad sobre los de la plantilla
+///
+/// Las plantillas se guardan dentro de la propia base de datos en la tabla
+/// `_unea_record_presets`, de modo que viajan con el archivo `.db`.
+/// =========================================================================
+
+use std::collections::HashMap;
+
+use chrono::Local;
+use rusqlite::{Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::State;
+
+use crate::codec_valores::{find_codec, table_codecs};
+use crate::database_manager::AppState;
+
+/// Nombre de la tabla interna donde se guardan las plantillas.
+pub(crate) const PRESETS_TABLE: &str = "_unea_record_presets";
+
+/// Plantilla tal como se envía al frontend
+#[derive(Debug, Serialize, Clone)]
+pub struct RecordPreset {
+    /// Identificador de la plantilla
+    pub id: i64,
+    /// Tabla a la que pertenece
+    pub table_name: String,
+    /// Nombre visible de la plantilla
+    pub name: String,
+    /// Valores de los campos que llena
+    pub values: HashMap<String, Value>,
+    /// Fecha de creación (RFC 3339)
+    pub created_at: String,
+    /// Fecha de última modificación (RFC 3339)
+    pub updated_at: String,
+}
+
+/// Datos que envía el frontend para crear o actualizar una plantilla
+#[derive(Debug, Deserialize)]
+pub struct RecordPresetInput {
+    pub table_name: String,
+    pub name: String,
+    #[serde(default)]
+    pub values: HashMap<String, Value>,
+}
+
+/// Crea la tabla de plantillas si todavía no existe
+fn ensure_presets_table(conn: &Connection) -> Result<(), String> {
+    conn.execute(
+        &format!(
+            "CREATE TABLE IF NOT EXISTS \"{}\" (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                table_name TEXT NOT NULL,
+                name TEXT NOT NULL,
+                field_values TEXT NOT NULL DEFAULT '{{}}',
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL,
+                UNIQUE (table_name, name)
+            )",
+            PRESETS_TABLE
+        ),
+        [],
+    ).map_err(|e| format!("Error al crear la tabla de plantillas: {}", e))?;
+    Ok(())
+}
+
+/// Convierte una fila de `_unea_record_presets` en `RecordPreset`
+fn row_to_preset(row: &rusqlite::Row) -> rusqlite::Result<RecordPreset> {
+    let values_json: String = row.get(3)?;
+    Ok(RecordPreset {
+        id: row.get(0)?,
+        table_name: row.get(1)?,
+        name: row.get(2)?,
+        // Un JSON dañado no debe impedir listar las demás plantillas
+        values: serde_json::from_str(&values_json).unwrap_or_default(),
+        created_at: row.get(4)?,
+        updated_at: row.get(5)?,
+    })
+}
+
+const PRESET_COLUMNS: &str = "id, table_name, name, field_values, created_at, updated_at";
+
+/// Obtiene una plantilla por su id
+fn load_preset(conn: &Connection, preset_id: i64) -> Result<RecordPreset, String> {
+    ensure_presets_table(conn)?;
+    conn.query_row(
+        &format!("SELECT {} FROM \"{}\" WHERE id = ?", PRESET_COLUMNS, PRESETS_TABLE),
+        [preset_id],
+        row_to_preset,
+    )
+    .optional()
+    .map_err(|e| format!("Error al leer la plantilla: {}", e))?
+    .ok_or_else(|| format!("No se encontró la plantilla con id {}", preset_id))
+}
+
+/// Obtiene una plantilla de la tabla por su nombre
+pub(crate) fn load_preset_by_name(conn: &Connection, table_name: &str, name: &str) -> Result<RecordPreset, String> {
+    ensure_presets_table(conn)?;
+    conn.query_row(
+        &format!("SELECT {} FROM \"{}\" WHERE table_name = ? AND name = ?", PRESET_COLUMNS, PRESETS_TABLE),
+        [table_name, name],
+        row_to_preset,
+    )
+    .optional()
+    .map_err(|e| format!("Error al leer la plantilla: {}", e))?
+    .ok_or_else(|| format!("No existe la plantilla '{}' para la tabla '{}'", name, table_name))
+}
+
+/// Agrega a `record` los valores de la plantilla que el usuario no llenó
+/// (campos que faltan o vienen en null)
+pub(crate) fn merge_preset(preset: &RecordPreset, record: &mut HashMap<String, Value>) {
+    for (column, value) in &preset.values {
+        if record.get(column).map_or(true, Value::is_null) {
+            record.insert(column.clone(), value.clone());
+        }
+    }
+}
+
+/// Comprueba que la plantilla tenga nombre y que sus valores se puedan
+/// guardar en las columnas de la tabla
+fn validate_preset(conn: &Connection, input: &RecordPresetInput) -> Result<(), String> {
+    if input.name.trim().is_empty() {
+        return Err("La plantilla debe tener un nombre.".to_string());
+    }
+    let codecs = table_codecs(conn, &input.table_name)?;
+    for (column, value) in &input.values {
+        find_codec(&codecs, column)?.encode(value)?;
+    }
+    Ok(())
+}
+
+/// Crea una nueva plantilla
+#[tauri::command]
+pub fn create_record_preset(
+    state: State<AppState>,
+    db_name: String,
+    preset: RecordPresetInput,
+) -> Result<RecordPreset, String> {
+    let db_path = state.db_dir.join(format!("{}.db", db_name));
+    let sqlite_path = state.db_dir.join(format!("{}.sqlite", db_name));
+
+    let db_file = if db_path.exists() {
+        db_path
+    } else if sqlite_path.exists() {
+        sqlite_path
+    } else {
+        return Err(format!("No se encontró la base de datos: {}", db_name));
+    };
+
+    let conn = Connection::open(&db_file)
+        .map_err(|e| format!("Error al abrir la base de datos: {}", e))?;
+
+    ensure_presets_table(&conn)?;
+    validate_preset(&conn, &preset)?;
+
+    let now = Local::now().to_rfc3339();
+    conn.execute(
+        &format!(
+            "INSERT INTO \"{}\" (table_name, name, field_values, created_at, updated_at) VALUES (?, ?, ?, ?, ?)",
+            PRESETS_TABLE
+        ),
+        rusqlite::params![
+            preset.table_name,
+            preset.name,
+            serde_json::to_string(&preset.values).map_err(|e| e.to_string())?,
+            now,
+            now,
+        ],
+    ).map_err(|e| {
+        if e.to_string().contains("UNIQUE") {
+            format!("Ya existe una plantilla llamada '{}' para la tabla '{}'", preset.name, preset.table_name)
+        } else {
+            format!("Error al guardar la plantilla: {}", e)
+        }
+    })?;
+
+    load_preset(&conn, conn.last_insert_rowid())
+}
+
+/// Lista las plantillas de una tabla
+#[tauri::command]
+pub fn list_record_presets(
+    state: State<AppState>,
+    db_name: String,
+    table_name: String,
+) -> Result<Vec<RecordPreset>, String> {
+    let db_path = state.db_dir.join(format!("{}.db", db_name));
+    let sqlite_path = state.db_dir.join(format!("{}.sqlite", db_name));
+
+    let db_file = if db_path.exists() {
+        db_path
+    } else if sqlite_path.exists() {
+        sqlite_path
+    } else {
+        return Err(format!("No se encontró la base de datos: {}", db_name));
+    };
+
+    let conn = Connection::open(&db_file)
+        .map_err(|e| format!("Error al abrir la base de datos: {}", e))?;
+
+    ensure_presets_table(&conn)?;
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM \"{}\" WHERE table_name = ? ORDER BY name",
+        PRESET_COLUMNS, PRESETS_TABLE
+    )).map_err(|e| format!("Error al preparar la consulta de plantillas: {}", e))?;
+
+    let presets = stmt.query_map([table_name], row_to_preset)
+        .map_err(|e| format!("Error al consultar las plantillas: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Error al leer las plantillas: {}", e))?;
+
+    Ok(presets)
+}
+
+/// Actualiza una plantilla existente
+#[tauri::command]
+pub fn update_record_preset(
+    state: State<AppState>,
+    db_name: String,
+    preset_id: i64,
+    preset: RecordPresetInput,
+) -> Result<RecordPreset, String> {
+    let db_path = state.db_dir.join(format!("{}.db", db_name));
+    let sqlite_path = state.db_dir.join(format!("{}.sqlite", db_name));
+
+    let db_file = if db_path.exists() {
+        db_path
+    } else if sqlite_path.exists() {
+        sqlite_path
+    } else {
+        return Err(format!("No se encontró la base de datos: {}", db_name));
+    };
+
+    let conn = Connection::open(&db_file)
+        .map_err(|e| format!("Error al abrir la base de datos: {}", e))?;
+
+    // Verifica que exista antes de validar
+    load_preset(&conn, preset_id)?;
+    validate_preset(&conn, &preset)?;
+
+    conn.execute(
+        &format!(
+            "UPDATE \"{}\" SET table_name = ?, name = ?, field_values = ?, updated_at = ? WHERE id = ?",
+            PRESETS_TABLE
+        ),
+        rusqlite::params![
+            preset.table_name,
+            preset.name,
+            serde_json::to_string(&preset.values).map_err(|e| e.to_string())?,
+            Local::now().to_rfc3339(),
+            preset_id,
+        ],
+    ).map_err(|e| {
+        if e.to_string().contains("UNIQUE") {
+            format!("Ya existe una plantilla llamada '{}' para la tabla '{}'", preset.name, preset.table_name)
+        } else {
+            format!("Error al actualizar la plantilla: {}", e)
+        }
+    })?;
+
+    load_preset(&conn, preset_id)
+}
+
+/// Elimina una plantilla
+#[tauri::command]
+pub fn delete_record_preset(
+    state: State<AppState>,
+    db_name: String,
+    preset_id: i64,
+) -> Result<(), String> {
+    let db_path = state.db_dir.join(format!("{}.db", db_name));
+    let sqlite_path = state.db_dir.join(format!("{}.sqlite", db_name));
+
+    let db_file = if db_path.exists() {
+        db_path
+    } else if sqlite_path.exists() {
+        sqlite_path
+    } else {
+        return Err(format!("No se encontró la base de datos: {}", db_name));
+    };
+
+    let conn = Connection::open(&db_file)
+        .map_err(|e| format!("Error al abrir la base de datos: {}", e))?;
+
+    ensure_presets_table(&conn)?;
+
+    let rows_affected = conn.execute(&format!("DELETE FROM \"{}\" WHERE id = ?", PRESETS_TABLE), [preset_id])
+        .map_err(|e| format!("Error al eliminar la plantilla: {}", e))?;
+
+    if rows_affected == 0 {
+        return Err(format!("No se encontró la plantilla con id {}", preset_id));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,348 @@
+/// =========================================================================
+/// Módulo: Procesamiento de imágenes de los registros
+///
+/// Antes de guardar una foto en una columna BLOB se prepara con el crate
+/// `image`, para que una foto de teléfono de 8 MB no llegue tal cual a la base:
+/// - El formato se comprueba por los bytes (PNG, JPEG, GIF, WebP, BMP), no por la extensión
+/// - Se aplica la rotación indicada en los datos EXIF de la cámara
+/// - Se reduce para que el lado mayor no pase de `max_dimension`
+/// - Se vuelve a codificar (JPEG con la calidad indicada, o PNG si tiene
+///   transparencia), lo que descarta EXIF, GPS y demás metadatos
+/// - Se genera una miniatura, que puede guardarse en otra columna
+///
+/// `store_record_image` escribe el resultado directamente en la fila y
+/// columna indicadas, sin que la imagen viaje en base64 por IPC.
+/// =========================================================================
+
+use std::fs;
+use std::io::Cursor;
+
+use image::imageops::FilterType;
+use image::{DynamicImage, GenericImageView, ImageOutputFormat};
+use rusqlite::types::Value as SqlValue;
+use rusqlite::{Connection, TransactionBehavior};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::State;
+
+use crate::almacen_imagenes::{is_store_column, put_image};
+use crate::auditoria::{log_row_changes, pk_text};
+use crate::busqueda_tabla::primary_key_column;
+use crate::codec_valores::{find_codec, json_to_sql, table_codecs, LogicalType};
+use crate::concurrencia::{version_assignments, UPDATED_AT_COLUMN, VERSION_COLUMN};
+use crate::database_manager::AppState;
+use crate::deshacer::{read_row, RowChange, RowImage, UndoState};
+use crate::papelera::active_rows_condition;
+use crate::protocolo_imagenes::{image_url, sniff_image_mime};
+
+/// Tamaño máximo del archivo original (50 MB)
+const MAX_SOURCE_BYTES: u64 = 50 * 1024 * 1024;
+
+fn quote_identifier(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace("\"", "\"\""))
+}
+
+fn default_max_dimension() -> u32 {
+    1600
+}
+
+fn default_quality() -> u8 {
+    80
+}
+
+fn default_thumbnail_size() -> u32 {
+    256
+}
+
+/// Opciones del procesamiento. Todas tienen un valor por omisión.
+#[derive(Debug, Deserialize, Clone)]
+pub struct ImageOptions {
+    /// Lado mayor de la imagen guardada, en píxeles
+    #[serde(default = "default_max_dimension")]
+    pub max_dimension: u32,
+    /// Calidad JPEG (1-100)
+    #[serde(default = "default_quality")]
+    pub quality: u8,
+    /// Lado mayor de la miniatura, en píxeles
+    #[serde(default = "default_thumbnail_size")]
+    pub thumbnail_size: u32,
+    /// Columna BLOB donde se guarda la miniatura (opcional)
+    #[serde(default)]
+    pub thumbnail_column: Option<String>,
+}
+
+impl Default for ImageOptions {
+    fn default() -> Self {
+        ImageOptions {
+            max_dimension: default_max_dimension(),
+            quality: default_quality(),
+            thumbnail_size: default_thumbnail_size(),
+            thumbnail_column: None,
+        }
+    }
+}
+
+/// Imagen lista para guardar
+pub(crate) struct ProcessedImage {
+    pub bytes: Vec<u8>,
+    pub mime_type: &'static str,
+    pub width: u32,
+    pub height: u32,
+    pub thumbnail: Vec<u8>,
+}
+
+/// Resultado de `store_record_image`
+#[derive(Debug, Serialize)]
+pub struct StoredImage {
+    pub mime_type: String,
+    pub width: u32,
+    pub height: u32,
+    /// Tamaño del archivo original, en bytes
+    pub original_size: u64,
+    /// Tamaño guardado, en bytes
+    pub stored_size: usize,
+    /// URL del protocolo `unea-img` para mostrar la imagen
+    pub image_url: String,
+    /// URL de la miniatura, si se guardó en una columna
+    pub thumbnail_url: Option<String>,
+}
+
+/// Orientación EXIF (1-8) de la imagen, o 1 si no la indica
+fn exif_orientation(bytes: &[u8]) -> u32 {
+    let Ok(exif) = exif::Reader::new().read_from_container(&mut Cursor::new(bytes)) else {
+        return 1;
+    };
+    exif.get_field(exif::Tag::Orientation, exif::In::PRIMARY)
+        .and_then(|field| field.value.get_uint(0))
+        .unwrap_or(1)
+}
+
+/// Gira o voltea la imagen según la orientación EXIF
+fn apply_orientation(img: DynamicImage, orientation: u32) -> DynamicImage {
+    match orientation {
+        2 => img.fliph(),
+        3 => img.rotate180(),
+        4 => img.flipv(),
+        5 => img.rotate90().fliph(),
+        6 => img.rotate90(),
+        7 => img.rotate270().fliph(),
+        8 => img.rotate270(),
+        _ => img,
+    }
+}
+
+/// Codifica la imagen. Las que tienen transparencia se guardan en PNG; el resto en JPEG.
+fn encode(img: &DynamicImage, quality: u8) -> Result<(Vec<u8>, &'static str), String> {
+    let mut out = Cursor::new(Vec::new());
+    if img.color().has_alpha() {
+        img.write_to(&mut out, ImageOutputFormat::Png)
+            .map_err(|e| format!("Error al codificar la imagen: {}", e))?;
+        Ok((out.into_inner(), "image/png"))
+    } else {
+        img.to_rgb8().write_to(&mut out, ImageOutputFormat::Jpeg(quality))
+            .map_err(|e| format!("Error al codificar la imagen: {}", e))?;
+        Ok((out.into_inner(), "image/jpeg"))
+    }
+}
+
+/// Procesa los bytes de una imagen: valida el formato, aplica la rotación
+/// EXIF, reduce el tamaño, vuelve a codificar sin metadatos y genera la miniatura
+pub(crate) fn process_image(bytes: &[u8], options: &ImageOptions) -> Result<ProcessedImage, String> {
+    let mime = sniff_image_mime(bytes)
+        .ok_or("El archivo no es una imagen compatible (PNG, JPEG, GIF, WebP o BMP)")?;
+    let format = image::ImageFormat::from_mime_type(mime)
+        .ok_or_else(|| format!("Formato de imagen no compatible: {}", mime))?;
+
+    let img = image::load_from_memory_with_format(bytes, format)
+        .map_err(|e| format!("Imagen inválida: {}", e))?;
+    let img = apply_orientation(img, exif_orientation(bytes));
+
+    let max_dimension = options.max_dimension.max(16);
+    let img = if img.width() > max_dimension || img.height() > max_dimension {
+        img.resize(max_dimension, max_dimension, FilterType::Lanczos3)
+    } else {
+        img
+    };
+
+    let quality = options.quality.clamp(1, 100);
+    let (processed, mime_type) = encode(&img, quality)?;
+
+    let thumbnail_size = options.thumbnail_size.clamp(16, max_dimension);
+    let (thumbnail, _) = encode(&img.thumbnail(thumbnail_size, thumbnail_size), quality)?;
+
+    let (width, height) = img.dimensions();
+    Ok(ProcessedImage { bytes: processed, mime_type, width, height, thumbnail })
+}
+
+/// Lee y procesa un archivo de imagen
+pub(crate) fn process_image_file(file_path: &str, options: &ImageOptions) -> Result<(ProcessedImage, u64), String> {
+    let size = fs::metadata(file_path)
+        .map_err(|e| format!("Error al leer la imagen '{}': {}", file_path, e))?
+        .len();
+    if size > MAX_SOURCE_BYTES {
+        return Err(format!(
+            "La imagen '{}' pesa {:.1} MB; el máximo es {} MB",
+            file_path,
+            size as f64 / (1024.0 * 1024.0),
+            MAX_SOURCE_BYTES / (1024 * 1024)
+        ));
+    }
+    let bytes = fs::read(file_path)
+        .map_err(|e| format!("Error al leer la imagen '{}': {}", file_path, e))?;
+    Ok((process_image(&bytes, options)?, size))
+}
+
+/// Comprueba que la columna exista y pueda guardar una imagen
+fn check_image_column(conn: &Connection, table_name: &str, column: &str) -> Result<(), String> {
+    let codecs = table_codecs(conn, table_name)?;
+    match find_codec(&codecs, column)?.logical {
+        LogicalType::Blob | LogicalType::Any => Ok(()),
+        _ => Err(format!("La columna '{}' no es de tipo imagen (BLOB)", column)),
+    }
+}
+
+/// Valor que se escribe en la celda: los bytes, o la referencia si la
+/// columna usa el almacén de imágenes por contenido
+fn cell_value(conn: &Connection, table_name: &str, column: &str, bytes: &[u8]) -> Result<SqlValue, String> {
+    if is_store_column(conn, table_name, column)? {
+        Ok(SqlValue::Text(put_image(conn, bytes)?))
+    } else {
+        Ok(SqlValue::Blob(bytes.to_vec()))
+    }
+}
+
+/// Escribe la imagen procesada (y su miniatura, si se indica la columna) en
+/// un registro existente. Se ejecuta dentro de la transacción del llamador y
+/// devuelve el cambio para el historial de deshacer, solo con las columnas
+/// escritas, la clave y las de versión (el resto de la fila no cambia y puede
+/// tener otras imágenes pesadas).
+pub(crate) fn write_record_image(
+    conn: &Connection,
+    table_name: &str,
+    pk_column: &str,
+    pk_value: &SqlValue,
+    column: &str,
+    image: &ProcessedImage,
+    thumbnail_column: Option<&str>,
+) -> Result<RowChange, String> {
+    check_image_column(conn, table_name, column)?;
+    if let Some(thumbnail_column) = thumbnail_column {
+        check_image_column(conn, table_name, thumbnail_column)?;
+    }
+
+    let before = read_row(conn, table_name, pk_column, pk_value)?
+        .ok_or_else(|| format!("No se encontró ningún registro con {} = {}", pk_column, pk_text(pk_value).unwrap_or_default()))?;
+
+    let mut assigned = vec![column.to_string()];
+    let mut params = vec![cell_value(conn, table_name, column, &image.bytes)?];
+    if let Some(thumbnail_column) = thumbnail_column {
+        assigned.push(thumbnail_column.to_string());
+        params.push(cell_value(conn, table_name, thumbnail_column, &image.thumbnail)?);
+    }
+    let all_columns: Vec<String> = before.keys().cloned().collect();
+    let mut set_clause: Vec<String> = assigned.iter().map(|c| format!("{} = ?", quote_identifier(c))).collect();
+    set_clause.extend(version_assignments(&all_columns, &assigned));
+
+    // Los registros en la papelera no se modifican
+    let mut sql = format!(
+        "UPDATE {} SET {} WHERE {} = ?",
+        quote_identifier(table_name),
+        set_clause.join(", "),
+        quote_identifier(pk_column)
+    );
+    if let Some(condition) = active_rows_condition(conn, table_name)? {
+        sql.push_str(&format!(" AND {}", condition));
+    }
+    params.push(pk_value.clone());
+
+    let affected = conn.execute(&sql, rusqlite::params_from_iter(params.iter()))
+        .map_err(|e| format!("Error al guardar la imagen: {}", e))?;
+    if affected == 0 {
+        return Err("El registro está en la papelera; restáurelo antes de cambiar su imagen".to_string());
+    }
+
+    let after = read_row(conn, table_name, pk_column, pk_value)?;
+    let kept: Vec<&str> = assigned
+        .iter()
+        .map(String::as_str)
+        .chain([pk_column, VERSION_COLUMN, UPDATED_AT_COLUMN])
+        .collect();
+    let partial = |image: RowImage| -> RowImage {
+        image.into_iter().filter(|(column, _)| kept.contains(&column.as_str())).collect()
+    };
+    Ok(RowChange {
+        table_name: table_name.to_string(),
+        pk_column: pk_column.to_string(),
+        before: Some(partial(before)),
+        after: after.map(partial),
+    })
+}
+
+/// Procesa una imagen desde una ruta y la guarda en la columna de un registro
+#[tauri::command]
+pub fn store_record_image(
+    state: State<AppState>,
+    undo_state: State<UndoState>,
+    db_name: String,
+    table_name: String,
+    pk_value: Value,
+    column_name: String,
+    file_path: String,
+    options: Option<ImageOptions>,
+) -> Result<StoredImage, String> {
+    let options = options.unwrap_or_default();
+    // Se procesa antes de abrir la transacción para no bloquear la base mientras tanto
+    let (image, original_size) = process_image_file(&file_path, &options)?;
+
+    let db_path = state.db_dir.join(format!("{}.db", db_name));
+    let sqlite_path = state.db_dir.join(format!("{}.sqlite", db_name));
+
+    let db_file = if db_path.exists() {
+        db_path
+    } else if sqlite_path.exists() {
+        sqlite_path
+    } else {
+        return Err(format!("Base de datos no encontrada: {}", db_name));
+    };
+
+    let mut conn = Connection::open(&db_file)
+        .map_err(|e| format!("Error al abrir la base de datos: {}", e))?;
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)
+        .map_err(|e| format!("Error al iniciar la transacción: {}", e))?;
+
+    let pk_column = primary_key_column(&tx, &table_name)?;
+    let pk_sql_value = json_to_sql(&pk_value)?;
+
+    let change = write_record_image(
+        &tx,
+        &table_name,
+        &pk_column,
+        &pk_sql_value,
+        &column_name,
+        &image,
+        options.thumbnail_column.as_deref(),
+    )?;
+    let changes = vec![change];
+    log_row_changes(&tx, "store_record_image", &changes)?;
+
+    tx.commit()
+        .map_err(|e| format!("Error al guardar los cambios: {}", e))?;
+
+    undo_state.record(
+        &db_name,
+        format!("Cambiar imagen de {} en {}", column_name, table_name),
+        changes,
+    );
+
+    Ok(StoredImage {
+        mime_type: image.mime_type.to_string(),
+        width: image.width,
+        height: image.height,
+        original_size,
+        stored_size: image.bytes.len(),
+        image_url: image_url(&db_name, &table_name, &pk_value, &column_name),
+        thumbnail_url: options.thumbnail_column
+            .as_deref()
+            .map(|column| image_url(&db_name, &table_name, &pk_value, column)),
+    })
+}
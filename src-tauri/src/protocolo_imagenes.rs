@@ -0,0 +1,258 @@
+/// =========================================================================
+/// Módulo: Protocolo `unea-img://` para imágenes guardadas como BLOB
+///
+/// En lugar de enviar cada imagen en base64 dentro del JSON de las consultas,
+/// las consultas devuelven una URL ligera y el webview pide la imagen a este
+/// protocolo solo cuando la muestra:
+///
+///   unea-img://localhost/<base>/<tabla>/<clave primaria>/<columna>?size=200
+///
+/// (en Windows Tauri expone el mismo protocolo como `https://unea-img.localhost/...`)
+///
+/// - El tipo MIME se detecta a partir de los bytes (PNG, JPEG, GIF, WebP, BMP)
+/// - `size` devuelve una miniatura cuyo lado mayor no supera ese valor
+/// - Se envía `ETag` y se responde 304 si el webview ya tiene la misma versión
+/// =========================================================================
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::io::Cursor;
+
+use rusqlite::{Connection, OpenFlags, OptionalExtension};
+use serde_json::Value;
+use tauri::http::{Request, Response, ResponseBuilder};
+use tauri::{AppHandle, Manager};
+
+use crate::database_manager::AppState;
+
+/// Nombre del protocolo registrado en `main.rs`.
+pub const IMAGE_SCHEME: &str = "unea-img";
+
+/// Tamaño máximo permitido para las miniaturas.
+const MAX_THUMBNAIL_SIZE: u32 = 1024;
+
+fn quote_identifier(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace("\"", "\"\""))
+}
+
+/// Codifica un segmento de la ruta para que nombres con espacios, acentos o "/" viajen en la URL
+fn encode_segment(segment: &str) -> String {
+    let mut encoded = String::new();
+    for byte in segment.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// Decodifica un segmento codificado con `encode_segment`
+fn decode_segment(segment: &str) -> Result<String, String> {
+    let bytes = segment.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).map_err(|e| e.to_string())?;
+            decoded.push(u8::from_str_radix(hex, 16).map_err(|e| e.to_string())?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).map_err(|e| e.to_string())
+}
+
+/// Construye la URL del protocolo para la imagen de una celda
+pub(crate) fn image_url(db_name: &str, table_name: &str, pk_value: &Value, column: &str) -> String {
+    let pk = match pk_value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    };
+    let path = format!(
+        "{}/{}/{}/{}",
+        encode_segment(db_name),
+        encode_segment(table_name),
+        encode_segment(&pk),
+        encode_segment(column)
+    );
+
+    #[cfg(target_os = "windows")]
+    {
+        format!("https://{}.localhost/{}", IMAGE_SCHEME, path)
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    {
+        format!("{}://localhost/{}", IMAGE_SCHEME, path)
+    }
+}
+
+/// Detecta el tipo MIME de una imagen por sus primeros bytes
+pub(crate) fn sniff_image_mime(bytes: &[u8]) -> Option<&'static str> {
+    if bytes.starts_with(&[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A]) {
+        Some("image/png")
+    } else if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some("image/jpeg")
+    } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        Some("image/gif")
+    } else if bytes.len() >= 12 && &bytes[0..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        Some("image/webp")
+    } else if bytes.starts_with(b"BM") {
+        Some("image/bmp")
+    } else {
+        None
+    }
+}
+
+/// Genera una miniatura. Se conserva PNG para imágenes con transparencia; el resto va a JPEG.
+fn make_thumbnail(bytes: &[u8], mime: &str, size: u32) -> Result<(Vec<u8>, &'static str), String> {
+    let img = image::load_from_memory(bytes).map_err(|e| format!("Imagen inválida: {}", e))?;
+    let thumb = img.thumbnail(size, size);
+
+    let mut out = Cursor::new(Vec::new());
+    if mime == "image/png" || mime == "image/gif" {
+        thumb.write_to(&mut out, image::ImageOutputFormat::Png)
+            .map_err(|e| format!("Error al generar miniatura: {}", e))?;
+        Ok((out.into_inner(), "image/png"))
+    } else {
+        thumb.to_rgb8().write_to(&mut out, image::ImageOutputFormat::Jpeg(80))
+            .map_err(|e| format!("Error al generar miniatura: {}", e))?;
+        Ok((out.into_inner(), "image/jpeg"))
+    }
+}
+
+fn error_response(status: u16, message: &str) -> Result<Response, Box<dyn std::error::Error>> {
+    ResponseBuilder::new()
+        .status(status)
+        .mimetype("text/plain")
+        .body(message.as_bytes().to_vec())
+        .map_err(Into::into)
+}
+
+/// Lee el BLOB de una celda
+fn read_blob(
+    app: &AppHandle,
+    db_name: &str,
+    table_name: &str,
+    pk: &str,
+    column: &str,
+) -> Result<Option<Vec<u8>>, String> {
+    let state = app.state::<AppState>();
+    let db_path = state.db_dir.join(format!("{}.db", db_name));
+    let sqlite_path = state.db_dir.join(format!("{}.sqlite", db_name));
+
+    let db_file = if db_path.exists() {
+        db_path
+    } else if sqlite_path.exists() {
+        sqlite_path
+    } else {
+        return Err(format!("No se encontró la base de datos: {}", db_name));
+    };
+
+    let conn = Connection::open_with_flags(&db_file, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| format!("Error al abrir la base de datos: {}", e))?;
+
+    let pk_column = crate::busqueda_tabla::primary_key_column(&conn, table_name)?;
+    let sql = format!(
+        "SELECT {} FROM {} WHERE {} = ?",
+        quote_identifier(column),
+        quote_identifier(table_name),
+        quote_identifier(&pk_column)
+    );
+
+    // La celda guarda los bytes o una referencia al almacén de imágenes
+    let cell: Option<(Option<Vec<u8>>, Option<String>)> = conn.query_row(&sql, [pk], |row| {
+        let value = row.get_ref(0)?;
+        match value {
+            rusqlite::types::ValueRef::Blob(b) => Ok((Some(b.to_vec()), None)),
+            other => Ok((None, crate::almacen_imagenes::parse_image_ref(other))),
+        }
+    })
+    .optional()
+    .map_err(|e| format!("Error al leer la imagen: {}", e))?;
+
+    match cell {
+        Some((Some(bytes), _)) => Ok(Some(bytes)),
+        Some((None, Some(hash))) => crate::almacen_imagenes::read_image(&conn, &hash),
+        _ => Ok(None),
+    }
+}
+
+/// Atiende las peticiones del protocolo `unea-img`. Se registra en `main.rs`.
+pub fn handle_image_request(app: &AppHandle, request: &Request) -> Result<Response, Box<dyn std::error::Error>> {
+    // Separar ruta y parámetros: todo lo que sigue al host "localhost/"
+    let uri = request.uri();
+    let after_host = match uri.find("localhost/") {
+        Some(pos) => &uri[pos + "localhost/".len()..],
+        None => return error_response(400, "URL de imagen inválida"),
+    };
+    let (path, query) = match after_host.split_once('?') {
+        Some((p, q)) => (p, Some(q)),
+        None => (after_host, None),
+    };
+
+    let segments = path
+        .split('/')
+        .map(decode_segment)
+        .collect::<Result<Vec<_>, _>>();
+    let segments = match segments {
+        Ok(s) if s.len() == 4 => s,
+        _ => return error_response(400, "URL de imagen inválida"),
+    };
+
+    let size: Option<u32> = query.and_then(|q| {
+        q.split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(k, _)| *k == "size")
+            .and_then(|(_, v)| v.parse().ok())
+            .map(|s: u32| s.clamp(16, MAX_THUMBNAIL_SIZE))
+    });
+
+    let bytes = match read_blob(app, &segments[0], &segments[1], &segments[2], &segments[3]) {
+        Ok(Some(bytes)) => bytes,
+        Ok(None) => return error_response(404, "Imagen no encontrada"),
+        Err(e) => return error_response(404, &e),
+    };
+
+    // ETag: cambia si cambian los bytes o el tamaño solicitado
+    let mut hasher = DefaultHasher::new();
+    bytes.hash(&mut hasher);
+    size.hash(&mut hasher);
+    let etag = format!("\"{:x}\"", hasher.finish());
+
+    let if_none_match = request.headers().get("if-none-match").and_then(|v| v.to_str().ok());
+    if if_none_match == Some(etag.as_str()) {
+        return ResponseBuilder::new()
+            .status(304)
+            .header("ETag", etag.as_str())
+            .body(Vec::new())
+            .map_err(Into::into);
+    }
+
+    let mime = sniff_image_mime(&bytes).unwrap_or("application/octet-stream");
+    let (body, mime) = match size {
+        Some(size) if mime != "application/octet-stream" => match make_thumbnail(&bytes, mime, size) {
+            Ok(thumb) => thumb,
+            Err(e) => return error_response(422, &e),
+        },
+        _ => (bytes, mime),
+    };
+
+    ResponseBuilder::new()
+        .status(200)
+        .mimetype(mime)
+        .header("ETag", etag.as_str())
+        .header("Cache-Control", "no-cache")
+        .header("Access-Control-Allow-Origin", "*")
+        .body(body)
+        .map_err(Into::into)
+}
+
+/// Indica si un valor recibido del frontend es una URL de este protocolo.
+/// Se usa para ignorar imágenes que el frontend devuelve sin modificar.
+pub(crate) fn is_image_url(value: &str) -> bool {
+    value.starts_with(&format!("{}://", IMAGE_SCHEME)) || value.starts_with(&format!("https://{}.localhost/", IMAGE_SCHEME))
+}
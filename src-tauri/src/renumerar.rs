@@ -0,0 +1,222 @@
+/// =========================================================================
+/// Módulo: Renumerar la columna "No."
+///
+/// Con el tiempo la columna "No." (por la que `consulta_tabla` ordena)
+/// acumula huecos por registros eliminados y números repetidos.
+/// `renumber_column` ordena los registros con el criterio elegido y vuelve
+/// a escribir la secuencia 1..N en una sola transacción:
+/// - Con `preview = true` solo muestra el número anterior y el nuevo
+/// - Solo se escriben las filas cuyo número cambia; cada cambio queda en
+///   la bitácora de auditoría y se puede deshacer
+/// - Los registros en la papelera se numeran después de los activos
+/// - El contador de la secuencia de la columna continúa en N+1
+/// =========================================================================
+
+use rusqlite::types::Value as SqlValue;
+use rusqlite::{Connection, TransactionBehavior};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::State;
+
+use crate::auditoria::log_row_changes;
+use crate::codec_valores::sql_to_json;
+use crate::busqueda_tabla::primary_key_column;
+use crate::database_manager::AppState;
+use crate::deshacer::{read_row, RowChange, UndoState};
+use crate::edicion_masiva::table_columns;
+use crate::filtros::{self, SortSpec};
+use crate::papelera::{is_soft_delete_enabled, DELETED_AT_COLUMN};
+use crate::secuencias::reset_counter;
+
+/// Columna que se renumera si no se indica otra
+const DEFAULT_COLUMN: &str = "No.";
+
+fn quote_identifier(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace("\"", "\"\""))
+}
+
+fn default_column() -> String {
+    DEFAULT_COLUMN.to_string()
+}
+
+/// Petición de renumeración
+#[derive(Debug, Deserialize)]
+pub struct RenumberRequest {
+    pub table_name: String,
+    /// Columna a renumerar (por omisión "No.")
+    #[serde(default = "default_column")]
+    pub column_name: String,
+    /// Orden de los registros; vacío = el número actual
+    #[serde(default)]
+    pub sort: Vec<SortSpec>,
+    /// Primer número (por omisión 1)
+    #[serde(default)]
+    pub start: Option<i64>,
+    /// Solo calcular los números nuevos sin escribirlos
+    #[serde(default)]
+    pub preview: bool,
+}
+
+/// Número anterior y nuevo de un registro
+#[derive(Debug, Serialize)]
+pub struct RenumberRow {
+    pub pk_value: Value,
+    pub old_value: Value,
+    pub new_value: i64,
+}
+
+/// Resultado de `renumber_column`
+#[derive(Debug, Serialize)]
+pub struct RenumberResult {
+    pub pk_column: String,
+    /// Registros de la tabla
+    pub total_rows: usize,
+    /// Registros cuyo número cambia (o cambió)
+    pub changed_rows: usize,
+    /// Indica si fue solo una vista previa
+    pub preview: bool,
+    /// Registros cuyo número cambia, en el orden nuevo
+    pub rows: Vec<RenumberRow>,
+}
+
+/// Valor actual de un número como entero, si lo es
+fn as_number(value: &SqlValue) -> Option<i64> {
+    match value {
+        SqlValue::Integer(i) => Some(*i),
+        SqlValue::Real(f) if f.fract() == 0.0 => Some(*f as i64),
+        SqlValue::Text(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+/// Reescribe la columna "No." (u otra) con la secuencia start..start+N-1
+#[tauri::command]
+pub fn renumber_column(
+    state: State<AppState>,
+    undo_state: State<UndoState>,
+    db_name: String,
+    request: RenumberRequest,
+) -> Result<RenumberResult, String> {
+    let db_path = state.db_dir.join(format!("{}.db", db_name));
+    let sqlite_path = state.db_dir.join(format!("{}.sqlite", db_name));
+
+    let db_file = if db_path.exists() {
+        db_path
+    } else if sqlite_path.exists() {
+        sqlite_path
+    } else {
+        return Err(format!("Base de datos no encontrada: {}", db_name));
+    };
+
+    let mut conn = Connection::open(&db_file)
+        .map_err(|e| format!("Error al abrir la base de datos: {}", e))?;
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)
+        .map_err(|e| format!("Error al iniciar la transacción: {}", e))?;
+
+    let metas = table_columns(&tx, &request.table_name)?;
+    let codec = metas.iter()
+        .find(|c| c.name == request.column_name)
+        .ok_or_else(|| format!("La columna '{}' no existe en la tabla", request.column_name))?
+        .codec();
+    let columns: Vec<String> = metas.into_iter().map(|c| c.name).collect();
+    let pk_column = primary_key_column(&tx, &request.table_name)?;
+    if pk_column == request.column_name {
+        return Err("No se puede renumerar la clave primaria de la tabla".to_string());
+    }
+    let start = request.start.unwrap_or(1);
+
+    // Orden: papelera al final, criterio elegido (o el número actual) y la
+    // clave primaria para desempatar repetidos
+    let sort = if request.sort.is_empty() {
+        vec![SortSpec { column: request.column_name.clone(), descending: false }]
+    } else {
+        request.sort
+    };
+    let mut order_sql = filtros::order_clause(&sort, &columns)?;
+    if is_soft_delete_enabled(&tx, &request.table_name)? {
+        order_sql = order_sql.replacen(
+            " ORDER BY ",
+            &format!(" ORDER BY {} IS NOT NULL, ", quote_identifier(DELETED_AT_COLUMN)),
+            1,
+        );
+    }
+    order_sql.push_str(&format!(", {}", quote_identifier(&pk_column)));
+
+    let mut stmt = tx.prepare(&format!(
+        "SELECT {}, {} FROM {}{}",
+        quote_identifier(&pk_column),
+        quote_identifier(&request.column_name),
+        quote_identifier(&request.table_name),
+        order_sql
+    )).map_err(|e| format!("Error al preparar la consulta de registros: {}", e))?;
+    let current: Vec<(SqlValue, SqlValue)> = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| format!("Error al consultar los registros: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Error al leer los registros: {}", e))?;
+    drop(stmt);
+
+    let total_rows = current.len();
+    let renumbered: Vec<(SqlValue, SqlValue, i64)> = current
+        .into_iter()
+        .zip(start..)
+        .filter(|((_, old), new)| as_number(old) != Some(*new))
+        .map(|((pk, old), new)| (pk, old, new))
+        .collect();
+
+    let rows: Vec<RenumberRow> = renumbered
+        .iter()
+        .map(|(pk, old, new)| RenumberRow {
+            pk_value: sql_to_json(pk),
+            old_value: codec.decode(old.into()),
+            new_value: *new,
+        })
+        .collect();
+
+    if request.preview {
+        return Ok(RenumberResult {
+            pk_column,
+            total_rows,
+            changed_rows: rows.len(),
+            preview: true,
+            rows,
+        });
+    }
+
+    let sql = format!(
+        "UPDATE {} SET {} = ? WHERE {} = ?",
+        quote_identifier(&request.table_name),
+        quote_identifier(&request.column_name),
+        quote_identifier(&pk_column)
+    );
+    let mut changes = Vec::with_capacity(renumbered.len());
+    for (pk, _, new) in &renumbered {
+        let before = read_row(&tx, &request.table_name, &pk_column, pk)?;
+        tx.execute(&sql, rusqlite::params![new, pk])
+            .map_err(|e| format!("Error al renumerar el registro: {}", e))?;
+        changes.push(RowChange {
+            table_name: request.table_name.clone(),
+            pk_column: pk_column.clone(),
+            before,
+            after: read_row(&tx, &request.table_name, &pk_column, pk)?,
+        });
+    }
+    log_row_changes(&tx, "renumber_column", &changes)?;
+    reset_counter(&tx, &request.table_name, &request.column_name, start + total_rows as i64)?;
+
+    tx.commit()
+        .map_err(|e| format!("Error al guardar los cambios: {}", e))?;
+
+    undo_state.record(
+        &db_name,
+        format!("Renumerar {} de {}", request.column_name, request.table_name),
+        changes,
+    );
+
+    Ok(RenumberResult {
+        pk_column,
+        total_rows,
+        changed_rows: rows.len(),
+        preview: false,
+        rows,
+    })
+}
@@ -0,0 +1,326 @@
+/// =========================================================================
+/// Módulo: Ejecución de scripts SQL de mantenimiento
+///
+/// A diferencia de `execute_sql` (una sola sentencia), este módulo ejecuta
+/// scripts con varias sentencias separadas por `;`:
+/// - Todas las sentencias se ejecutan en UNA transacción; si una falla,
+///   no se aplica ninguna
+/// - Modo de prueba (`dry_run`): se ejecuta todo, se reportan las filas
+///   afectadas por sentencia y se revierte la transacción
+/// - DROP, ALTER, y DELETE o UPDATE sin WHERE se marcan como destructivas: requieren
+///   confirmación explícita y antes de aplicarlas se crea un respaldo
+///   automático de la base de datos
+/// =========================================================================
+
+use rusqlite::fallible_iterator::FallibleIterator;
+use rusqlite::{Batch, Connection, Transaction};
+use serde::Serialize;
+use tauri::State;
+
+use crate::auditoria::log_operation;
+use crate::database_manager::{create_snapshot, AppState};
+
+/// Resultado de cada sentencia del script
+#[derive(Debug, Serialize)]
+pub struct StatementResult {
+    /// Posición de la sentencia en el script (desde 1)
+    pub index: usize,
+    /// Texto de la sentencia
+    pub sql: String,
+    /// Tipo de sentencia (SELECT, INSERT, UPDATE, DELETE, CREATE, DROP...)
+    pub kind: String,
+    /// Indica si la sentencia es destructiva (DROP, ALTER, DELETE o UPDATE sin WHERE)
+    pub destructive: bool,
+    /// Filas insertadas, modificadas o eliminadas
+    pub affected_rows: usize,
+    /// Filas devueltas (para sentencias de consulta)
+    pub returned_rows: usize,
+    /// Error de la sentencia; la ejecución se detiene en la primera que falla
+    pub error: Option<String>,
+}
+
+/// Resultado de `run_sql_script`
+#[derive(Debug, Serialize)]
+pub struct ScriptResult {
+    pub statements: Vec<StatementResult>,
+    /// Indica si los cambios quedaron guardados
+    pub committed: bool,
+    /// Indica si fue una ejecución de prueba
+    pub dry_run: bool,
+    /// El script contiene sentencias destructivas y falta la confirmación.
+    /// No se aplicó nada; `statements` muestra lo que habría pasado.
+    pub requires_confirmation: bool,
+    /// Ruta del respaldo creado antes de aplicar sentencias destructivas
+    pub snapshot_path: Option<String>,
+}
+
+/// Palabra clave de una sentencia con su nivel de paréntesis (0 = sentencia principal)
+struct Keyword {
+    word: String,
+    depth: usize,
+}
+
+/// Palabras clave de una sentencia, ignorando comentarios, textos e identificadores entre comillas
+fn keywords(sql: &str) -> Vec<Keyword> {
+    let chars: Vec<char> = sql.chars().collect();
+    let mut words = Vec::new();
+    let mut depth = 0usize;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '-' && chars.get(i + 1) == Some(&'-') {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if c == '/' && chars.get(i + 1) == Some(&'*') {
+            i += 2;
+            while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                i += 1;
+            }
+            i += 2;
+        } else if c == '\'' || c == '"' || c == '`' || c == '[' {
+            let close = if c == '[' { ']' } else { c };
+            i += 1;
+            while i < chars.len() && chars[i] != close {
+                i += 1;
+            }
+            i += 1;
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            words.push(Keyword {
+                word: chars[start..i].iter().collect::<String>().to_uppercase(),
+                depth,
+            });
+        } else {
+            match c {
+                '(' => depth += 1,
+                ')' => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+            i += 1;
+        }
+    }
+    words
+}
+
+/// Tipo de sentencia. En `WITH ... DELETE/UPDATE/INSERT` se toma la operación principal
+/// (la que está fuera de los paréntesis de las subconsultas).
+fn statement_kind(words: &[Keyword]) -> String {
+    match words.first().map(|w| w.word.as_str()) {
+        Some("WITH") => words
+            .iter()
+            .filter(|w| w.depth == 0)
+            .find(|w| matches!(w.word.as_str(), "INSERT" | "REPLACE" | "UPDATE" | "DELETE"))
+            .map(|w| w.word.clone())
+            .unwrap_or_else(|| "SELECT".to_string()),
+        Some(word) => word.to_string(),
+        None => String::new(),
+    }
+}
+
+/// Palabras que pueden aparecer en una condición que no depende de ninguna columna
+/// (`WHERE 1`, `WHERE 1 = 1`, `WHERE TRUE`)
+const CONSTANT_WORDS: [&str; 7] = ["TRUE", "FALSE", "NULL", "NOT", "AND", "OR", "IS"];
+
+/// Cláusulas que terminan el WHERE de la sentencia principal
+const AFTER_WHERE: [&str; 4] = ["RETURNING", "ORDER", "LIMIT", "GROUP"];
+
+/// DROP y ALTER siempre son destructivas. DELETE y UPDATE lo son si no tienen
+/// un WHERE propio (uno dentro de una subconsulta no cuenta) o si su condición
+/// no usa ninguna columna.
+fn is_destructive(kind: &str, words: &[Keyword]) -> bool {
+    match kind {
+        "DROP" | "ALTER" => true,
+        "DELETE" | "UPDATE" => {
+            let Some(where_pos) = words.iter().position(|w| w.depth == 0 && w.word == "WHERE") else {
+                return true;
+            };
+            let mut condition = words[where_pos + 1..]
+                .iter()
+                .take_while(|w| !(w.depth == 0 && AFTER_WHERE.contains(&w.word.as_str())));
+            !condition.any(|w| !CONSTANT_WORDS.contains(&w.word.as_str()))
+        }
+        _ => false,
+    }
+}
+
+/// Ejecuta todas las sentencias del script dentro de la transacción recibida.
+/// Se detiene en la primera sentencia con error (queda registrada en el resultado).
+fn run_statements(tx: &Transaction, script: &str) -> Vec<StatementResult> {
+    let mut results = Vec::new();
+    let mut batch = Batch::new(tx, script);
+
+    loop {
+        let index = results.len() + 1;
+        let mut stmt = match batch.next() {
+            Ok(Some(stmt)) => stmt,
+            Ok(None) => break,
+            Err(e) => {
+                results.push(StatementResult {
+                    index,
+                    sql: String::new(),
+                    kind: String::new(),
+                    destructive: false,
+                    affected_rows: 0,
+                    returned_rows: 0,
+                    error: Some(format!("Error al preparar la sentencia {}: {}", index, e)),
+                });
+                break;
+            }
+        };
+
+        let sql = stmt.expanded_sql().unwrap_or_default().trim().to_string();
+        let words = keywords(&sql);
+        let kind = statement_kind(&words);
+        let mut result = StatementResult {
+            index,
+            destructive: is_destructive(&kind, &words),
+            sql,
+            kind,
+            affected_rows: 0,
+            returned_rows: 0,
+            error: None,
+        };
+
+        // El script ya corre en una transacción: no se permite abrir ni cerrar otras
+        if matches!(result.kind.as_str(), "BEGIN" | "COMMIT" | "END" | "ROLLBACK" | "SAVEPOINT" | "RELEASE") {
+            result.error = Some(format!(
+                "La sentencia {} controla transacciones; el script ya se ejecuta en una sola transacción",
+                index
+            ));
+            results.push(result);
+            break;
+        }
+
+        // Las sentencias que devuelven filas (SELECT, algunos PRAGMA) se recorren completas
+        let outcome = if stmt.column_count() > 0 {
+            stmt.query([]).and_then(|mut rows| {
+                while rows.next()?.is_some() {
+                    result.returned_rows += 1;
+                }
+                Ok(())
+            })
+        } else {
+            stmt.execute([]).map(|_| ())
+        };
+
+        match outcome {
+            Ok(()) => {
+                if matches!(result.kind.as_str(), "INSERT" | "REPLACE" | "UPDATE" | "DELETE") {
+                    result.affected_rows = tx.changes() as usize;
+                }
+                results.push(result);
+            }
+            Err(e) => {
+                result.error = Some(format!("Error en la sentencia {}: {}", index, e));
+                results.push(result);
+                break;
+            }
+        }
+    }
+
+    results
+}
+
+/// Ejecuta un script SQL con varias sentencias en una sola transacción.
+///
+/// # Argumentos
+/// - `script`: sentencias separadas por `;`
+/// - `dry_run`: ejecuta, reporta filas afectadas y revierte todo
+/// - `confirm_destructive`: confirmación para aplicar DROP, ALTER o DELETE sin WHERE
+///
+/// Si el script tiene sentencias destructivas y no se confirmó, no se aplica
+/// nada y se devuelve `requires_confirmation = true` con el resultado simulado.
+#[tauri::command]
+pub fn run_sql_script(
+    state: State<AppState>,
+    db_name: String,
+    script: String,
+    dry_run: Option<bool>,
+    confirm_destructive: Option<bool>,
+) -> Result<ScriptResult, String> {
+    let dry_run = dry_run.unwrap_or(false);
+    let confirmed = confirm_destructive.unwrap_or(false);
+
+    let db_path = state.db_dir.join(format!("{}.db", db_name));
+    let sqlite_path = state.db_dir.join(format!("{}.sqlite", db_name));
+
+    let db_file = if db_path.exists() {
+        db_path
+    } else if sqlite_path.exists() {
+        sqlite_path
+    } else {
+        return Err(format!("Base de datos no encontrada: {}", db_name));
+    };
+
+    let mut conn = Connection::open(&db_file)
+        .map_err(|e| format!("Error al abrir la base de datos: {}", e))?;
+
+    let tx = conn.transaction()
+        .map_err(|e| format!("Error al iniciar la transacción: {}", e))?;
+    let statements = run_statements(&tx, &script);
+
+    if statements.is_empty() {
+        return Err("El script no contiene sentencias".to_string());
+    }
+
+    let failed = statements.iter().any(|s| s.error.is_some());
+    let destructive = statements.iter().any(|s| s.destructive);
+
+    // Prueba, error o falta de confirmación: no se guarda nada
+    if dry_run || failed || (destructive && !confirmed) {
+        tx.rollback()
+            .map_err(|e| format!("Error al revertir la transacción: {}", e))?;
+        return Ok(ScriptResult {
+            statements,
+            committed: false,
+            dry_run,
+            requires_confirmation: destructive && !confirmed && !dry_run && !failed,
+            snapshot_path: None,
+        });
+    }
+
+    if !destructive {
+        log_operation(&tx, "script", None, None, Some(&script))?;
+        tx.commit()
+            .map_err(|e| format!("Error al guardar los cambios: {}", e))?;
+        return Ok(ScriptResult {
+            statements,
+            committed: true,
+            dry_run: false,
+            requires_confirmation: false,
+            snapshot_path: None,
+        });
+    }
+
+    // Destructivo y confirmado: se revierte, se respalda el estado original y se vuelve a aplicar
+    tx.rollback()
+        .map_err(|e| format!("Error al revertir la transacción: {}", e))?;
+    let snapshot = create_snapshot(&state.db_dir, &db_file, &db_name, "script")?;
+
+    let tx = conn.transaction()
+        .map_err(|e| format!("Error al iniciar la transacción: {}", e))?;
+    let statements = run_statements(&tx, &script);
+    let committed = if statements.iter().any(|s| s.error.is_some()) {
+        tx.rollback()
+            .map_err(|e| format!("Error al revertir la transacción: {}", e))?;
+        false
+    } else {
+        log_operation(&tx, "script", None, None, Some(&script))?;
+        tx.commit()
+            .map_err(|e| format!("Error al guardar los cambios: {}", e))?;
+        true
+    };
+
+    Ok(ScriptResult {
+        statements,
+        committed,
+        dry_run: false,
+        requires_confirmation: false,
+        snapshot_path: Some(snapshot.to_string_lossy().to_string()),
+    })
+}
@@ -0,0 +1,442 @@
+/// =========================================================================
+/// Módulo: Secuencias de identificadores
+///
+/// Asigna el siguiente ID o código de una columna (ej. "No." o el ID de
+/// `crear_registro_con_auto_incremento`) sin que dos estaciones obtengan
+/// el mismo valor:
+/// - El contador de cada tabla/columna se guarda en la tabla interna
+///   `_unea_sequences` y se incrementa dentro de la transacción de escritura
+///   que inserta el registro
+/// - El formato es configurable, ej. `INV-{PLANTEL}-{YYYY}-{000000}`:
+///   `{000000}` contador con ceros a la izquierda, `{N}` contador sin
+///   relleno, `{YYYY}` `{YY}` `{MM}` `{DD}` fecha actual y cualquier otro
+///   nombre el valor de esa columna en el registro nuevo
+/// - Opcionalmente se reutilizan los números liberados por registros eliminados
+///
+/// Si una columna no tiene secuencia configurada se crea una con formato
+/// `{N}` que continúa a partir del mayor valor numérico existente.
+/// =========================================================================
+
+use std::collections::{HashMap, HashSet};
+
+use chrono::Local;
+use rusqlite::{Connection, OptionalExtension, TransactionBehavior};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::State;
+
+use crate::auditoria::log_operation;
+use crate::database_manager::AppState;
+use crate::edicion_masiva::table_columns;
+
+/// Tabla interna con los contadores
+pub(crate) const SEQUENCES_TABLE: &str = "_unea_sequences";
+/// Formato de las secuencias creadas automáticamente
+const DEFAULT_PATTERN: &str = "{N}";
+
+fn quote_identifier(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace("\"", "\"\""))
+}
+
+/// Secuencia tal como se envía al frontend
+#[derive(Debug, Serialize, Clone)]
+pub struct SequenceConfig {
+    pub table_name: String,
+    pub column_name: String,
+    /// Formato del código, ej. "INV-{PLANTEL}-{YYYY}-{000000}"
+    pub pattern: String,
+    /// Próximo número del contador
+    pub next_value: i64,
+    /// Si se reutilizan los números de registros eliminados
+    pub reuse_gaps: bool,
+    /// Fecha de la última modificación (RFC 3339)
+    pub updated_at: String,
+}
+
+/// Datos que envía el frontend para crear o modificar una secuencia
+#[derive(Debug, Deserialize)]
+pub struct SequenceInput {
+    pub table_name: String,
+    pub column_name: String,
+    pub pattern: String,
+    #[serde(default)]
+    pub reuse_gaps: bool,
+    /// Reinicia el contador en este número (si se omite se conserva)
+    #[serde(default)]
+    pub next_value: Option<i64>,
+}
+
+fn ensure_sequences_table(conn: &Connection) -> Result<(), String> {
+    conn.execute(
+        &format!(
+            "CREATE TABLE IF NOT EXISTS {} (
+                table_name TEXT NOT NULL,
+                column_name TEXT NOT NULL,
+                pattern TEXT NOT NULL,
+                next_value INTEGER NOT NULL DEFAULT 1,
+                reuse_gaps INTEGER NOT NULL DEFAULT 0,
+                updated_at TEXT NOT NULL,
+                PRIMARY KEY (table_name, column_name)
+            )",
+            SEQUENCES_TABLE
+        ),
+        [],
+    ).map_err(|e| format!("Error al crear la tabla de secuencias: {}", e))?;
+    Ok(())
+}
+
+const SEQUENCE_COLUMNS: &str = "table_name, column_name, pattern, next_value, reuse_gaps, updated_at";
+
+fn row_to_sequence(row: &rusqlite::Row) -> rusqlite::Result<SequenceConfig> {
+    Ok(SequenceConfig {
+        table_name: row.get(0)?,
+        column_name: row.get(1)?,
+        pattern: row.get(2)?,
+        next_value: row.get(3)?,
+        reuse_gaps: row.get::<_, i64>(4)? != 0,
+        updated_at: row.get(5)?,
+    })
+}
+
+fn read_sequence(conn: &Connection, table_name: &str, column_name: &str) -> Result<Option<SequenceConfig>, String> {
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM {} WHERE table_name = ? AND column_name = ?",
+            SEQUENCE_COLUMNS, SEQUENCES_TABLE
+        ),
+        [table_name, column_name],
+        row_to_sequence,
+    )
+    .optional()
+    .map_err(|e| format!("Error al leer la secuencia: {}", e))
+}
+
+/// Parte de un formato
+enum Token {
+    Literal(String),
+    /// Contador con relleno de ceros hasta `width` (0 = sin relleno)
+    Counter { width: usize },
+    Date(&'static str),
+    Column(String),
+}
+
+fn parse_pattern(pattern: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut rest = pattern;
+    while let Some(start) = rest.find('{') {
+        if start > 0 {
+            tokens.push(Token::Literal(rest[..start].to_string()));
+        }
+        let end = rest[start..].find('}')
+            .ok_or_else(|| format!("Falta '}}' en el formato '{}'", pattern))?;
+        let name = &rest[start + 1..start + end];
+        tokens.push(match name {
+            "" => return Err(format!("Hay un campo vacío '{{}}' en el formato '{}'", pattern)),
+            "N" => Token::Counter { width: 0 },
+            "YYYY" => Token::Date("%Y"),
+            "YY" => Token::Date("%y"),
+            "MM" => Token::Date("%m"),
+            "DD" => Token::Date("%d"),
+            zeros if zeros.chars().all(|c| c == '0') => Token::Counter { width: zeros.len() },
+            column => Token::Column(column.to_string()),
+        });
+        rest = &rest[start + end + 1..];
+    }
+    if !rest.is_empty() {
+        tokens.push(Token::Literal(rest.to_string()));
+    }
+
+    if !tokens.iter().any(|t| matches!(t, Token::Counter { .. })) {
+        return Err(format!(
+            "El formato '{}' debe incluir el contador ({{N}} o {{000000}})",
+            pattern
+        ));
+    }
+    Ok(tokens)
+}
+
+/// Valor de texto de un campo del registro (el nombre no distingue mayúsculas)
+fn record_field(record: &HashMap<String, Value>, column: &str) -> Option<String> {
+    record
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(column))
+        .and_then(|(_, value)| match value {
+            Value::Null => None,
+            Value::String(s) if s.trim().is_empty() => None,
+            Value::String(s) => Some(s.trim().to_string()),
+            other => Some(other.to_string()),
+        })
+}
+
+/// Código de un número. Con `record = None` los campos de columna se dejan
+/// como están (solo para mostrar el formato).
+fn format_code(tokens: &[Token], number: i64, record: Option<&HashMap<String, Value>>) -> Result<String, String> {
+    let now = Local::now();
+    let mut code = String::new();
+    for token in tokens {
+        match token {
+            Token::Literal(text) => code.push_str(text),
+            Token::Counter { width } => code.push_str(&format!("{:0width$}", number, width = *width)),
+            Token::Date(fmt) => code.push_str(&now.format(fmt).to_string()),
+            Token::Column(column) => match record {
+                Some(record) => code.push_str(&record_field(record, column).ok_or_else(|| {
+                    format!("El código necesita el campo '{}', que está vacío", column)
+                })?),
+                None => code.push_str(&format!("{{{}}}", column)),
+            },
+        }
+    }
+    Ok(code)
+}
+
+/// Valores actuales de la columna como texto
+fn existing_codes(conn: &Connection, table_name: &str, column_name: &str) -> Result<HashSet<String>, String> {
+    let column = quote_identifier(column_name);
+    let mut stmt = conn.prepare(&format!(
+        "SELECT CAST({} AS TEXT) FROM {} WHERE {} IS NOT NULL",
+        column,
+        quote_identifier(table_name),
+        column
+    )).map_err(|e| format!("Error al preparar la consulta de códigos: {}", e))?;
+    let codes = stmt.query_map([], |row| row.get::<_, String>(0))
+        .map_err(|e| format!("Error al consultar los códigos existentes: {}", e))?
+        .collect::<Result<HashSet<_>, _>>()
+        .map_err(|e| format!("Error al leer los códigos existentes: {}", e))?;
+    Ok(codes)
+}
+
+/// Crea la secuencia por omisión de una columna, continuando el mayor valor numérico
+fn create_default_sequence(conn: &Connection, table_name: &str, column_name: &str) -> Result<SequenceConfig, String> {
+    let column = quote_identifier(column_name);
+    let max: Option<i64> = conn.query_row(
+        &format!(
+            "SELECT MAX(CAST({} AS INTEGER)) FROM {} WHERE {} IS NOT NULL AND {} != ''",
+            column,
+            quote_identifier(table_name),
+            column,
+            column
+        ),
+        [],
+        |row| row.get(0),
+    ).map_err(|e| format!("Error al obtener el último valor de '{}': {}", column_name, e))?;
+
+    let config = SequenceConfig {
+        table_name: table_name.to_string(),
+        column_name: column_name.to_string(),
+        pattern: DEFAULT_PATTERN.to_string(),
+        next_value: max.unwrap_or(0) + 1,
+        reuse_gaps: false,
+        updated_at: Local::now().to_rfc3339(),
+    };
+    save_sequence(conn, &config)?;
+    Ok(config)
+}
+
+fn save_sequence(conn: &Connection, config: &SequenceConfig) -> Result<(), String> {
+    conn.execute(
+        &format!(
+            "INSERT INTO {} ({}) VALUES (?, ?, ?, ?, ?, ?)
+             ON CONFLICT(table_name, column_name) DO UPDATE SET
+                pattern = excluded.pattern,
+                next_value = excluded.next_value,
+                reuse_gaps = excluded.reuse_gaps,
+                updated_at = excluded.updated_at",
+            SEQUENCES_TABLE, SEQUENCE_COLUMNS
+        ),
+        rusqlite::params![
+            config.table_name,
+            config.column_name,
+            config.pattern,
+            config.next_value,
+            config.reuse_gaps as i64,
+            config.updated_at,
+        ],
+    ).map_err(|e| format!("Error al guardar la secuencia: {}", e))?;
+    Ok(())
+}
+
+/// Asigna el siguiente código de la columna para el registro `record`.
+///
+/// Debe llamarse dentro de la transacción de escritura (BEGIN IMMEDIATE) que
+/// inserta el registro, para que otra estación no obtenga el mismo código.
+/// Los números que ya estén en uso (ej. escritos a mano) se saltan.
+pub(crate) fn next_code(
+    conn: &Connection,
+    table_name: &str,
+    column_name: &str,
+    record: &HashMap<String, Value>,
+) -> Result<String, String> {
+    ensure_sequences_table(conn)?;
+    let mut config = match read_sequence(conn, table_name, column_name)? {
+        Some(config) => config,
+        None => create_default_sequence(conn, table_name, column_name)?,
+    };
+    let tokens = parse_pattern(&config.pattern)?;
+    let existing = existing_codes(conn, table_name, column_name)?;
+
+    let mut number = if config.reuse_gaps { 1 } else { config.next_value.max(1) };
+    let mut code = format_code(&tokens, number, Some(record))?;
+    while existing.contains(&code) {
+        number += 1;
+        code = format_code(&tokens, number, Some(record))?;
+    }
+
+    // Al reutilizar huecos el contador solo avanza si se pasó del último número
+    if number >= config.next_value {
+        config.next_value = number + 1;
+        save_sequence(conn, &config)?;
+    }
+    Ok(code)
+}
+
+/// Continúa el contador de una secuencia existente en `next_value`
+/// (ej. después de renumerar la columna)
+pub(crate) fn reset_counter(conn: &Connection, table_name: &str, column_name: &str, next_value: i64) -> Result<(), String> {
+    ensure_sequences_table(conn)?;
+    conn.execute(
+        &format!(
+            "UPDATE {} SET next_value = ?, updated_at = ? WHERE table_name = ? AND column_name = ?",
+            SEQUENCES_TABLE
+        ),
+        rusqlite::params![next_value, Local::now().to_rfc3339(), table_name, column_name],
+    ).map_err(|e| format!("Error al actualizar la secuencia: {}", e))?;
+    Ok(())
+}
+
+/// Código como valor JSON: número si el código es solo el contador
+pub(crate) fn code_to_json(code: &str) -> Value {
+    match code.parse::<i64>() {
+        Ok(n) if !code.starts_with('0') || code == "0" => Value::Number(n.into()),
+        _ => Value::String(code.to_string()),
+    }
+}
+
+fn open_database(state: &AppState, db_name: &str) -> Result<Connection, String> {
+    let db_path = state.db_dir.join(format!("{}.db", db_name));
+    let sqlite_path = state.db_dir.join(format!("{}.sqlite", db_name));
+
+    let db_file = if db_path.exists() {
+        db_path
+    } else if sqlite_path.exists() {
+        sqlite_path
+    } else {
+        return Err(format!("Base de datos no encontrada: {}", db_name));
+    };
+
+    Connection::open(&db_file)
+        .map_err(|e| format!("Error al abrir la base de datos: {}", e))
+}
+
+/// Lista las secuencias configuradas en la base de datos
+#[tauri::command]
+pub fn list_sequences(state: State<AppState>, db_name: String) -> Result<Vec<SequenceConfig>, String> {
+    let conn = open_database(&state, &db_name)?;
+    ensure_sequences_table(&conn)?;
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM {} ORDER BY table_name, column_name",
+        SEQUENCE_COLUMNS, SEQUENCES_TABLE
+    )).map_err(|e| format!("Error al preparar la consulta de secuencias: {}", e))?;
+    let sequences = stmt.query_map([], row_to_sequence)
+        .map_err(|e| format!("Error al consultar las secuencias: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Error al leer las secuencias: {}", e))?;
+    Ok(sequences)
+}
+
+/// Crea o modifica la secuencia de una columna
+#[tauri::command]
+pub fn set_sequence(
+    state: State<AppState>,
+    db_name: String,
+    sequence: SequenceInput,
+) -> Result<SequenceConfig, String> {
+    let mut conn = open_database(&state, &db_name)?;
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)
+        .map_err(|e| format!("Error al iniciar la transacción: {}", e))?;
+    ensure_sequences_table(&tx)?;
+
+    // Validar el formato y que las columnas que usa existan
+    let columns: Vec<String> = table_columns(&tx, &sequence.table_name)?.into_iter().map(|c| c.name).collect();
+    if !columns.contains(&sequence.column_name) {
+        return Err(format!("La columna '{}' no existe en la tabla", sequence.column_name));
+    }
+    for token in parse_pattern(&sequence.pattern)? {
+        if let Token::Column(name) = token {
+            if !columns.iter().any(|c| c.eq_ignore_ascii_case(&name)) {
+                return Err(format!("El formato usa la columna '{}', que no existe en la tabla", name));
+            }
+        }
+    }
+    if sequence.next_value.map_or(false, |n| n < 1) {
+        return Err("El contador debe empezar en 1 o más".to_string());
+    }
+
+    let next_value = match (sequence.next_value, read_sequence(&tx, &sequence.table_name, &sequence.column_name)?) {
+        (Some(n), _) => n,
+        (None, Some(existing)) => existing.next_value,
+        (None, None) => create_default_sequence(&tx, &sequence.table_name, &sequence.column_name)?.next_value,
+    };
+    let config = SequenceConfig {
+        table_name: sequence.table_name,
+        column_name: sequence.column_name,
+        pattern: sequence.pattern,
+        next_value,
+        reuse_gaps: sequence.reuse_gaps,
+        updated_at: Local::now().to_rfc3339(),
+    };
+    save_sequence(&tx, &config)?;
+    log_operation(&tx, "sequence", Some(&config.table_name), Some(&config.column_name), Some(&config.pattern))?;
+
+    tx.commit()
+        .map_err(|e| format!("Error al guardar los cambios: {}", e))?;
+    Ok(config)
+}
+
+/// Elimina la secuencia de una columna (volverá a crearse con el formato por omisión)
+#[tauri::command]
+pub fn delete_sequence(
+    state: State<AppState>,
+    db_name: String,
+    table_name: String,
+    column_name: String,
+) -> Result<bool, String> {
+    let conn = open_database(&state, &db_name)?;
+    ensure_sequences_table(&conn)?;
+    let affected = conn.execute(
+        &format!("DELETE FROM {} WHERE table_name = ? AND column_name = ?", SEQUENCES_TABLE),
+        [&table_name, &column_name],
+    ).map_err(|e| format!("Error al eliminar la secuencia: {}", e))?;
+    if affected > 0 {
+        log_operation(&conn, "sequence", Some(&table_name), Some(&column_name), Some("eliminada"))?;
+    }
+    Ok(affected > 0)
+}
+
+/// Muestra el código que recibiría un registro nuevo, sin consumir el contador.
+/// Sin `record`, los campos de columna del formato se muestran tal cual (ej. "{PLANTEL}").
+#[tauri::command]
+pub fn preview_sequence(
+    state: State<AppState>,
+    db_name: String,
+    table_name: String,
+    column_name: String,
+    record: Option<HashMap<String, Value>>,
+) -> Result<String, String> {
+    let mut conn = open_database(&state, &db_name)?;
+    // La transacción se descarta: el contador no avanza
+    let tx = conn.transaction()
+        .map_err(|e| format!("Error al iniciar la transacción: {}", e))?;
+
+    match record {
+        Some(record) => next_code(&tx, &table_name, &column_name, &record),
+        None => {
+            ensure_sequences_table(&tx)?;
+            let config = match read_sequence(&tx, &table_name, &column_name)? {
+                Some(config) => config,
+                None => create_default_sequence(&tx, &table_name, &column_name)?,
+            };
+            format_code(&parse_pattern(&config.pattern)?, config.next_value, None)
+        }
+    }
+}
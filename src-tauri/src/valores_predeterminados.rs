@@ -0,0 +1,322 @@
+/// =========================================================================
+/// Módulo: Valores predeterminados de los registros nuevos
+///
+/// Cada columna puede tener una regla que da su valor cuando el registro
+/// nuevo no lo trae (el campo falta o es null):
+/// - `constant`: un valor fijo
+/// - `today`: la fecha actual (AAAA-MM-DD)
+/// - `now`: la fecha y hora actual (RFC 3339)
+/// - `station`: el nombre del equipo que crea el registro
+/// - `user`: el usuario del sistema operativo
+/// - `last_record`: el valor de la columna en el último registro creado
+///
+/// Las reglas se guardan en la tabla interna `_unea_column_defaults`.
+/// Si una columna no tiene regla queda en NULL.
+/// =========================================================================
+
+use std::collections::HashMap;
+
+use chrono::Local;
+use rusqlite::{Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::State;
+
+use crate::auditoria::{current_user, log_operation, station_name};
+use crate::codec_valores::{find_codec, table_codecs, ColumnCodec};
+use crate::database_manager::AppState;
+
+/// Tabla interna con las reglas
+pub(crate) const DEFAULTS_TABLE: &str = "_unea_column_defaults";
+
+fn quote_identifier(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace("\"", "\"\""))
+}
+
+/// Tipo de regla
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DefaultKind {
+    Constant,
+    Today,
+    Now,
+    Station,
+    User,
+    LastRecord,
+}
+
+impl DefaultKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            DefaultKind::Constant => "constant",
+            DefaultKind::Today => "today",
+            DefaultKind::Now => "now",
+            DefaultKind::Station => "station",
+            DefaultKind::User => "user",
+            DefaultKind::LastRecord => "last_record",
+        }
+    }
+
+    fn parse(kind: &str) -> Option<Self> {
+        match kind {
+            "constant" => Some(DefaultKind::Constant),
+            "today" => Some(DefaultKind::Today),
+            "now" => Some(DefaultKind::Now),
+            "station" => Some(DefaultKind::Station),
+            "user" => Some(DefaultKind::User),
+            "last_record" => Some(DefaultKind::LastRecord),
+            _ => None,
+        }
+    }
+}
+
+/// Regla tal como se envía al frontend
+#[derive(Debug, Serialize, Clone)]
+pub struct ColumnDefault {
+    pub table_name: String,
+    pub column_name: String,
+    pub kind: DefaultKind,
+    /// Valor fijo (solo para `constant`)
+    pub value: Option<Value>,
+    /// Fecha de la última modificación (RFC 3339)
+    pub updated_at: String,
+}
+
+/// Datos que envía el frontend para crear o modificar una regla
+#[derive(Debug, Deserialize)]
+pub struct ColumnDefaultInput {
+    pub table_name: String,
+    pub column_name: String,
+    pub kind: DefaultKind,
+    #[serde(default)]
+    pub value: Option<Value>,
+}
+
+fn ensure_defaults_table(conn: &Connection) -> Result<(), String> {
+    conn.execute(
+        &format!(
+            "CREATE TABLE IF NOT EXISTS {} (
+                table_name TEXT NOT NULL,
+                column_name TEXT NOT NULL,
+                kind TEXT NOT NULL,
+                value TEXT,
+                updated_at TEXT NOT NULL,
+                PRIMARY KEY (table_name, column_name)
+            )",
+            DEFAULTS_TABLE
+        ),
+        [],
+    ).map_err(|e| format!("Error al crear la tabla de valores predeterminados: {}", e))?;
+    Ok(())
+}
+
+fn row_to_default(row: &rusqlite::Row) -> rusqlite::Result<ColumnDefault> {
+    let kind: String = row.get(2)?;
+    let value: Option<String> = row.get(3)?;
+    Ok(ColumnDefault {
+        table_name: row.get(0)?,
+        column_name: row.get(1)?,
+        // Una regla desconocida (de una versión más nueva) se trata como valor fijo
+        kind: DefaultKind::parse(&kind).unwrap_or(DefaultKind::Constant),
+        value: value.and_then(|v| serde_json::from_str(&v).ok()),
+        updated_at: row.get(4)?,
+    })
+}
+
+fn read_defaults(conn: &Connection, table_name: Option<&str>) -> Result<Vec<ColumnDefault>, String> {
+    let exists: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name=?",
+        [DEFAULTS_TABLE],
+        |row| row.get(0),
+    ).map_err(|e| format!("Error al consultar los valores predeterminados: {}", e))?;
+    if exists == 0 {
+        return Ok(Vec::new());
+    }
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT table_name, column_name, kind, value, updated_at FROM {}
+         WHERE ?1 IS NULL OR table_name = ?1
+         ORDER BY table_name, column_name",
+        DEFAULTS_TABLE
+    )).map_err(|e| format!("Error al preparar la consulta de valores predeterminados: {}", e))?;
+    let defaults = stmt.query_map([table_name], row_to_default)
+        .map_err(|e| format!("Error al consultar los valores predeterminados: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Error al leer los valores predeterminados: {}", e))?;
+    Ok(defaults)
+}
+
+/// Valor de la columna en el último registro creado (mayor rowid)
+fn last_record_value(conn: &Connection, table_name: &str, codec: &ColumnCodec) -> Result<Value, String> {
+    let value = conn.query_row(
+        &format!(
+            "SELECT {} FROM {} ORDER BY rowid DESC LIMIT 1",
+            quote_identifier(&codec.name),
+            quote_identifier(table_name)
+        ),
+        [],
+        |row| Ok(codec.decode(row.get_ref(0)?)),
+    )
+    .optional()
+    .map_err(|e| format!("Error al leer el último registro de '{}': {}", table_name, e))?;
+    Ok(value.unwrap_or(Value::Null))
+}
+
+/// Valor que da la regla para un registro nuevo
+fn resolve(conn: &Connection, table_name: &str, rule: &ColumnDefault, codec: &ColumnCodec) -> Result<Value, String> {
+    Ok(match rule.kind {
+        DefaultKind::Constant => rule.value.clone().unwrap_or(Value::Null),
+        DefaultKind::Today => Value::String(Local::now().format("%Y-%m-%d").to_string()),
+        DefaultKind::Now => Value::String(Local::now().to_rfc3339()),
+        DefaultKind::Station => Value::String(station_name()),
+        DefaultKind::User => Value::String(current_user()),
+        DefaultKind::LastRecord => last_record_value(conn, table_name, codec)?,
+    })
+}
+
+/// Completa el registro nuevo con los valores predeterminados de la tabla.
+/// Solo se llenan los campos que faltan o vienen en null; se llama dentro de
+/// la transacción que inserta el registro y antes de asignar su código.
+pub(crate) fn apply_defaults(
+    conn: &Connection,
+    table_name: &str,
+    record: &mut HashMap<String, Value>,
+) -> Result<(), String> {
+    let rules = read_defaults(conn, Some(table_name))?;
+    if rules.is_empty() {
+        return Ok(());
+    }
+    let codecs = table_codecs(conn, table_name)?;
+    for rule in &rules {
+        if !record.get(&rule.column_name).map_or(true, Value::is_null) {
+            continue;
+        }
+        // Reglas de columnas que ya no existen se ignoran
+        let Some(codec) = codecs.iter().find(|c| c.name == rule.column_name) else {
+            continue;
+        };
+        let value = resolve(conn, table_name, rule, codec)?;
+        if !value.is_null() {
+            record.insert(rule.column_name.clone(), value);
+        }
+    }
+    Ok(())
+}
+
+fn open_database(state: &AppState, db_name: &str) -> Result<Connection, String> {
+    let db_path = state.db_dir.join(format!("{}.db", db_name));
+    let sqlite_path = state.db_dir.join(format!("{}.sqlite", db_name));
+
+    let db_file = if db_path.exists() {
+        db_path
+    } else if sqlite_path.exists() {
+        sqlite_path
+    } else {
+        return Err(format!("Base de datos no encontrada: {}", db_name));
+    };
+
+    Connection::open(&db_file)
+        .map_err(|e| format!("Error al abrir la base de datos: {}", e))
+}
+
+/// Lista las reglas de una tabla (o de toda la base de datos)
+#[tauri::command]
+pub fn list_column_defaults(
+    state: State<AppState>,
+    db_name: String,
+    table_name: Option<String>,
+) -> Result<Vec<ColumnDefault>, String> {
+    let conn = open_database(&state, &db_name)?;
+    read_defaults(&conn, table_name.as_deref())
+}
+
+/// Crea o modifica la regla de una columna
+#[tauri::command]
+pub fn set_column_default(
+    state: State<AppState>,
+    db_name: String,
+    column_default: ColumnDefaultInput,
+) -> Result<ColumnDefault, String> {
+    let conn = open_database(&state, &db_name)?;
+    ensure_defaults_table(&conn)?;
+
+    let codecs = table_codecs(&conn, &column_default.table_name)?;
+    let codec = find_codec(&codecs, &column_default.column_name)?;
+
+    // El valor fijo debe poder guardarse en la columna
+    let value = match column_default.kind {
+        DefaultKind::Constant => {
+            let value = column_default.value.filter(|v| !v.is_null())
+                .ok_or("Indique el valor fijo de la columna")?;
+            codec.encode(&value)?;
+            Some(value)
+        }
+        _ => None,
+    };
+
+    let rule = ColumnDefault {
+        table_name: column_default.table_name,
+        column_name: column_default.column_name,
+        kind: column_default.kind,
+        value,
+        updated_at: Local::now().to_rfc3339(),
+    };
+    conn.execute(
+        &format!(
+            "INSERT INTO {} (table_name, column_name, kind, value, updated_at) VALUES (?, ?, ?, ?, ?)
+             ON CONFLICT(table_name, column_name) DO UPDATE SET
+                kind = excluded.kind,
+                value = excluded.value,
+                updated_at = excluded.updated_at",
+            DEFAULTS_TABLE
+        ),
+        rusqlite::params![
+            rule.table_name,
+            rule.column_name,
+            rule.kind.as_str(),
+            rule.value.as_ref().map(|v| v.to_string()),
+            rule.updated_at,
+        ],
+    ).map_err(|e| format!("Error al guardar el valor predeterminado: {}", e))?;
+
+    let detail = match &rule.value {
+        Some(value) => format!("{}: {}", rule.kind.as_str(), value),
+        None => rule.kind.as_str().to_string(),
+    };
+    log_operation(&conn, "column_default", Some(&rule.table_name), Some(&rule.column_name), Some(&detail))?;
+
+    Ok(rule)
+}
+
+/// Elimina la regla de una columna (la columna quedará en NULL en los registros nuevos)
+#[tauri::command]
+pub fn delete_column_default(
+    state: State<AppState>,
+    db_name: String,
+    table_name: String,
+    column_name: String,
+) -> Result<bool, String> {
+    let conn = open_database(&state, &db_name)?;
+    ensure_defaults_table(&conn)?;
+    let affected = conn.execute(
+        &format!("DELETE FROM {} WHERE table_name = ? AND column_name = ?", DEFAULTS_TABLE),
+        [&table_name, &column_name],
+    ).map_err(|e| format!("Error al eliminar el valor predeterminado: {}", e))?;
+    if affected > 0 {
+        log_operation(&conn, "column_default", Some(&table_name), Some(&column_name), Some("eliminado"))?;
+    }
+    Ok(affected > 0)
+}
+
+/// Valores con los que se llena el formulario de un registro nuevo
+#[tauri::command]
+pub fn get_new_record_defaults(
+    state: State<AppState>,
+    db_name: String,
+    table_name: String,
+) -> Result<HashMap<String, Value>, String> {
+    let conn = open_database(&state, &db_name)?;
+    let mut record = HashMap::new();
+    apply_defaults(&conn, &table_name, &mut record)?;
+    Ok(record)
+}
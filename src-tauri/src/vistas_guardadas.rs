@@ -0,0 +1,431 @@
+/// =========================================================================
+/// Módulo: Vistas guardadas
+///
+/// Funcionalidades:
+/// - Guardar combinaciones de filtro, orden y columnas visibles por tabla
+///   (ej. "Equipos en reparación del plantel Centro", "Sin responsable")
+/// - Listar, actualizar, eliminar y ejecutar vistas
+///
+/// Las vistas se guardan dentro de la propia base de datos en la tabla
+/// `_unea_saved_views`, de modo que viajan con el archivo `.db`.
+/// Una vista compartida (`shared`) la ven todas las estaciones; una vista
+/// privada solo la ve su propietario (`owner`).
+/// =========================================================================
+
+use rusqlite::types::Value as SqlValue;
+use rusqlite::{Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use chrono::Local;
+
+use crate::consulta_tablas::{query_table_data, TableData};
+use crate::database_manager::AppState;
+use crate::filtros::{self, FilterNode, SortSpec};
+use crate::papelera::active_rows_condition;
+
+/// Nombre de la tabla interna donde se guardan las vistas.
+pub(crate) const VIEWS_TABLE: &str = "_unea_saved_views";
+
+fn quote_identifier(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace("\"", "\"\""))
+}
+
+/// Vista guardada tal como se envía al frontend
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SavedView {
+    /// Identificador de la vista
+    pub id: i64,
+    /// Tabla sobre la que se aplica la vista
+    pub table_name: String,
+    /// Nombre visible de la vista
+    pub name: String,
+    /// Árbol de filtros (None = sin filtro)
+    pub filter: Option<FilterNode>,
+    /// Criterios de ordenamiento
+    pub sort: Vec<SortSpec>,
+    /// Columnas visibles en orden (vacío = todas)
+    pub columns: Vec<String>,
+    /// Si la vista es visible para todas las estaciones
+    pub shared: bool,
+    /// Estación o usuario que creó la vista
+    pub owner: Option<String>,
+    /// Fecha de creación (RFC 3339)
+    pub created_at: String,
+    /// Fecha de última modificación (RFC 3339)
+    pub updated_at: String,
+}
+
+/// Datos que envía el frontend para crear o actualizar una vista
+#[derive(Debug, Deserialize)]
+pub struct SavedViewInput {
+    pub table_name: String,
+    pub name: String,
+    #[serde(default)]
+    pub filter: Option<FilterNode>,
+    #[serde(default)]
+    pub sort: Vec<SortSpec>,
+    #[serde(default)]
+    pub columns: Vec<String>,
+    #[serde(default)]
+    pub shared: bool,
+    #[serde(default)]
+    pub owner: Option<String>,
+}
+
+/// Crea la tabla de vistas si todavía no existe
+pub(crate) fn ensure_views_table(conn: &Connection) -> Result<(), String> {
+    conn.execute(
+        &format!(
+            "CREATE TABLE IF NOT EXISTS \"{}\" (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                table_name TEXT NOT NULL,
+                name TEXT NOT NULL,
+                filter TEXT,
+                sort TEXT NOT NULL DEFAULT '[]',
+                columns TEXT NOT NULL DEFAULT '[]',
+                shared INTEGER NOT NULL DEFAULT 0,
+                owner TEXT,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL,
+                UNIQUE (table_name, name)
+            )",
+            VIEWS_TABLE
+        ),
+        [],
+    ).map_err(|e| format!("Error al crear la tabla de vistas: {}", e))?;
+    Ok(())
+}
+
+/// Convierte una fila de `_unea_saved_views` en `SavedView`
+fn row_to_view(row: &rusqlite::Row) -> rusqlite::Result<SavedView> {
+    let filter_json: Option<String> = row.get(3)?;
+    let sort_json: String = row.get(4)?;
+    let columns_json: String = row.get(5)?;
+    let shared: i64 = row.get(6)?;
+
+    Ok(SavedView {
+        id: row.get(0)?,
+        table_name: row.get(1)?,
+        name: row.get(2)?,
+        // Un JSON dañado no debe impedir listar las demás vistas
+        filter: filter_json.and_then(|f| serde_json::from_str(&f).ok()),
+        sort: serde_json::from_str(&sort_json).unwrap_or_default(),
+        columns: serde_json::from_str(&columns_json).unwrap_or_default(),
+        shared: shared != 0,
+        owner: row.get(7)?,
+        created_at: row.get(8)?,
+        updated_at: row.get(9)?,
+    })
+}
+
+const VIEW_COLUMNS: &str = "id, table_name, name, filter, sort, columns, shared, owner, created_at, updated_at";
+
+/// Obtiene una vista por su id
+pub(crate) fn load_view(conn: &Connection, view_id: i64) -> Result<SavedView, String> {
+    ensure_views_table(conn)?;
+    conn.query_row(
+        &format!("SELECT {} FROM \"{}\" WHERE id = ?", VIEW_COLUMNS, VIEWS_TABLE),
+        [view_id],
+        row_to_view,
+    )
+    .optional()
+    .map_err(|e| format!("Error al leer la vista: {}", e))?
+    .ok_or_else(|| format!("No se encontró la vista con id {}", view_id))
+}
+
+/// Partes SQL de una vista sobre su tabla, para armar el SELECT que convenga
+pub(crate) struct ViewSql {
+    /// Todas las columnas de la tabla, incluidas las de control
+    pub table_columns: Vec<String>,
+    /// Columnas elegidas por la vista, en su orden (vacío = todas)
+    pub columns: Vec<String>,
+    /// " WHERE ..." con el filtro y, con borrado lógico, solo registros activos
+    pub where_sql: String,
+    /// " ORDER BY ..." o vacío
+    pub order_sql: String,
+    pub params: Vec<SqlValue>,
+    /// Indica si la tabla tiene borrado lógico
+    pub soft_delete: bool,
+}
+
+/// Traduce filtro, orden y columnas de una vista a SQL validado contra la tabla.
+/// Es la base de la ejecución de vistas, de la exportación y de las agregaciones.
+pub(crate) fn view_sql(
+    conn: &Connection,
+    table_name: &str,
+    filter: Option<&FilterNode>,
+    sort: &[SortSpec],
+    columns: &[String],
+) -> Result<ViewSql, String> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", quote_identifier(table_name)))
+        .map_err(|e| format!("Error al preparar la consulta de columnas: {}", e))?;
+    let table_columns: Vec<String> = stmt.query_map([], |row| row.get::<_, String>(1))
+        .map_err(|e| format!("Error al ejecutar la consulta de columnas: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Error al obtener columnas: {}", e))?;
+
+    if table_columns.is_empty() {
+        return Err(format!("No se encontró la tabla: {}", table_name));
+    }
+    if let Some(col) = columns.iter().find(|c| !table_columns.contains(c)) {
+        return Err(format!("La columna '{}' no existe en la tabla", col));
+    }
+
+    let mut params = Vec::new();
+    let mut where_sql = filtros::where_clause(filter, &table_columns, &mut params)?;
+    // Los registros en la papelera no forman parte de ninguna vista
+    let active_condition = active_rows_condition(conn, table_name)?;
+    if let Some(active) = &active_condition {
+        where_sql = if where_sql.is_empty() {
+            format!(" WHERE {}", active)
+        } else {
+            format!("{} AND {}", where_sql, active)
+        };
+    }
+    let order_sql = filtros::order_clause(sort, &table_columns)?;
+
+    Ok(ViewSql {
+        table_columns,
+        columns: columns.to_vec(),
+        where_sql,
+        order_sql,
+        params,
+        soft_delete: active_condition.is_some(),
+    })
+}
+
+/// Ejecuta una vista sobre una conexión abierta y devuelve sus filas
+pub(crate) fn run_view(conn: &Connection, db_name: &str, view: &SavedView) -> Result<TableData, String> {
+    query_table_data(
+        conn,
+        db_name,
+        view.table_name.clone(),
+        view.filter.as_ref(),
+        &view.sort,
+        Some(&view.columns),
+    )
+}
+
+/// Comprueba que la vista se pueda ejecutar antes de guardarla
+/// (tabla, columnas, filtro y orden válidos) sin leer los datos.
+fn validate_view(conn: &Connection, input: &SavedViewInput) -> Result<(), String> {
+    if input.name.trim().is_empty() {
+        return Err("La vista debe tener un nombre.".to_string());
+    }
+
+    view_sql(conn, &input.table_name, input.filter.as_ref(), &input.sort, &input.columns)?;
+    Ok(())
+}
+
+/// Crea una nueva vista guardada
+#[tauri::command]
+pub fn create_saved_view(
+    state: State<AppState>,
+    db_name: String,
+    view: SavedViewInput,
+) -> Result<SavedView, String> {
+    let db_path = state.db_dir.join(format!("{}.db", db_name));
+    let sqlite_path = state.db_dir.join(format!("{}.sqlite", db_name));
+
+    let db_file = if db_path.exists() {
+        db_path
+    } else if sqlite_path.exists() {
+        sqlite_path
+    } else {
+        return Err(format!("No se encontró la base de datos: {}", db_name));
+    };
+
+    let conn = Connection::open(&db_file)
+        .map_err(|e| format!("Error al abrir la base de datos: {}", e))?;
+
+    ensure_views_table(&conn)?;
+    validate_view(&conn, &view)?;
+
+    let now = Local::now().to_rfc3339();
+    let filter_json = match &view.filter {
+        Some(f) => Some(serde_json::to_string(f).map_err(|e| e.to_string())?),
+        None => None,
+    };
+
+    conn.execute(
+        &format!(
+            "INSERT INTO \"{}\" (table_name, name, filter, sort, columns, shared, owner, created_at, updated_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            VIEWS_TABLE
+        ),
+        rusqlite::params![
+            view.table_name,
+            view.name,
+            filter_json,
+            serde_json::to_string(&view.sort).map_err(|e| e.to_string())?,
+            serde_json::to_string(&view.columns).map_err(|e| e.to_string())?,
+            view.shared as i64,
+            view.owner,
+            now,
+            now,
+        ],
+    ).map_err(|e| {
+        if e.to_string().contains("UNIQUE") {
+            format!("Ya existe una vista llamada '{}' para la tabla '{}'", view.name, view.table_name)
+        } else {
+            format!("Error al guardar la vista: {}", e)
+        }
+    })?;
+
+    load_view(&conn, conn.last_insert_rowid())
+}
+
+/// Lista las vistas de una tabla: las compartidas y las del propietario indicado.
+/// Sin `owner` se devuelven todas.
+#[tauri::command]
+pub fn list_saved_views(
+    state: State<AppState>,
+    db_name: String,
+    table_name: String,
+    owner: Option<String>,
+) -> Result<Vec<SavedView>, String> {
+    let db_path = state.db_dir.join(format!("{}.db", db_name));
+    let sqlite_path = state.db_dir.join(format!("{}.sqlite", db_name));
+
+    let db_file = if db_path.exists() {
+        db_path
+    } else if sqlite_path.exists() {
+        sqlite_path
+    } else {
+        return Err(format!("No se encontró la base de datos: {}", db_name));
+    };
+
+    let conn = Connection::open(&db_file)
+        .map_err(|e| format!("Error al abrir la base de datos: {}", e))?;
+
+    ensure_views_table(&conn)?;
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM \"{}\" WHERE table_name = ?1 AND (?2 IS NULL OR shared = 1 OR owner = ?2) ORDER BY name",
+        VIEW_COLUMNS, VIEWS_TABLE
+    )).map_err(|e| format!("Error al preparar la consulta de vistas: {}", e))?;
+
+    let views = stmt.query_map(rusqlite::params![table_name, owner], row_to_view)
+        .map_err(|e| format!("Error al consultar las vistas: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Error al leer las vistas: {}", e))?;
+
+    Ok(views)
+}
+
+/// Actualiza una vista existente
+#[tauri::command]
+pub fn update_saved_view(
+    state: State<AppState>,
+    db_name: String,
+    view_id: i64,
+    view: SavedViewInput,
+) -> Result<SavedView, String> {
+    let db_path = state.db_dir.join(format!("{}.db", db_name));
+    let sqlite_path = state.db_dir.join(format!("{}.sqlite", db_name));
+
+    let db_file = if db_path.exists() {
+        db_path
+    } else if sqlite_path.exists() {
+        sqlite_path
+    } else {
+        return Err(format!("No se encontró la base de datos: {}", db_name));
+    };
+
+    let conn = Connection::open(&db_file)
+        .map_err(|e| format!("Error al abrir la base de datos: {}", e))?;
+
+    // Verifica que exista antes de validar
+    load_view(&conn, view_id)?;
+    validate_view(&conn, &view)?;
+
+    let filter_json = match &view.filter {
+        Some(f) => Some(serde_json::to_string(f).map_err(|e| e.to_string())?),
+        None => None,
+    };
+
+    conn.execute(
+        &format!(
+            "UPDATE \"{}\" SET table_name = ?, name = ?, filter = ?, sort = ?, columns = ?, shared = ?, owner = ?, updated_at = ?
+             WHERE id = ?",
+            VIEWS_TABLE
+        ),
+        rusqlite::params![
+            view.table_name,
+            view.name,
+            filter_json,
+            serde_json::to_string(&view.sort).map_err(|e| e.to_string())?,
+            serde_json::to_string(&view.columns).map_err(|e| e.to_string())?,
+            view.shared as i64,
+            view.owner,
+            Local::now().to_rfc3339(),
+            view_id,
+        ],
+    ).map_err(|e| {
+        if e.to_string().contains("UNIQUE") {
+            format!("Ya existe una vista llamada '{}' para la tabla '{}'", view.name, view.table_name)
+        } else {
+            format!("Error al actualizar la vista: {}", e)
+        }
+    })?;
+
+    load_view(&conn, view_id)
+}
+
+/// Elimina una vista guardada
+#[tauri::command]
+pub fn delete_saved_view(
+    state: State<AppState>,
+    db_name: String,
+    view_id: i64,
+) -> Result<(), String> {
+    let db_path = state.db_dir.join(format!("{}.db", db_name));
+    let sqlite_path = state.db_dir.join(format!("{}.sqlite", db_name));
+
+    let db_file = if db_path.exists() {
+        db_path
+    } else if sqlite_path.exists() {
+        sqlite_path
+    } else {
+        return Err(format!("No se encontró la base de datos: {}", db_name));
+    };
+
+    let conn = Connection::open(&db_file)
+        .map_err(|e| format!("Error al abrir la base de datos: {}", e))?;
+
+    ensure_views_table(&conn)?;
+
+    let rows_affected = conn.execute(&format!("DELETE FROM \"{}\" WHERE id = ?", VIEWS_TABLE), [view_id])
+        .map_err(|e| format!("Error al eliminar la vista: {}", e))?;
+
+    if rows_affected == 0 {
+        return Err(format!("No se encontró la vista con id {}", view_id));
+    }
+
+    Ok(())
+}
+
+/// Ejecuta una vista guardada y devuelve los datos con el mismo formato que `consulta_tabla`
+#[tauri::command]
+pub fn run_saved_view(
+    state: State<AppState>,
+    db_name: String,
+    view_id: i64,
+) -> Result<TableData, String> {
+    let db_path = state.db_dir.join(format!("{}.db", db_name));
+    let sqlite_path = state.db_dir.join(format!("{}.sqlite", db_name));
+
+    let db_file = if db_path.exists() {
+        db_path
+    } else if sqlite_path.exists() {
+        sqlite_path
+    } else {
+        return Err(format!("No se encontró la base de datos: {}", db_name));
+    };
+
+    let conn = Connection::open(&db_file)
+        .map_err(|e| format!("Error al abrir la base de datos: {}", e))?;
+
+    let view = load_view(&conn, view_id)?;
+    run_view(&conn, &db_name, &view)
+}
@@ -32,8 +32,7 @@ interface ConsultaTablaFrontProps {
   onSaveRow?: (
     pk: { name: string; value: any },
     updatedData: Record<string, any>,
-    columnTypes?: Record<string, string>,
-    columnNotNull?: Record<string, number>
+    originalValues?: Record<string, any>
   ) => Promise<boolean>;
   onDeleteRow?: (pk: { name: string; value: any }) => Promise<boolean>;
   searchTerm?: string;
@@ -232,7 +231,8 @@ const TablaSegura: React.FC<ConsultaTablaFrontProps> = memo(({
     });
 
     try {
-      const ok = await onSaveRow(pk, payload, columnTypes, columnNotNull);
+      // Valores de la fila tal como se leyeron, para detectar cambios de otra estación
+      const ok = await onSaveRow(pk, payload, processedRows[editingRowId]);
 
       if (ok) {
         // Add a small delay to ensure database transaction completes
@@ -246,7 +246,7 @@ const TablaSegura: React.FC<ConsultaTablaFrontProps> = memo(({
     } catch (e) {
       toast.error(`Error al guardar: ${e}`);
     }
-  }, [editingRowId, editData, onSaveRow, getRowPK, fetchTableData, isImageColumn, columnNotNull]);
+  }, [editingRowId, editData, onSaveRow, getRowPK, fetchTableData, isImageColumn, columnNotNull, processedRows]);
 
   const handleConfirmDelete = useCallback(async () => {
     if (selectedRowId === null || selectedRowId < 0) return;
@@ -475,7 +475,7 @@ const TablaSegura: React.FC<ConsultaTablaFrontProps> = memo(({
                                 />
                               )
                             ) : (
-                              isImage && row[col] && typeof row[col] === 'string' && row[col] !== null && row[col].startsWith('data:image/') ? (
+                              isImage && row[col] && typeof row[col] === 'string' && row[col] !== null && (row[col].startsWith('data:image/') || row[col].includes('unea-img')) ? (
                                 <img
                                   src={row[col]}
                                   alt={col}
@@ -1,4 +1,5 @@
 import { useState } from "react";
+import { createPortal } from "react-dom";
 import { useNavigate, useLocation } from "react-router-dom";
 import { invoke } from '@tauri-apps/api/tauri';
 import toast, { Toaster } from "react-hot-toast";
@@ -6,6 +7,27 @@ import TablaSegura from '../../components/ui/TablaSegura';
 import '../../styles/estilosPaginatsx.css';
 
 
+// Conflicto devuelto por update_table_row cuando otra estación modificó el registro
+interface EditConflict {
+  message: string;
+  changed_columns: string[];
+  current_values: Record<string, any> | null;
+}
+
+// Combinación pendiente: el usuario elige, por columna, su valor o el actual
+interface PendingMerge {
+  conflict: EditConflict;
+  mine: Record<string, any>;
+  choices: Record<string, 'mine' | 'theirs'>;
+  resolve: (merged: Record<string, any> | null) => void;
+}
+
+const displayValue = (value: any) => {
+  if (value === null || value === undefined) return '(vacío)';
+  if (typeof value === 'string' && value.includes('unea-img')) return '(imagen)';
+  return String(value);
+};
+
 const Pagina = () => {
   const navigate = useNavigate();
   const location = useLocation();
@@ -13,13 +35,38 @@ const Pagina = () => {
 
   const [searchTerm, setSearchTerm] = useState("");
   const [selectedRowId, setSelectedRowId] = useState<number | null>(null);
+  const [pendingMerge, setPendingMerge] = useState<PendingMerge | null>(null);
 
   const handleRowSelect = (rowId: number) => {
     const newSelectedId = selectedRowId === rowId ? null : rowId;
     setSelectedRowId(newSelectedId);
   };
 
-  const handleSaveRow = async (pk: { name: string, value: any }, updatedData: Record<string, any>, columnTypes?: Record<string, string>, columnNotNull?: Record<string, number>): Promise<boolean> => {
+  // Muestra el diálogo de combinación y espera la decisión del usuario
+  const askMerge = (conflict: EditConflict, mine: Record<string, any>, original?: Record<string, any>) =>
+    new Promise<Record<string, any> | null>(resolve => {
+      const choices: Record<string, 'mine' | 'theirs'> = {};
+      conflict.changed_columns.forEach(col => {
+        // Si el usuario no tocó la columna, se conserva el valor de la otra estación
+        const edited = col in mine && String(mine[col] ?? '') !== String(original?.[col] ?? '');
+        choices[col] = edited ? 'mine' : 'theirs';
+      });
+      setPendingMerge({ conflict, mine, choices, resolve });
+    });
+
+  const finishMerge = (accept: boolean) => {
+    if (!pendingMerge) return;
+    const { conflict, mine, choices, resolve } = pendingMerge;
+    setPendingMerge(null);
+    if (!accept) return resolve(null);
+    const merged = { ...mine };
+    Object.entries(choices).forEach(([col, choice]) => {
+      if (choice === 'theirs' && col in merged) merged[col] = conflict.current_values?.[col] ?? null;
+    });
+    resolve(merged);
+  };
+
+  const handleSaveRow = async (pk: { name: string, value: any }, updatedData: Record<string, any>, originalValues?: Record<string, any>): Promise<boolean> => {
     try {
       if (!dbName || !tableName) throw new Error('Nombre de base de datos o tabla no especificado.');
       if (!pk || pk.name === undefined || pk.value === undefined) throw new Error('Clave primaria inválida.');
@@ -32,8 +79,7 @@ const Pagina = () => {
         pkColumn: pk.name,
         pkValue: pk.value,
         updates: updatedData,
-        columnTypes,
-        columnNotNull,
+        originalValues,
       });
 
       console.log('Update result:', result);
@@ -41,6 +87,19 @@ const Pagina = () => {
     } catch (error) {
       console.error('Error updating row:', error);
       const errorMessage = error instanceof Error ? error.message : String(error);
+      // Conflicto de edición: otra estación modificó el registro
+      if (errorMessage.startsWith('CONFLICTO:')) {
+        const conflict: EditConflict = JSON.parse(errorMessage.slice('CONFLICTO:'.length));
+        // Registro eliminado por otra estación: no hay nada con qué combinar
+        if (!conflict.current_values) {
+          toast.error(conflict.message);
+          return false;
+        }
+        const merged = await askMerge(conflict, updatedData, originalValues);
+        if (!merged) return false;
+        // Se reintenta con los valores actuales como nueva base
+        return handleSaveRow(pk, merged, conflict.current_values);
+      }
       toast.error(`Error al guardar: ${errorMessage}`);
       return false;
     }
@@ -143,6 +202,70 @@ const Pagina = () => {
         )}
       </main>
 
+      {pendingMerge && createPortal(
+        <div className="modal-overlay">
+          <div className="modal-container">
+            <div className="modal-header">
+              <h3>Conflicto de edición</h3>
+            </div>
+            <div className="modal-body">
+              <div className="modal-message">{pendingMerge.conflict.message}</div>
+              <table className="dark-grid-grid">
+                <thead>
+                  <tr>
+                    <th className="dark-grid-th">Campo</th>
+                    <th className="dark-grid-th">Su valor</th>
+                    <th className="dark-grid-th">Valor actual</th>
+                  </tr>
+                </thead>
+                <tbody>
+                  {pendingMerge.conflict.changed_columns.map(col => (
+                    <tr key={col} className="dark-grid-tr">
+                      <td className="dark-grid-td">{col}</td>
+                      {(['mine', 'theirs'] as const).map(choice => (
+                        <td key={choice} className="dark-grid-td">
+                          <label>
+                            <input
+                              type="radio"
+                              name={`merge-${col}`}
+                              checked={pendingMerge.choices[col] === choice}
+                              onChange={() => setPendingMerge({
+                                ...pendingMerge,
+                                choices: { ...pendingMerge.choices, [col]: choice },
+                              })}
+                            />
+                            {' '}
+                            {displayValue(choice === 'mine'
+                              ? pendingMerge.mine[col]
+                              : pendingMerge.conflict.current_values?.[col])}
+                          </label>
+                        </td>
+                      ))}
+                    </tr>
+                  ))}
+                </tbody>
+              </table>
+            </div>
+            <div className="modal-footer">
+              <button
+                className="glass-button"
+                onClick={() => finishMerge(true)}
+                style={{
+                  background: 'var(--primary-color)',
+                  color: 'white'
+                }}
+              >
+                Guardar combinación
+              </button>
+              <button className="glass-button" onClick={() => finishMerge(false)}>
+                Cancelar
+              </button>
+            </div>
+          </div>
+        </div>,
+        document.body
+      )}
+
       <Toaster position="bottom-center" containerStyle={{ zIndex: 99999, bottom: '50px' }} />
     </div>
   );
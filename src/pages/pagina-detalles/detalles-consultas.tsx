@@ -9,6 +9,7 @@ import styles from './detalles-consultas.module.css';
 interface RecordDetails {
   table_name: string;
   record: Record<string, any>;
+  image_urls?: Record<string, string>;
 }
 
 interface ColumnInfo {
@@ -120,7 +121,10 @@ const DetallesConsultas: React.FC = () => {
   const renderFieldValue = (columnName: string, value: any) => {
     if (isImageColumn(columnName) && value) {
       let imageSrc = '';
-      if (typeof value === 'string') {
+      const imageUrl = recordDetails?.image_urls?.[columnName];
+      if (imageUrl) {
+        imageSrc = imageUrl;
+      } else if (typeof value === 'string') {
         imageSrc = value.startsWith('data:image/') ? value : `data:image/png;base64,${value}`;
       } else {
         imageSrc = `data:image/png;base64,${value}`;
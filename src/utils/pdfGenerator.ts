@@ -207,7 +207,10 @@ export const generateBulkPDF = async (
         yPos += 5;
 
         try {
-          const imgBase64 = String(value).startsWith('data:image') ? value : `data:image/png;base64,${value}`;
+          // Las imágenes llegan como URL unea-img://; jsPDF necesita el contenido en base64
+          const imgBase64 = String(value).includes('unea-img')
+            ? (await getLogoData(String(value))).base64
+            : String(value).startsWith('data:image') ? value : `data:image/png;base64,${value}`;
           const imgH = 100; const imgW = 140; const imgX = (pageWidth - imgW) / 2;
           doc.addImage(String(imgBase64), 'PNG', imgX, yPos, imgW, imgH);
           yPos += imgH + 15;